@@ -0,0 +1,2771 @@
+//! Configuration for the sqs capability provider, taken from the values of a
+//! link definition.
+//!
+use std::collections::HashMap;
+
+use aws_sdk_sqs as sqs;
+use aws_types::region::Region;
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+use wasmbus_rpc::{
+    core::LinkDefinition,
+    error::{RpcError, RpcResult},
+};
+
+const CONFIG_QUEUE_NAME: &str = "queue_name";
+const CONFIG_QUEUE_NAMES: &str = "queue_names";
+const CONFIG_QUEUE_URL: &str = "queue_url";
+const CONFIG_QUEUE_ENDPOINTS: &str = "queue_endpoints";
+const CONFIG_AWS_REGION: &str = "aws_region";
+const CONFIG_ACCESS_KEY_ID: &str = "access_key_id";
+const CONFIG_SECRET_ACCESS_KEY: &str = "secret_access_key";
+const CONFIG_SESSION_TOKEN: &str = "session_token";
+const CONFIG_AWS_PROFILE: &str = "aws_profile";
+const CONFIG_CREDENTIAL_SOURCE: &str = "credential_source";
+const CONFIG_ASSUME_ROLE_ARN: &str = "assume_role_arn";
+const CONFIG_ASSUME_ROLE_SESSION_NAME: &str = "assume_role_session_name";
+const CONFIG_ASSUME_ROLE_EXTERNAL_ID: &str = "assume_role_external_id";
+const CONFIG_CONNECT_TIMEOUT_MS: &str = "connect_timeout_ms";
+const CONFIG_OPERATION_TIMEOUT_MS: &str = "operation_timeout_ms";
+const CONFIG_MAX_ATTEMPTS: &str = "max_attempts";
+const CONFIG_CREATE_QUEUE_IF_MISSING: &str = "create_queue_if_missing";
+const CONFIG_MESSAGE_AUTO_DELETE: &str = "message_auto_delete";
+const CONFIG_WAIT_TIME_SECONDS: &str = "wait_time_seconds";
+const CONFIG_MAX_NUMBER_OF_MESSAGES: &str = "max_number_of_messages";
+const CONFIG_CONTENT_BASED_DEDUPLICATION: &str = "content_based_deduplication";
+const CONFIG_DEDUP_FROM_BODY_HASH: &str = "dedup_from_body_hash";
+const CONFIG_SUBJECT_ROUTING: &str = "subject_routing";
+const CONFIG_BATCH_FLUSH_MS: &str = "batch_flush_ms";
+const CONFIG_VISIBILITY_TIMEOUT_SECONDS: &str = "visibility_timeout_seconds";
+const CONFIG_DISPATCH_TIMEOUT_MS: &str = "dispatch_timeout_ms";
+const CONFIG_ENDPOINT_URL: &str = "endpoint_url";
+const CONFIG_QUEUE_OWNER_ACCOUNT_ID: &str = "queue_owner_account_id";
+const CONFIG_RECEIVE_BACKOFF_MAX_SECONDS: &str = "receive_backoff_max_seconds";
+const CONFIG_IDLE_BACKOFF_MS: &str = "idle_backoff_ms";
+const CONFIG_IDLE_NOTIFY_POLLS: &str = "idle_notify_polls";
+const CONFIG_MESSAGE_TTL_SECONDS: &str = "message_ttl_seconds";
+const CONFIG_PUBLISH_RATE_LIMIT: &str = "publish_rate_limit";
+const CONFIG_RATE_LIMIT_BEHAVIOR: &str = "rate_limit_behavior";
+const CONFIG_RATE_LIMIT_WAIT_MS: &str = "rate_limit_wait_ms";
+const CONFIG_FAILOVER_QUEUE_NAME: &str = "failover_queue_name";
+const CONFIG_FAILOVER_REGION: &str = "failover_region";
+const CONFIG_FAILOVER_THRESHOLD: &str = "failover_threshold";
+const CONFIG_FAILOVER_PROBE_INTERVAL_MS: &str = "failover_probe_interval_ms";
+const CONFIG_PROPAGATE_TRACE_CONTEXT: &str = "propagate_trace_context";
+const CONFIG_DEAD_LETTER_QUEUE_NAME: &str = "dead_letter_queue_name";
+const CONFIG_MAX_RECEIVE_COUNT: &str = "max_receive_count";
+const CONFIG_MAX_PROCESSING_ATTEMPTS: &str = "max_processing_attempts";
+const CONFIG_MAX_CONCURRENT_HANDLERS: &str = "max_concurrent_handlers";
+const CONFIG_SUBSCRIBE_FILTER: &str = "subscribe_filter";
+const CONFIG_PUBLISH_ALLOW: &str = "publish_allow";
+const CONFIG_PUBLISH_DENY: &str = "publish_deny";
+const CONFIG_ENVELOPE_FORMAT: &str = "envelope_format";
+const CONFIG_CONSUMER_COUNT: &str = "consumer_count";
+const CONFIG_LARGE_PAYLOAD_BUCKET: &str = "large_payload_bucket";
+const CONFIG_LARGE_PAYLOAD_THRESHOLD: &str = "large_payload_threshold";
+const CONFIG_DELETE_FILTERED: &str = "delete_filtered";
+const CONFIG_MESSAGE_RETENTION_SECONDS: &str = "message_retention_seconds";
+const CONFIG_KMS_MASTER_KEY_ID: &str = "kms_master_key_id";
+const CONFIG_DEDUPLICATION_SCOPE: &str = "deduplication_scope";
+const CONFIG_FIFO_THROUGHPUT_LIMIT: &str = "fifo_throughput_limit";
+const CONFIG_KMS_DATA_KEY_REUSE_PERIOD: &str = "kms_data_key_reuse_period";
+const CONFIG_DELAY_SECONDS: &str = "delay_seconds";
+const CONFIG_BODY_ENCODING: &str = "body_encoding";
+const CONFIG_DELIVERY_MODE: &str = "delivery_mode";
+const CONFIG_UNWRAP_SNS_ENVELOPE: &str = "unwrap_sns_envelope";
+const CONFIG_ENABLE_SNS_PUBLISH: &str = "enable_sns_publish";
+const CONFIG_LOG_MESSAGE_BODIES: &str = "log_message_bodies";
+const CONFIG_LOG_BODY_PREVIEW_BYTES: &str = "log_body_preview_bytes";
+const CONFIG_LOG_BODY_REDACT_PATTERN: &str = "log_body_redact_pattern";
+const CONFIG_XRAY_TRACING: &str = "xray_tracing";
+const CONFIG_CACHE_QUEUE_URL: &str = "cache_queue_url";
+const CONFIG_QUEUE_TAGS: &str = "queue_tags";
+const CONFIG_RECONCILE_TAGS: &str = "reconcile_tags";
+const CONFIG_VISIBILITY_HEARTBEAT: &str = "visibility_heartbeat";
+const CONFIG_MAX_VISIBILITY_EXTENSION_SECONDS: &str = "max_visibility_extension_seconds";
+const CONFIG_DISPATCH_ERROR_POLICY: &str = "dispatch_error_policy";
+const CONFIG_QUEUE_NAME_PREFIX: &str = "queue_name_prefix";
+const CONFIG_PREFIX_REFRESH_SECONDS: &str = "prefix_refresh_seconds";
+const CONFIG_GROUP_ID_FROM: &str = "group_id_from";
+const CONFIG_DEFAULT_MESSAGE_GROUP_ID: &str = "default_message_group_id";
+const CONFIG_ALLOW_PURGE: &str = "allow_purge";
+const CONFIG_ALLOW_DLQ_REPLAY: &str = "allow_dlq_replay";
+const CONFIG_SHUTDOWN_DRAIN_TIMEOUT_MS: &str = "shutdown_drain_timeout_ms";
+const CONFIG_DRAIN_ON_DELETE_LINK: &str = "drain_on_delete_link";
+
+/// long-poll duration sqs waits before returning an empty receive (seconds).
+/// 20 is the maximum sqs allows and the cheapest setting for idle queues.
+const DEFAULT_WAIT_TIME_SECONDS: i32 = 20;
+
+/// how many messages a single receive_message may return (1-10)
+const DEFAULT_MAX_NUMBER_OF_MESSAGES: i32 = 1;
+/// longest the receive loop backs off between failed polls unless configured
+const DEFAULT_RECEIVE_BACKOFF_MAX_SECONDS: u64 = 30;
+/// handlers running at once per receive loop unless configured otherwise
+const DEFAULT_MAX_CONCURRENT_HANDLERS: usize = 1;
+/// receives before a message is moved to the dead-letter queue
+const DEFAULT_MAX_RECEIVE_COUNT: i32 = 5;
+/// sqs's own default message retention (4 days)
+const DEFAULT_MESSAGE_RETENTION_SECONDS: i32 = 345_600;
+/// sqs's own default delivery delay
+const DEFAULT_DELAY_SECONDS: i32 = 0;
+/// how long shutdown waits for in-flight work before force-cancelling
+const DEFAULT_SHUTDOWN_DRAIN_TIMEOUT_MS: u64 = 5_000;
+/// how long a blocking publish waits for a rate-limit token before giving up
+const DEFAULT_RATE_LIMIT_WAIT_MS: u64 = 1_000;
+/// consecutive primary-send failures before publishes fail over
+const DEFAULT_FAILOVER_THRESHOLD: u64 = 3;
+/// how often a failed-over link probes the primary to fail back
+const DEFAULT_FAILOVER_PROBE_INTERVAL_MS: u64 = 30_000;
+/// payloads over this many bytes are offloaded to s3 when a bucket is
+/// configured; the default is the sqs message size cap itself
+const DEFAULT_LARGE_PAYLOAD_THRESHOLD_BYTES: u64 = 262_144;
+/// how much of a message body a debug preview shows
+const DEFAULT_LOG_BODY_PREVIEW_BYTES: usize = 256;
+/// cap on how long the visibility heartbeat keeps extending one message
+const DEFAULT_MAX_VISIBILITY_EXTENSION_SECONDS: u64 = 3_600;
+/// visibility timeout sqs applies when neither the link nor the queue set one
+const DEFAULT_SQS_VISIBILITY_TIMEOUT_SECONDS: i32 = 30;
+/// how often prefix discovery re-lists queues for new matches
+const DEFAULT_PREFIX_REFRESH_SECONDS: u64 = 300;
+/// upper bound on parallel poll loops per queue; past this the provider is
+/// more likely starved of connections than of consumers
+const MAX_CONSUMER_COUNT: u64 = 64;
+/// session name stamped on assume-role sts calls unless configured
+const DEFAULT_ASSUME_ROLE_SESSION_NAME: &str = "wasmcloud-sqs-provider";
+
+/// Which credentials provider [`SQSConfig::configure_aws`] will install
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub(crate) enum CredentialsSource {
+    /// access key id and secret straight off the link
+    StaticKeys,
+    /// a named profile from the shared credentials file
+    Profile(String),
+    /// the sdk's default environment chain
+    Environment,
+    /// environment variables only, never falling through to imds or files
+    EnvOnly,
+    /// the ec2 instance metadata service
+    Imds,
+    /// the ecs container credentials endpoint
+    Ecs,
+}
+
+/// Parse an explicit `credential_source` selection. `static` and `profile`
+/// need their companion settings to mean anything, so their absence is a
+/// link error rather than a silent fallthrough to another source.
+fn parse_credential_source(
+    value: &str,
+    values: &HashMap<String, String>,
+) -> RpcResult<CredentialsSource> {
+    match value {
+        "env" => Ok(CredentialsSource::EnvOnly),
+        "imds" => Ok(CredentialsSource::Imds),
+        "ecs" => Ok(CredentialsSource::Ecs),
+        "default_chain" => Ok(CredentialsSource::Environment),
+        "static" => {
+            if values.contains_key(CONFIG_ACCESS_KEY_ID)
+                && values.contains_key(CONFIG_SECRET_ACCESS_KEY)
+            {
+                Ok(CredentialsSource::StaticKeys)
+            } else {
+                Err(RpcError::ProviderInit(format!(
+                    "{}=static requires '{}' and '{}'",
+                    CONFIG_CREDENTIAL_SOURCE, CONFIG_ACCESS_KEY_ID, CONFIG_SECRET_ACCESS_KEY
+                )))
+            }
+        }
+        "profile" => match values.get(CONFIG_AWS_PROFILE) {
+            Some(profile) => Ok(CredentialsSource::Profile(profile.clone())),
+            None => Err(RpcError::ProviderInit(format!(
+                "{}=profile requires '{}'",
+                CONFIG_CREDENTIAL_SOURCE, CONFIG_AWS_PROFILE
+            ))),
+        },
+        _ => Err(RpcError::ProviderInit(format!(
+            "invalid {} '{}': must be one of env, static, profile, imds, ecs, default_chain",
+            CONFIG_CREDENTIAL_SOURCE, value
+        ))),
+    }
+}
+
+/// When a received message is acknowledged relative to its dispatch.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub(crate) enum DeliveryMode {
+    /// delete after a successful dispatch (the default): a crashed handler
+    /// sees the message again after the visibility timeout
+    #[default]
+    AtLeastOnce,
+    /// delete before dispatch: a crashed handler loses the message instead
+    /// of reprocessing it, for actors that can't tolerate duplicates
+    AtMostOnce,
+}
+
+/// How messages are represented on the sqs wire
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub(crate) enum EnvelopeFormat {
+    /// body on the sqs body, metadata in message attributes (the default)
+    #[default]
+    Native,
+    /// the whole message (subject, reply_to, base64 body) as one json
+    /// document in the sqs body, for actors that round-trip rich metadata
+    /// without touching sqs attributes
+    Json,
+}
+
+/// Parse an `envelope_format` link value
+fn parse_envelope_format(value: &str) -> RpcResult<EnvelopeFormat> {
+    match value {
+        "native" => Ok(EnvelopeFormat::Native),
+        "json" => Ok(EnvelopeFormat::Json),
+        _ => Err(RpcError::ProviderInit(format!(
+            "invalid {} '{}': must be 'native' or 'json'",
+            CONFIG_ENVELOPE_FORMAT, value
+        ))),
+    }
+}
+
+/// What a publish does when the rate limiter has no token for it
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub(crate) enum RateLimitBehavior {
+    /// wait (up to the configured bound) for a token
+    #[default]
+    Block,
+    /// fail the publish immediately so the caller can shed load itself
+    Error,
+}
+
+/// Parse a `rate_limit_behavior` link value
+fn parse_rate_limit_behavior(value: &str) -> RpcResult<RateLimitBehavior> {
+    match value {
+        "block" => Ok(RateLimitBehavior::Block),
+        "error" => Ok(RateLimitBehavior::Error),
+        _ => Err(RpcError::ProviderInit(format!(
+            "invalid '{}' value \"{}\": expected block or error",
+            CONFIG_RATE_LIMIT_BEHAVIOR, value
+        ))),
+    }
+}
+
+/// Parse a `delivery_mode` link value
+fn parse_delivery_mode(value: &str) -> RpcResult<DeliveryMode> {
+    match value {
+        "at_least_once" => Ok(DeliveryMode::AtLeastOnce),
+        "at_most_once" => Ok(DeliveryMode::AtMostOnce),
+        _ => Err(RpcError::ProviderInit(format!(
+            "link value '{}' must be at_least_once or at_most_once, found \"{}\"",
+            CONFIG_DELIVERY_MODE, value
+        ))),
+    }
+}
+
+/// What happens to a received message whose dispatch to the actor fails.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub(crate) enum DispatchErrorPolicy {
+    /// leave the message alone; sqs redelivers it after the visibility
+    /// timeout expires (the default)
+    #[default]
+    Redeliver,
+    /// zero the message's visibility timeout so redelivery happens on the
+    /// next poll instead of after the full timeout
+    FastRetry,
+    /// delete the message: a handler error discards it permanently, for
+    /// actors that would otherwise hot-loop on a poison message
+    Drop,
+}
+
+/// Parse a `dispatch_error_policy` link value
+fn parse_dispatch_error_policy(value: &str) -> RpcResult<DispatchErrorPolicy> {
+    match value {
+        "redeliver" => Ok(DispatchErrorPolicy::Redeliver),
+        "fast_retry" => Ok(DispatchErrorPolicy::FastRetry),
+        "drop" => Ok(DispatchErrorPolicy::Drop),
+        _ => Err(RpcError::ProviderInit(format!(
+            "link value '{}' must be redeliver, fast_retry or drop, found \"{}\"",
+            CONFIG_DISPATCH_ERROR_POLICY, value
+        ))),
+    }
+}
+
+/// Where the message group id for a fifo publish comes from when the
+/// message does not carry one.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub(crate) enum GroupIdStrategy {
+    /// only the explicit attribute, falling back to the configured default
+    /// group id if one is set (the default)
+    #[default]
+    Attribute,
+    /// the publish subject, so each routing subject is its own ordered stream
+    Subject,
+    /// always the configured default group id
+    Static,
+    /// a hash of the message body, spreading unrelated messages across groups
+    HashOfBody,
+}
+
+/// Parse a `group_id_from` link value
+fn parse_group_id_strategy(value: &str) -> RpcResult<GroupIdStrategy> {
+    match value {
+        "attribute" => Ok(GroupIdStrategy::Attribute),
+        "subject" => Ok(GroupIdStrategy::Subject),
+        "static" => Ok(GroupIdStrategy::Static),
+        "hash_of_body" => Ok(GroupIdStrategy::HashOfBody),
+        _ => Err(RpcError::ProviderInit(format!(
+            "link value '{}' must be attribute, subject, static or hash_of_body, found \"{}\"",
+            CONFIG_GROUP_ID_FROM, value
+        ))),
+    }
+}
+
+/// How publish payloads are turned into the text bodies sqs requires.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub(crate) enum BodyEncoding {
+    /// send the payload as-is and error when it is not valid utf-8
+    Utf8,
+    /// always base64-encode, marking the message for the receive side
+    Base64,
+    /// utf-8 when the payload allows it, base64 otherwise (the default)
+    #[default]
+    Auto,
+}
+
+/// Parse a `body_encoding` link value
+fn parse_body_encoding(value: &str) -> RpcResult<BodyEncoding> {
+    match value {
+        "utf8" => Ok(BodyEncoding::Utf8),
+        "base64" => Ok(BodyEncoding::Base64),
+        "auto" => Ok(BodyEncoding::Auto),
+        _ => Err(RpcError::ProviderInit(format!(
+            "link value '{}' must be utf8, base64 or auto, found \"{}\"",
+            CONFIG_BODY_ENCODING, value
+        ))),
+    }
+}
+
+/// What a queue binding is used for. An actor publishing to one queue while
+/// consuming from others lists each with an explicit role; a bare queue name
+/// keeps doing both.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub(crate) enum QueueRole {
+    Publish,
+    Subscribe,
+    Both,
+}
+
+/// One queue an actor's link is bound to
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub(crate) struct QueueBinding {
+    pub(crate) name: String,
+    pub(crate) role: QueueRole,
+    /// relative polling weight; a heavier queue pulls proportionally more
+    /// messages per receive (each binding still polls independently, so a
+    /// busy queue can never starve the others)
+    #[serde(default = "default_queue_weight")]
+    pub(crate) weight: u32,
+    /// endpoint this one queue lives behind, for links spanning several
+    /// sqs-compatible endpoints; unset falls back to the link's endpoint_url
+    #[serde(default)]
+    pub(crate) endpoint_url: Option<String>,
+}
+
+fn default_queue_weight() -> u32 {
+    1
+}
+
+impl QueueBinding {
+    pub(crate) fn publishes(&self) -> bool {
+        matches!(self.role, QueueRole::Publish | QueueRole::Both)
+    }
+
+    pub(crate) fn subscribes(&self) -> bool {
+        matches!(self.role, QueueRole::Subscribe | QueueRole::Both)
+    }
+}
+
+/// Parse one `queue_names` entry of the form `name`, `name:role` or
+/// `name:role:weight`, where the role is `publish`, `subscribe` or `both`
+/// (the default) and the weight is a positive polling weight (default 1)
+fn parse_binding(entry: &str) -> RpcResult<QueueBinding> {
+    let (rest, weight) = match entry.split_once(':').map(|(_, tail)| tail) {
+        Some(tail) => match tail.split_once(':') {
+            Some((_, raw_weight)) => {
+                let weight = raw_weight.trim().parse::<u32>().ok().filter(|w| *w >= 1);
+                match weight {
+                    Some(weight) => (entry.rsplit_once(':').map(|(head, _)| head).unwrap_or(entry), weight),
+                    None => {
+                        return Err(RpcError::ProviderInit(format!(
+                            "invalid queue weight '{}' in '{}': expected a positive integer",
+                            raw_weight, CONFIG_QUEUE_NAMES
+                        )))
+                    }
+                }
+            }
+            None => (entry, default_queue_weight()),
+        },
+        None => (entry, default_queue_weight()),
+    };
+    let (name, role) = match rest.split_once(':') {
+        None => (rest, QueueRole::Both),
+        Some((name, "publish")) => (name, QueueRole::Publish),
+        Some((name, "subscribe")) => (name, QueueRole::Subscribe),
+        Some((name, "both")) => (name, QueueRole::Both),
+        Some((_, role)) => {
+            return Err(RpcError::ProviderInit(format!(
+                "invalid queue role '{}' in '{}': expected publish, subscribe or both",
+                role, CONFIG_QUEUE_NAMES
+            )))
+        }
+    };
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(RpcError::ProviderInit(format!(
+            "empty queue name in '{}'",
+            CONFIG_QUEUE_NAMES
+        )));
+    }
+    Ok(QueueBinding {
+        name: name.to_string(),
+        role,
+        weight,
+        endpoint_url: None,
+    })
+}
+
+/// Attach `queue_endpoints` overrides (comma-separated `name=url` pairs) to
+/// the bindings they name; naming an unbound queue is a misconfiguration
+fn attach_binding_endpoints(bindings: &mut [QueueBinding], value: &str) -> RpcResult<()> {
+    for pair in value.split(',').filter(|p| !p.trim().is_empty()) {
+        let (name, url) = pair.split_once('=').ok_or_else(|| {
+            RpcError::ProviderInit(format!(
+                "link value '{}' entry \"{}\" is not of the form queue=url",
+                CONFIG_QUEUE_ENDPOINTS, pair
+            ))
+        })?;
+        let (name, url) = (name.trim(), url.trim());
+        let binding = bindings
+            .iter_mut()
+            .find(|b| b.name == name)
+            .ok_or_else(|| {
+                RpcError::ProviderInit(format!(
+                    "link value '{}' names queue \"{}\" which is not bound on this link",
+                    CONFIG_QUEUE_ENDPOINTS, name
+                ))
+            })?;
+        binding.endpoint_url = Some(url.to_string());
+    }
+    Ok(())
+}
+
+/// Configuration for an sqs client, per link.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[allow(clippy::upper_case_acronyms)]
+pub(crate) struct SQSConfig {
+    /// name of the queue the linked actor publishes to and receives from
+    pub(crate) queue_name: String,
+    /// every queue this link is bound to, with its role; always holds at
+    /// least one entry after a successful parse
+    #[serde(default)]
+    pub(crate) bindings: Vec<QueueBinding>,
+    /// AWS region the queue lives in. Falls back to the provider's environment when unset
+    #[serde(default)]
+    pub(crate) aws_region: Option<String>,
+    /// AWS_ACCESS_KEY_ID, can be specified from environment
+    #[serde(default)]
+    pub(crate) access_key_id: Option<String>,
+    /// AWS_SECRET_ACCESS_KEY, can be specified from environment
+    #[serde(default)]
+    pub(crate) secret_access_key: Option<String>,
+    /// optional session token for temporary credentials
+    #[serde(default)]
+    pub(crate) session_token: Option<String>,
+    /// explicitly selected credentials provider; when unset the usual
+    /// precedence (static keys, profile, default chain) applies. lets links
+    /// on ec2/ecs skip slow or disabled imds lookups when they know where
+    /// their credentials come from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) credential_source: Option<CredentialsSource>,
+    /// named profile from the shared aws credentials file; static keys on the
+    /// link take precedence over it
+    #[serde(default)]
+    pub(crate) aws_profile: Option<String>,
+    /// iam role to assume via sts on top of the base credentials, for
+    /// cross-account access
+    #[serde(default)]
+    pub(crate) assume_role_arn: Option<String>,
+    /// session name for the assumed role; defaults to the provider's own
+    #[serde(default)]
+    pub(crate) assume_role_session_name: Option<String>,
+    /// external id required by some cross-account trust policies
+    #[serde(default)]
+    pub(crate) assume_role_external_id: Option<String>,
+    /// limit on establishing a socket connection to sqs; None uses the sdk's
+    /// default
+    #[serde(default)]
+    pub(crate) connect_timeout_ms: Option<u64>,
+    /// limit on a whole sqs operation including retries; None uses the sdk's
+    /// default. Must exceed wait_time_seconds or long polls will always
+    /// time out.
+    #[serde(default)]
+    pub(crate) operation_timeout_ms: Option<u64>,
+    /// total attempts (first try plus retries) per sqs operation; None uses
+    /// the sdk's default
+    #[serde(default)]
+    pub(crate) max_attempts: Option<u32>,
+    /// create the queue at link time if it does not already exist
+    #[serde(default)]
+    pub(crate) create_queue_if_missing: bool,
+    /// delete messages from the queue once they have been received
+    #[serde(default)]
+    pub(crate) message_auto_delete: bool,
+    /// seconds each receive_message long-polls before returning empty (0-20, default 20)
+    #[serde(default = "default_wait_time_seconds")]
+    pub(crate) wait_time_seconds: i32,
+    /// messages pulled per receive_message call (1-10, default 1)
+    #[serde(default = "default_max_number_of_messages")]
+    pub(crate) max_number_of_messages: i32,
+    /// set when the linked fifo queue has content-based deduplication enabled,
+    /// making an explicit deduplication id optional on publish
+    #[serde(default)]
+    pub(crate) content_based_deduplication: bool,
+    /// derive the fifo deduplication id from a sha-256 of the body, so
+    /// identical payloads collapse within the dedup window without actors
+    /// managing ids
+    #[serde(default)]
+    pub(crate) dedup_from_body_hash: bool,
+    /// treat a publish's subject as the name of the destination queue instead
+    /// of always sending to the configured queue
+    #[serde(default)]
+    pub(crate) subject_routing: bool,
+    /// when non-zero, publishes are coalesced into send_message_batch calls
+    /// flushed at this interval (or sooner when a batch fills up)
+    #[serde(default)]
+    pub(crate) batch_flush_ms: u64,
+    /// how long a received message stays hidden from other consumers before
+    /// sqs redelivers it; handlers slower than this will see the same message
+    /// twice. None uses the queue's own default.
+    #[serde(default)]
+    pub(crate) visibility_timeout_seconds: Option<i32>,
+    /// longest a single handle_message dispatch may run before the provider
+    /// abandons it and leaves the message for redelivery; zero (the default)
+    /// waits forever
+    #[serde(default)]
+    pub(crate) dispatch_timeout_ms: u64,
+    /// point the client at a non-AWS sqs endpoint, e.g. a local stack on
+    /// http://localhost:4566, instead of the region's real endpoint
+    #[serde(default)]
+    pub(crate) endpoint_url: Option<String>,
+    /// aws account id owning the queue, for queues shared across accounts;
+    /// passed to get_queue_url as QueueOwnerAWSAccountId
+    #[serde(default)]
+    pub(crate) queue_owner_account_id: Option<String>,
+    /// kms key used to encrypt queues created by this provider (SSE-KMS);
+    /// an alias, key id or full arn
+    #[serde(default)]
+    pub(crate) kms_master_key_id: Option<String>,
+    /// DeduplicationScope for created fifo queues: `queue` (the default) or
+    /// `messageGroup` for high-throughput fifo
+    #[serde(default)]
+    pub(crate) deduplication_scope: Option<String>,
+    /// FifoThroughputLimit for created fifo queues: `perQueue` (the default)
+    /// or `perMessageGroupId` for high-throughput fifo
+    #[serde(default)]
+    pub(crate) fifo_throughput_limit: Option<String>,
+    /// seconds sqs may reuse a kms data key before asking kms for a new one
+    /// (60-86400); only meaningful alongside kms_master_key_id
+    #[serde(default)]
+    pub(crate) kms_data_key_reuse_period: Option<i32>,
+    /// longest the receive loop will back off between failed polls
+    #[serde(default = "default_receive_backoff_max_seconds")]
+    pub(crate) receive_backoff_max_seconds: u64,
+    /// most publishes per second this link allows, token-bucket style with a
+    /// one-second burst; unset means unlimited
+    #[serde(default)]
+    pub(crate) publish_rate_limit: Option<u32>,
+    /// whether a throttled publish waits for a token or fails immediately
+    #[serde(default)]
+    pub(crate) rate_limit_behavior: RateLimitBehavior,
+    /// longest a blocking publish waits for a token
+    #[serde(default = "default_rate_limit_wait_ms")]
+    pub(crate) rate_limit_wait_ms: u64,
+    /// queue publishes fail over to after failover_threshold consecutive
+    /// primary failures; lives in failover_region when that is set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) failover_queue_name: Option<String>,
+    /// region of the failover queue; the primary's region when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) failover_region: Option<String>,
+    /// consecutive primary failures that open the failover circuit
+    #[serde(default = "default_failover_threshold")]
+    pub(crate) failover_threshold: u64,
+    /// how often a failed-over link retries the primary to fail back
+    #[serde(default = "default_failover_probe_interval_ms")]
+    pub(crate) failover_probe_interval_ms: u64,
+    /// pause after a poll that returned nothing, for cost-sensitive links
+    /// with many mostly-idle queues; zero (the default) re-polls immediately
+    #[serde(default)]
+    pub(crate) idle_backoff_ms: u64,
+    /// dispatch a synthetic queue-idle event to the actor after this many
+    /// consecutive empty polls; zero (the default) never notifies
+    #[serde(default)]
+    pub(crate) idle_notify_polls: u64,
+    /// stamp each publish with an expires-at attribute this many seconds in
+    /// the future; the receive loop drops stamped messages past their stamp
+    /// instead of dispatching them. Zero (the default) disables the ttl
+    #[serde(default)]
+    pub(crate) message_ttl_seconds: u64,
+    /// carry the w3c trace context across the sqs hop in message attributes
+    #[serde(default)]
+    pub(crate) propagate_trace_context: bool,
+    /// how many received messages may be dispatched to the actor at once; the
+    /// receive loop stops pulling more work while the actor is saturated
+    #[serde(default = "default_max_concurrent_handlers")]
+    pub(crate) max_concurrent_handlers: usize,
+    /// parallel poll loops spawned per subscribed queue; each holds its own
+    /// receive cursor, multiplying receive/dispatch throughput
+    #[serde(default = "default_consumer_count")]
+    pub(crate) consumer_count: u32,
+    /// how message bodies and metadata are laid out on the wire
+    #[serde(default)]
+    pub(crate) envelope_format: EnvelopeFormat,
+    /// subject patterns this link may publish to; empty allows everything
+    /// not denied. patterns may use '*' as a wildcard
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) publish_allow: Vec<String>,
+    /// subject patterns this link may never publish to; checked before the
+    /// allowlist
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) publish_deny: Vec<String>,
+    /// attribute=value pairs a received message must all carry to be
+    /// dispatched; emulates topic filtering on a queue shared by several
+    /// message types. Empty means dispatch everything.
+    #[serde(default)]
+    pub(crate) subscribe_filter: HashMap<String, String>,
+    /// delete messages the subscribe filter rejects instead of leaving them
+    /// for another consumer of the shared queue
+    #[serde(default)]
+    pub(crate) delete_filtered: bool,
+    /// receives after which the poll loop itself quarantines a message to the
+    /// dead-letter queue instead of redispatching it; a client-side complement
+    /// to the native redrive policy
+    #[serde(default)]
+    pub(crate) max_processing_attempts: Option<u32>,
+    /// when set, the primary queue gets a redrive policy pointing at this
+    /// queue, which is created if it does not exist
+    #[serde(default)]
+    pub(crate) dead_letter_queue_name: Option<String>,
+    /// receives before a message is moved to the dead-letter queue
+    #[serde(default = "default_max_receive_count")]
+    pub(crate) max_receive_count: i32,
+    /// how long created queues retain messages; only applied when the
+    /// provider creates the queue
+    #[serde(default = "default_message_retention_seconds")]
+    pub(crate) message_retention_seconds: i32,
+    /// how publish payloads are encoded into sqs message bodies
+    #[serde(default)]
+    pub(crate) body_encoding: BodyEncoding,
+    /// whether messages are acknowledged before or after dispatch
+    #[serde(default)]
+    pub(crate) delivery_mode: DeliveryMode,
+    /// unwrap sns notification envelopes on receive, dispatching only the
+    /// inner message; for queues subscribed to an sns topic
+    #[serde(default)]
+    pub(crate) unwrap_sns_envelope: bool,
+    /// allow publishes whose subject is an sns topic arn to go to sns
+    /// instead of sqs, for fan-out patterns
+    #[serde(default)]
+    pub(crate) enable_sns_publish: bool,
+    /// s3 bucket oversized payloads are stored in, extended-client style: the
+    /// queue carries a pointer and the body lives in the bucket. Unset
+    /// disables offloading and oversized publishes fail the size check.
+    #[serde(default)]
+    pub(crate) large_payload_bucket: Option<String>,
+    /// payload size in bytes above which a publish is offloaded
+    #[serde(default = "default_large_payload_threshold")]
+    pub(crate) large_payload_threshold: u64,
+    /// log a truncated preview of message bodies (default off: compliance
+    /// setups must never see payloads in logs)
+    #[serde(default)]
+    pub(crate) log_message_bodies: bool,
+    /// how many bytes of the body a preview shows
+    #[serde(default = "default_log_body_preview_bytes")]
+    pub(crate) log_body_preview_bytes: usize,
+    /// regex whose matches are redacted out of body previews
+    #[serde(default)]
+    pub(crate) log_body_redact_pattern: Option<String>,
+    /// stamp the x-ray trace header system attribute on publishes and
+    /// surface it from received messages
+    #[serde(default)]
+    pub(crate) xray_tracing: bool,
+    /// cache resolved queue urls (the default); turn off in environments
+    /// where queues are recreated often enough that cached urls go stale
+    #[serde(default = "default_true")]
+    pub(crate) cache_queue_url: bool,
+    /// tags applied to queues this link creates, for cost allocation
+    #[serde(default)]
+    pub(crate) queue_tags: HashMap<String, String>,
+    /// also apply the configured tags to queues that already existed
+    #[serde(default)]
+    pub(crate) reconcile_tags: bool,
+    /// periodically re-extend the visibility timeout of in-flight messages
+    /// so handlers that outrun the timeout don't cause duplicate processing
+    #[serde(default)]
+    pub(crate) visibility_heartbeat: bool,
+    /// cap, in seconds, on how long the heartbeat keeps one message hidden
+    #[serde(default = "default_max_visibility_extension_seconds")]
+    pub(crate) max_visibility_extension_seconds: u64,
+    /// subscribe to every queue whose name starts with this prefix, in
+    /// addition to the explicitly bound queues
+    #[serde(default)]
+    pub(crate) queue_name_prefix: Option<String>,
+    /// how often, in seconds, prefix discovery re-lists queues so newly
+    /// created matches get picked up
+    #[serde(default = "default_prefix_refresh_seconds")]
+    pub(crate) prefix_refresh_seconds: u64,
+    /// what to do with a message whose dispatch fails
+    #[serde(default)]
+    pub(crate) dispatch_error_policy: DispatchErrorPolicy,
+    /// how a fifo publish without an explicit group id attribute derives one
+    #[serde(default)]
+    pub(crate) group_id_from: GroupIdStrategy,
+    /// group id used by the static strategy, and the fallback for the
+    /// attribute strategy
+    #[serde(default)]
+    pub(crate) default_message_group_id: Option<String>,
+    /// allow the __control/purge subject to purge the linked queue; off by
+    /// default so production queues can't be emptied by accident
+    #[serde(default)]
+    pub(crate) allow_purge: bool,
+    /// permit the dlq replay control subject to move quarantined messages
+    /// back onto the main queue; off by default like allow_purge
+    #[serde(default)]
+    pub(crate) allow_dlq_replay: bool,
+    /// how long shutdown waits for this link's in-flight polls and handlers
+    /// to finish before force-cancelling them
+    #[serde(default = "default_shutdown_drain_timeout_ms")]
+    pub(crate) shutdown_drain_timeout_ms: u64,
+    /// on delete_link, wait (up to the drain timeout) for messages already
+    /// received to finish dispatching instead of abandoning their leases
+    #[serde(default)]
+    pub(crate) drain_on_delete_link: bool,
+    /// delivery delay for created queues; only applied when the provider
+    /// creates the queue
+    #[serde(default = "default_delay_seconds")]
+    pub(crate) delay_seconds: i32,
+}
+
+fn default_wait_time_seconds() -> i32 {
+    DEFAULT_WAIT_TIME_SECONDS
+}
+
+fn default_large_payload_threshold() -> u64 {
+    DEFAULT_LARGE_PAYLOAD_THRESHOLD_BYTES
+}
+
+/// A threshold above the sqs size cap would never trigger, leaving oversized
+/// publishes to fail the size check the bucket was configured to avoid
+fn validate_large_payload_threshold(value: u64) -> RpcResult<u64> {
+    if value == 0 || value > DEFAULT_LARGE_PAYLOAD_THRESHOLD_BYTES {
+        return Err(RpcError::ProviderInit(format!(
+            "link value '{}' must be between 1 and {}",
+            CONFIG_LARGE_PAYLOAD_THRESHOLD, DEFAULT_LARGE_PAYLOAD_THRESHOLD_BYTES
+        )));
+    }
+    Ok(value)
+}
+
+fn default_rate_limit_wait_ms() -> u64 {
+    DEFAULT_RATE_LIMIT_WAIT_MS
+}
+
+fn default_failover_threshold() -> u64 {
+    DEFAULT_FAILOVER_THRESHOLD
+}
+
+fn default_failover_probe_interval_ms() -> u64 {
+    DEFAULT_FAILOVER_PROBE_INTERVAL_MS
+}
+
+fn default_log_body_preview_bytes() -> usize {
+    DEFAULT_LOG_BODY_PREVIEW_BYTES
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_max_visibility_extension_seconds() -> u64 {
+    DEFAULT_MAX_VISIBILITY_EXTENSION_SECONDS
+}
+
+fn default_prefix_refresh_seconds() -> u64 {
+    DEFAULT_PREFIX_REFRESH_SECONDS
+}
+
+fn default_receive_backoff_max_seconds() -> u64 {
+    DEFAULT_RECEIVE_BACKOFF_MAX_SECONDS
+}
+
+fn default_max_receive_count() -> i32 {
+    DEFAULT_MAX_RECEIVE_COUNT
+}
+
+fn default_message_retention_seconds() -> i32 {
+    DEFAULT_MESSAGE_RETENTION_SECONDS
+}
+
+fn default_delay_seconds() -> i32 {
+    DEFAULT_DELAY_SECONDS
+}
+
+fn default_max_number_of_messages() -> i32 {
+    DEFAULT_MAX_NUMBER_OF_MESSAGES
+}
+
+fn default_max_concurrent_handlers() -> usize {
+    DEFAULT_MAX_CONCURRENT_HANDLERS
+}
+
+fn default_consumer_count() -> u32 {
+    1
+}
+
+fn default_shutdown_drain_timeout_ms() -> u64 {
+    DEFAULT_SHUTDOWN_DRAIN_TIMEOUT_MS
+}
+
+impl Default for SQSConfig {
+    fn default() -> SQSConfig {
+        SQSConfig {
+            queue_name: String::default(),
+            bindings: Vec::new(),
+            aws_region: None,
+            access_key_id: None,
+            secret_access_key: None,
+            session_token: None,
+            aws_profile: None,
+            credential_source: None,
+            assume_role_arn: None,
+            assume_role_session_name: None,
+            assume_role_external_id: None,
+            connect_timeout_ms: None,
+            operation_timeout_ms: None,
+            max_attempts: None,
+            create_queue_if_missing: false,
+            message_auto_delete: false,
+            wait_time_seconds: DEFAULT_WAIT_TIME_SECONDS,
+            max_number_of_messages: DEFAULT_MAX_NUMBER_OF_MESSAGES,
+            content_based_deduplication: false,
+            dedup_from_body_hash: false,
+            subject_routing: false,
+            batch_flush_ms: 0,
+            visibility_timeout_seconds: None,
+            dispatch_timeout_ms: 0,
+            endpoint_url: None,
+            queue_owner_account_id: None,
+            kms_master_key_id: None,
+            deduplication_scope: None,
+            fifo_throughput_limit: None,
+            kms_data_key_reuse_period: None,
+            receive_backoff_max_seconds: DEFAULT_RECEIVE_BACKOFF_MAX_SECONDS,
+            idle_backoff_ms: 0,
+            idle_notify_polls: 0,
+            message_ttl_seconds: 0,
+            publish_rate_limit: None,
+            rate_limit_behavior: RateLimitBehavior::default(),
+            rate_limit_wait_ms: DEFAULT_RATE_LIMIT_WAIT_MS,
+            failover_queue_name: None,
+            failover_region: None,
+            failover_threshold: DEFAULT_FAILOVER_THRESHOLD,
+            failover_probe_interval_ms: DEFAULT_FAILOVER_PROBE_INTERVAL_MS,
+            propagate_trace_context: false,
+            dead_letter_queue_name: None,
+            max_concurrent_handlers: DEFAULT_MAX_CONCURRENT_HANDLERS,
+            consumer_count: 1,
+            subscribe_filter: HashMap::default(),
+            envelope_format: EnvelopeFormat::default(),
+            publish_allow: Vec::default(),
+            publish_deny: Vec::default(),
+            delete_filtered: false,
+            max_processing_attempts: None,
+            max_receive_count: DEFAULT_MAX_RECEIVE_COUNT,
+            message_retention_seconds: DEFAULT_MESSAGE_RETENTION_SECONDS,
+            body_encoding: BodyEncoding::default(),
+            delivery_mode: DeliveryMode::default(),
+            unwrap_sns_envelope: false,
+            enable_sns_publish: false,
+            large_payload_bucket: None,
+            large_payload_threshold: DEFAULT_LARGE_PAYLOAD_THRESHOLD_BYTES,
+            log_message_bodies: false,
+            log_body_preview_bytes: DEFAULT_LOG_BODY_PREVIEW_BYTES,
+            log_body_redact_pattern: None,
+            xray_tracing: false,
+            cache_queue_url: true,
+            queue_tags: HashMap::default(),
+            reconcile_tags: false,
+            visibility_heartbeat: false,
+            max_visibility_extension_seconds: DEFAULT_MAX_VISIBILITY_EXTENSION_SECONDS,
+            queue_name_prefix: None,
+            prefix_refresh_seconds: DEFAULT_PREFIX_REFRESH_SECONDS,
+            dispatch_error_policy: DispatchErrorPolicy::default(),
+            group_id_from: GroupIdStrategy::default(),
+            default_message_group_id: None,
+            allow_purge: false,
+            allow_dlq_replay: false,
+            shutdown_drain_timeout_ms: DEFAULT_SHUTDOWN_DRAIN_TIMEOUT_MS,
+            drain_on_delete_link: false,
+            delay_seconds: DEFAULT_DELAY_SECONDS,
+        }
+    }
+}
+
+impl SQSConfig {
+    /// initialize from the values of a link definition
+    pub(crate) fn from_link(ld: &LinkDefinition) -> RpcResult<SQSConfig> {
+        let values = &ld.values;
+        // an explicit queue_url bypasses resolution and discovery outright,
+        // for air-gapped or mocked environments where get_queue_url and
+        // list_queues must never be called
+        let queue_url_override = validate_queue_url_override(values)?;
+        // a link either names several queues with roles, or a single queue
+        // used for everything
+        let bindings = match get_opt(values, CONFIG_QUEUE_NAMES) {
+            Some(entries) => entries
+                .split(',')
+                .map(parse_binding)
+                .collect::<RpcResult<Vec<QueueBinding>>>()?,
+            None => Vec::new(),
+        };
+        let queue_name = if let Some(url) = &queue_url_override {
+            url.clone()
+        } else {
+            match values
+            .get(CONFIG_QUEUE_NAME)
+            .map(|v| v.trim().to_string())
+            .filter(|v| !v.is_empty())
+        {
+            Some(name) => name,
+            // with explicit bindings, the primary queue is the first one
+            // that publishes (or just the first, for subscribe-only links)
+            None => bindings
+                .iter()
+                .find(|b| b.publishes())
+                .or_else(|| bindings.first())
+                .map(|b| b.name.clone())
+                .ok_or_else(|| {
+                    RpcError::ProviderInit(format!(
+                        "missing link value '{}' for actor {}: the link must name the sqs queue the actor uses",
+                        CONFIG_QUEUE_NAME, ld.actor_id
+                    ))
+                })?,
+            }
+        };
+        let mut bindings = if bindings.is_empty() {
+            vec![QueueBinding {
+                name: queue_name.clone(),
+                role: QueueRole::Both,
+                weight: default_queue_weight(),
+                endpoint_url: None,
+            }]
+        } else {
+            bindings
+        };
+        if let Some(endpoints) = get_opt(values, CONFIG_QUEUE_ENDPOINTS) {
+            attach_binding_endpoints(&mut bindings, &endpoints)?;
+        }
+        let config = SQSConfig {
+            queue_name,
+            bindings,
+            aws_region: get_opt(values, CONFIG_AWS_REGION),
+            access_key_id: get_opt(values, CONFIG_ACCESS_KEY_ID),
+            secret_access_key: get_opt(values, CONFIG_SECRET_ACCESS_KEY),
+            session_token: get_opt(values, CONFIG_SESSION_TOKEN),
+            aws_profile: get_opt(values, CONFIG_AWS_PROFILE),
+            credential_source: get_opt(values, CONFIG_CREDENTIAL_SOURCE)
+                .map(|v| parse_credential_source(&v, values))
+                .transpose()?,
+            assume_role_arn: get_opt(values, CONFIG_ASSUME_ROLE_ARN),
+            assume_role_session_name: get_opt(values, CONFIG_ASSUME_ROLE_SESSION_NAME),
+            assume_role_external_id: get_opt(values, CONFIG_ASSUME_ROLE_EXTERNAL_ID),
+            connect_timeout_ms: get_u64(values, CONFIG_CONNECT_TIMEOUT_MS)?
+                .map(|ms| validate_positive(CONFIG_CONNECT_TIMEOUT_MS, ms))
+                .transpose()?,
+            operation_timeout_ms: get_u64(values, CONFIG_OPERATION_TIMEOUT_MS)?
+                .map(|ms| {
+                    validate_operation_timeout_ms(
+                        validate_positive(CONFIG_OPERATION_TIMEOUT_MS, ms)?,
+                        clamp_wait_time(
+                            get_i32(values, CONFIG_WAIT_TIME_SECONDS)?
+                                .unwrap_or(DEFAULT_WAIT_TIME_SECONDS),
+                        ),
+                    )
+                })
+                .transpose()?,
+            max_attempts: get_u64(values, CONFIG_MAX_ATTEMPTS)?
+                .map(|attempts| {
+                    validate_positive(CONFIG_MAX_ATTEMPTS, attempts)
+                        .map(|attempts| attempts.min(u32::MAX as u64) as u32)
+                })
+                .transpose()?,
+            create_queue_if_missing: get_bool(values, CONFIG_CREATE_QUEUE_IF_MISSING)?,
+            message_auto_delete: get_bool(values, CONFIG_MESSAGE_AUTO_DELETE)?,
+            wait_time_seconds: clamp_wait_time(
+                get_i32(values, CONFIG_WAIT_TIME_SECONDS)?.unwrap_or(DEFAULT_WAIT_TIME_SECONDS),
+            ),
+            max_number_of_messages: validate_max_messages(
+                get_i32(values, CONFIG_MAX_NUMBER_OF_MESSAGES)?
+                    .unwrap_or(DEFAULT_MAX_NUMBER_OF_MESSAGES),
+            )?,
+            content_based_deduplication: get_bool(values, CONFIG_CONTENT_BASED_DEDUPLICATION)?,
+            dedup_from_body_hash: validate_dedup_from_body_hash(
+                get_bool(values, CONFIG_DEDUP_FROM_BODY_HASH)?,
+                get_bool(values, CONFIG_CONTENT_BASED_DEDUPLICATION)?,
+            )?,
+            subject_routing: get_bool(values, CONFIG_SUBJECT_ROUTING)?,
+            batch_flush_ms: get_u64(values, CONFIG_BATCH_FLUSH_MS)?.unwrap_or(0),
+            visibility_timeout_seconds: get_i32(values, CONFIG_VISIBILITY_TIMEOUT_SECONDS)?
+                .map(validate_visibility_timeout)
+                .transpose()?,
+            dispatch_timeout_ms: validate_dispatch_timeout_ms(
+                get_u64(values, CONFIG_DISPATCH_TIMEOUT_MS)?.unwrap_or(0),
+                get_i32(values, CONFIG_VISIBILITY_TIMEOUT_SECONDS)?,
+            )?,
+            endpoint_url: get_opt(values, CONFIG_ENDPOINT_URL),
+            queue_owner_account_id: get_opt(values, CONFIG_QUEUE_OWNER_ACCOUNT_ID)
+                .map(validate_account_id)
+                .transpose()?,
+            kms_master_key_id: get_opt(values, CONFIG_KMS_MASTER_KEY_ID),
+            deduplication_scope: get_opt(values, CONFIG_DEDUPLICATION_SCOPE)
+                .map(|scope| validate_enum(CONFIG_DEDUPLICATION_SCOPE, scope, &["queue", "messageGroup"]))
+                .transpose()?,
+            fifo_throughput_limit: get_opt(values, CONFIG_FIFO_THROUGHPUT_LIMIT)
+                .map(|limit| {
+                    validate_enum(
+                        CONFIG_FIFO_THROUGHPUT_LIMIT,
+                        limit,
+                        &["perQueue", "perMessageGroupId"],
+                    )
+                })
+                .transpose()?,
+            kms_data_key_reuse_period: get_i32(values, CONFIG_KMS_DATA_KEY_REUSE_PERIOD)?
+                .map(validate_kms_reuse_period)
+                .transpose()?,
+            receive_backoff_max_seconds: get_u64(values, CONFIG_RECEIVE_BACKOFF_MAX_SECONDS)?
+                .unwrap_or(DEFAULT_RECEIVE_BACKOFF_MAX_SECONDS),
+            idle_backoff_ms: get_u64(values, CONFIG_IDLE_BACKOFF_MS)?
+                .map(validate_idle_backoff_ms)
+                .transpose()?
+                .unwrap_or(0),
+            idle_notify_polls: get_u64(values, CONFIG_IDLE_NOTIFY_POLLS)?.unwrap_or(0),
+            message_ttl_seconds: get_u64(values, CONFIG_MESSAGE_TTL_SECONDS)?.unwrap_or(0),
+            publish_rate_limit: get_u64(values, CONFIG_PUBLISH_RATE_LIMIT)?
+                .map(|v| validate_positive(CONFIG_PUBLISH_RATE_LIMIT, v).map(|v| v as u32))
+                .transpose()?,
+            rate_limit_behavior: get_opt(values, CONFIG_RATE_LIMIT_BEHAVIOR)
+                .map(|v| parse_rate_limit_behavior(&v))
+                .transpose()?
+                .unwrap_or_default(),
+            rate_limit_wait_ms: get_u64(values, CONFIG_RATE_LIMIT_WAIT_MS)?
+                .map(|v| validate_positive(CONFIG_RATE_LIMIT_WAIT_MS, v))
+                .transpose()?
+                .unwrap_or(DEFAULT_RATE_LIMIT_WAIT_MS),
+            failover_queue_name: get_opt(values, CONFIG_FAILOVER_QUEUE_NAME),
+            failover_region: validate_failover_region(
+                get_opt(values, CONFIG_FAILOVER_REGION),
+                values.contains_key(CONFIG_FAILOVER_QUEUE_NAME),
+            )?,
+            failover_threshold: get_u64(values, CONFIG_FAILOVER_THRESHOLD)?
+                .map(|v| validate_positive(CONFIG_FAILOVER_THRESHOLD, v))
+                .transpose()?
+                .unwrap_or(DEFAULT_FAILOVER_THRESHOLD),
+            failover_probe_interval_ms: get_u64(values, CONFIG_FAILOVER_PROBE_INTERVAL_MS)?
+                .map(|v| validate_positive(CONFIG_FAILOVER_PROBE_INTERVAL_MS, v))
+                .transpose()?
+                .unwrap_or(DEFAULT_FAILOVER_PROBE_INTERVAL_MS),
+            propagate_trace_context: get_bool(values, CONFIG_PROPAGATE_TRACE_CONTEXT)?,
+            max_concurrent_handlers: get_u64(values, CONFIG_MAX_CONCURRENT_HANDLERS)?
+                .map(validate_max_concurrent_handlers)
+                .transpose()?
+                .unwrap_or(DEFAULT_MAX_CONCURRENT_HANDLERS),
+            consumer_count: get_u64(values, CONFIG_CONSUMER_COUNT)?
+                .map(validate_consumer_count)
+                .transpose()?
+                .unwrap_or(1),
+            subscribe_filter: get_opt(values, CONFIG_SUBSCRIBE_FILTER)
+                .map(|v| parse_subscribe_filter(&v))
+                .transpose()?
+                .unwrap_or_default(),
+            envelope_format: get_opt(values, CONFIG_ENVELOPE_FORMAT)
+                .map(|v| parse_envelope_format(&v))
+                .transpose()?
+                .unwrap_or_default(),
+            publish_allow: get_opt(values, CONFIG_PUBLISH_ALLOW)
+                .map(|v| parse_subject_patterns(&v))
+                .unwrap_or_default(),
+            publish_deny: get_opt(values, CONFIG_PUBLISH_DENY)
+                .map(|v| parse_subject_patterns(&v))
+                .unwrap_or_default(),
+            delete_filtered: get_bool(values, CONFIG_DELETE_FILTERED)?,
+            max_processing_attempts: get_u64(values, CONFIG_MAX_PROCESSING_ATTEMPTS)?
+                .map(validate_max_processing_attempts)
+                .transpose()?,
+            dead_letter_queue_name: get_opt(values, CONFIG_DEAD_LETTER_QUEUE_NAME),
+            max_receive_count: validate_max_receive_count(
+                get_i32(values, CONFIG_MAX_RECEIVE_COUNT)?.unwrap_or(DEFAULT_MAX_RECEIVE_COUNT),
+            )?,
+            message_retention_seconds: validate_retention(
+                get_i32(values, CONFIG_MESSAGE_RETENTION_SECONDS)?
+                    .unwrap_or(DEFAULT_MESSAGE_RETENTION_SECONDS),
+            )?,
+            allow_purge: get_bool(values, CONFIG_ALLOW_PURGE)?,
+            allow_dlq_replay: get_bool(values, CONFIG_ALLOW_DLQ_REPLAY)?,
+            shutdown_drain_timeout_ms: get_u64(values, CONFIG_SHUTDOWN_DRAIN_TIMEOUT_MS)?
+                .unwrap_or(DEFAULT_SHUTDOWN_DRAIN_TIMEOUT_MS),
+            drain_on_delete_link: get_bool(values, CONFIG_DRAIN_ON_DELETE_LINK)?,
+            body_encoding: get_opt(values, CONFIG_BODY_ENCODING)
+                .map(|mode| parse_body_encoding(&mode))
+                .transpose()?
+                .unwrap_or_default(),
+            delivery_mode: get_opt(values, CONFIG_DELIVERY_MODE)
+                .map(|mode| parse_delivery_mode(&mode))
+                .transpose()?
+                .unwrap_or_default(),
+            unwrap_sns_envelope: get_bool(values, CONFIG_UNWRAP_SNS_ENVELOPE)?,
+            enable_sns_publish: get_bool(values, CONFIG_ENABLE_SNS_PUBLISH)?,
+            large_payload_bucket: get_opt(values, CONFIG_LARGE_PAYLOAD_BUCKET),
+            large_payload_threshold: get_u64(values, CONFIG_LARGE_PAYLOAD_THRESHOLD)?
+                .map(validate_large_payload_threshold)
+                .transpose()?
+                .unwrap_or(DEFAULT_LARGE_PAYLOAD_THRESHOLD_BYTES),
+            log_message_bodies: get_bool(values, CONFIG_LOG_MESSAGE_BODIES)?,
+            log_body_preview_bytes: get_u64(values, CONFIG_LOG_BODY_PREVIEW_BYTES)?
+                .map(|v| v as usize)
+                .unwrap_or(DEFAULT_LOG_BODY_PREVIEW_BYTES),
+            log_body_redact_pattern: get_opt(values, CONFIG_LOG_BODY_REDACT_PATTERN)
+                .map(|pattern| match regex::Regex::new(&pattern) {
+                    Ok(_) => Ok(pattern),
+                    Err(e) => Err(RpcError::ProviderInit(format!(
+                        "link value '{}' is not a valid regex: {}",
+                        CONFIG_LOG_BODY_REDACT_PATTERN, e
+                    ))),
+                })
+                .transpose()?,
+            xray_tracing: get_bool(values, CONFIG_XRAY_TRACING)?,
+            cache_queue_url: get_bool_or(values, CONFIG_CACHE_QUEUE_URL, true)?,
+            queue_tags: get_opt(values, CONFIG_QUEUE_TAGS)
+                .map(|tags| parse_queue_tags(&tags))
+                .transpose()?
+                .unwrap_or_default(),
+            reconcile_tags: get_bool(values, CONFIG_RECONCILE_TAGS)?,
+            visibility_heartbeat: get_bool(values, CONFIG_VISIBILITY_HEARTBEAT)?,
+            max_visibility_extension_seconds: validate_positive(
+                CONFIG_MAX_VISIBILITY_EXTENSION_SECONDS,
+                get_u64(values, CONFIG_MAX_VISIBILITY_EXTENSION_SECONDS)?
+                    .unwrap_or(DEFAULT_MAX_VISIBILITY_EXTENSION_SECONDS),
+            )?,
+            queue_name_prefix: get_opt(values, CONFIG_QUEUE_NAME_PREFIX),
+            prefix_refresh_seconds: validate_positive(
+                CONFIG_PREFIX_REFRESH_SECONDS,
+                get_u64(values, CONFIG_PREFIX_REFRESH_SECONDS)?
+                    .unwrap_or(DEFAULT_PREFIX_REFRESH_SECONDS),
+            )?,
+            dispatch_error_policy: get_opt(values, CONFIG_DISPATCH_ERROR_POLICY)
+                .map(|policy| parse_dispatch_error_policy(&policy))
+                .transpose()?
+                .unwrap_or_default(),
+            group_id_from: get_opt(values, CONFIG_GROUP_ID_FROM)
+                .map(|strategy| parse_group_id_strategy(&strategy))
+                .transpose()?
+                .unwrap_or_default(),
+            default_message_group_id: get_opt(values, CONFIG_DEFAULT_MESSAGE_GROUP_ID)
+                .map(validate_group_id)
+                .transpose()?,
+            delay_seconds: validate_delay(
+                get_i32(values, CONFIG_DELAY_SECONDS)?.unwrap_or(DEFAULT_DELAY_SECONDS),
+            )?,
+        };
+        if config.group_id_from == GroupIdStrategy::Static
+            && config.default_message_group_id.is_none()
+        {
+            return Err(RpcError::ProviderInit(format!(
+                "'{}' \"static\" requires '{}'",
+                CONFIG_GROUP_ID_FROM, CONFIG_DEFAULT_MESSAGE_GROUP_ID
+            )));
+        }
+        if config.access_key_id.is_some() != config.secret_access_key.is_some() {
+            return Err(RpcError::ProviderInit(format!(
+                "'{}' and '{}' must be provided together",
+                CONFIG_ACCESS_KEY_ID, CONFIG_SECRET_ACCESS_KEY
+            )));
+        }
+        if config.max_processing_attempts.is_some() && config.dead_letter_queue_name.is_none() {
+            return Err(RpcError::ProviderInit(format!(
+                "'{}' requires '{}': quarantined messages need somewhere to go",
+                CONFIG_MAX_PROCESSING_ATTEMPTS, CONFIG_DEAD_LETTER_QUEUE_NAME
+            )));
+        }
+        if (config.deduplication_scope.is_some() || config.fifo_throughput_limit.is_some())
+            && !config.queue_name.ends_with(".fifo")
+        {
+            return Err(RpcError::ProviderInit(format!(
+                "'{}' and '{}' only apply to .fifo queues",
+                CONFIG_DEDUPLICATION_SCOPE, CONFIG_FIFO_THROUGHPUT_LIMIT
+            )));
+        }
+        if let Some(role_arn) = &config.assume_role_arn {
+            if !role_arn.starts_with("arn:") {
+                return Err(RpcError::ProviderInit(format!(
+                    "link value '{}' must be an iam role arn, found \"{}\"",
+                    CONFIG_ASSUME_ROLE_ARN, role_arn
+                )));
+            }
+        } else if config.assume_role_session_name.is_some()
+            || config.assume_role_external_id.is_some()
+        {
+            return Err(RpcError::ProviderInit(format!(
+                "'{}' and '{}' require '{}'",
+                CONFIG_ASSUME_ROLE_SESSION_NAME,
+                CONFIG_ASSUME_ROLE_EXTERNAL_ID,
+                CONFIG_ASSUME_ROLE_ARN
+            )));
+        }
+        if config.kms_data_key_reuse_period.is_some() && config.kms_master_key_id.is_none() {
+            return Err(RpcError::ProviderInit(format!(
+                "'{}' requires '{}' to be set",
+                CONFIG_KMS_DATA_KEY_REUSE_PERIOD, CONFIG_KMS_MASTER_KEY_ID
+            )));
+        }
+        // fail at link time rather than on the first send
+        config.endpoint()?;
+        Ok(config)
+    }
+
+    /// Where this link's aws credentials come from. An explicit
+    /// `credential_source` wins outright; otherwise the usual precedence
+    /// applies: static keys on the link, then a named profile, then
+    /// whatever the provider's environment resolves to.
+    pub(crate) fn credentials_source(&self) -> CredentialsSource {
+        if let Some(source) = &self.credential_source {
+            return source.clone();
+        }
+        if self.access_key_id.is_some() && self.secret_access_key.is_some() {
+            CredentialsSource::StaticKeys
+        } else if let Some(profile) = &self.aws_profile {
+            CredentialsSource::Profile(profile.clone())
+        } else {
+            CredentialsSource::Environment
+        }
+    }
+
+    /// Resolve the aws configuration for this link. Settings on the link
+    /// (region, static credentials, a named profile, an assumed role) win
+    /// over the provider's environment.
+    pub(crate) async fn configure_aws(&self) -> aws_types::SdkConfig {
+        use aws_types::credentials::SharedCredentialsProvider;
+
+        let mut loader = aws_config::from_env();
+        if let Some(region) = &self.aws_region {
+            loader = loader.region(Region::new(region.clone()));
+        }
+        if self.connect_timeout_ms.is_some() || self.operation_timeout_ms.is_some() {
+            let mut timeouts = aws_config::timeout::TimeoutConfig::builder();
+            timeouts.set_connect_timeout(
+                self.connect_timeout_ms
+                    .map(std::time::Duration::from_millis),
+            );
+            timeouts.set_operation_timeout(
+                self.operation_timeout_ms
+                    .map(std::time::Duration::from_millis),
+            );
+            loader = loader.timeout_config(timeouts.build());
+        }
+        if let Some(max_attempts) = self.max_attempts {
+            loader = loader
+                .retry_config(aws_config::retry::RetryConfig::standard().with_max_attempts(max_attempts));
+        }
+        let base = match self.credentials_source() {
+            CredentialsSource::StaticKeys => Some(SharedCredentialsProvider::new(
+                aws_types::credentials::Credentials::from_keys(
+                    self.access_key_id.clone().unwrap_or_default(),
+                    self.secret_access_key.clone().unwrap_or_default(),
+                    self.session_token.clone(),
+                ),
+            )),
+            CredentialsSource::Profile(profile) => Some(SharedCredentialsProvider::new(
+                aws_config::profile::ProfileFileCredentialsProvider::builder()
+                    .profile_name(profile)
+                    .build(),
+            )),
+            CredentialsSource::EnvOnly => Some(SharedCredentialsProvider::new(
+                aws_config::environment::credentials::EnvironmentVariableCredentialsProvider::new(),
+            )),
+            CredentialsSource::Imds => Some(SharedCredentialsProvider::new(
+                aws_config::imds::credentials::ImdsCredentialsProvider::builder().build(),
+            )),
+            CredentialsSource::Ecs => Some(SharedCredentialsProvider::new(
+                aws_config::ecs::EcsCredentialsProvider::builder().build(),
+            )),
+            CredentialsSource::Environment => None,
+        };
+        match (&self.assume_role_arn, base) {
+            // the assumed role wraps whatever base credentials resolve to;
+            // the sts provider refreshes the session credentials itself
+            (Some(role_arn), base) => {
+                let base = match base {
+                    Some(base) => base,
+                    None => SharedCredentialsProvider::new(
+                        aws_config::default_provider::credentials::DefaultCredentialsChain::builder()
+                            .build()
+                            .await,
+                    ),
+                };
+                let mut role = aws_config::sts::AssumeRoleProvider::builder(role_arn)
+                    .session_name(
+                        self.assume_role_session_name
+                            .clone()
+                            .unwrap_or_else(|| DEFAULT_ASSUME_ROLE_SESSION_NAME.to_string()),
+                    );
+                if let Some(external_id) = &self.assume_role_external_id {
+                    role = role.external_id(external_id);
+                }
+                if let Some(region) = &self.aws_region {
+                    role = role.region(Region::new(region.clone()));
+                }
+                loader = loader.credentials_provider(role.build(base));
+            }
+            (None, Some(base)) => {
+                loader = loader.credentials_provider(base);
+            }
+            (None, None) => {}
+        }
+        loader.load().await
+    }
+
+    /// The visibility timeout the heartbeat assumes for its extension
+    /// cadence: the link's, or the sqs default when the link leaves the
+    /// queue's own setting in charge
+    pub(crate) fn heartbeat_base_seconds(&self) -> i32 {
+        self.visibility_timeout_seconds
+            .unwrap_or(DEFAULT_SQS_VISIBILITY_TIMEOUT_SECONDS)
+    }
+
+    /// A partition-correct endpoint for the link's region, for partitions
+    /// whose hostnames the environment-driven defaults can miss. An explicit
+    /// endpoint_url always wins over this; for commercial regions the sdk's
+    /// own resolver is left in charge.
+    pub(crate) fn partition_endpoint(&self, service: &str) -> Option<sqs::Endpoint> {
+        let region = self.aws_region.as_deref()?;
+        let url = if region.starts_with("us-gov-") {
+            format!("https://{}.{}.amazonaws.com", service, region)
+        } else if region.starts_with("cn-") {
+            format!("https://{}.{}.amazonaws.com.cn", service, region)
+        } else {
+            return None;
+        };
+        url.parse().map(sqs::Endpoint::immutable).ok()
+    }
+
+    /// The endpoint override for this link, if one was configured. Parsed on
+    /// demand so a bad url is reported with the link value that caused it.
+    pub(crate) fn endpoint(&self) -> RpcResult<Option<sqs::Endpoint>> {
+        self.endpoint_url
+            .as_ref()
+            .map(|url| {
+                url.parse().map(sqs::Endpoint::immutable).map_err(|e| {
+                    RpcError::ProviderInit(format!(
+                        "invalid '{}' value \"{}\": {}",
+                        CONFIG_ENDPOINT_URL, url, e
+                    ))
+                })
+            })
+            .transpose()
+    }
+}
+
+/// returns the trimmed link value, treating a missing key and an empty value the same
+fn get_opt(values: &HashMap<String, String>, key: &str) -> Option<String> {
+    values
+        .get(key)
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+}
+
+/// parse an optional boolean link value from the strings "true"/"false" (default false)
+fn get_bool(values: &HashMap<String, String>, key: &str) -> RpcResult<bool> {
+    match values.get(key).map(|v| v.trim()).filter(|v| !v.is_empty()) {
+        Some(v) => v.parse::<bool>().map_err(|_| {
+            RpcError::ProviderInit(format!(
+                "link value '{}' must be \"true\" or \"false\", found \"{}\"",
+                key, v
+            ))
+        }),
+        None => Ok(false),
+    }
+}
+
+/// parse an optional boolean link value that defaults to something other
+/// than false when absent
+fn get_bool_or(values: &HashMap<String, String>, key: &str, default: bool) -> RpcResult<bool> {
+    match values.get(key).map(|v| v.trim()).filter(|v| !v.is_empty()) {
+        Some(_) => get_bool(values, key),
+        None => Ok(default),
+    }
+}
+
+/// parse an optional integer link value
+fn get_u64(values: &HashMap<String, String>, key: &str) -> RpcResult<Option<u64>> {
+    match values.get(key).map(|v| v.trim()).filter(|v| !v.is_empty()) {
+        Some(v) => v.parse::<u64>().map(Some).map_err(|_| {
+            RpcError::ProviderInit(format!(
+                "link value '{}' must be a non-negative integer, found \"{}\"",
+                key, v
+            ))
+        }),
+        None => Ok(None),
+    }
+}
+
+fn get_i32(values: &HashMap<String, String>, key: &str) -> RpcResult<Option<i32>> {
+    match values.get(key).map(|v| v.trim()).filter(|v| !v.is_empty()) {
+        Some(v) => v
+            .parse::<i32>()
+            .map(Some)
+            .map_err(|_| {
+                RpcError::ProviderInit(format!(
+                    "link value '{}' must be an integer, found \"{}\"",
+                    key, v
+                ))
+            }),
+        None => Ok(None),
+    }
+}
+
+/// Reject receive batch sizes outside the 1-10 range sqs allows. Unlike the
+/// wait time this is an error, not a clamp: silently shrinking a configured
+/// batch size would hide misconfiguration.
+fn validate_max_messages(count: i32) -> RpcResult<i32> {
+    if (1..=10).contains(&count) {
+        Ok(count)
+    } else {
+        Err(RpcError::ProviderInit(format!(
+            "link value '{}' must be between 1 and 10, found {}",
+            CONFIG_MAX_NUMBER_OF_MESSAGES, count
+        )))
+    }
+}
+
+/// Reject visibility timeouts outside the 0 second to 12 hour range sqs
+/// allows. Like the batch size, out-of-range values are an error rather than
+/// a clamp.
+fn validate_visibility_timeout(seconds: i32) -> RpcResult<i32> {
+    if (0..=43_200).contains(&seconds) {
+        Ok(seconds)
+    } else {
+        Err(RpcError::ProviderInit(format!(
+            "link value '{}' must be between 0 and 43200 seconds, found {}",
+            CONFIG_VISIBILITY_TIMEOUT_SECONDS, seconds
+        )))
+    }
+}
+
+/// reject receive counts outside the 1-1000 range sqs allows for redrive
+fn validate_max_receive_count(count: i32) -> RpcResult<i32> {
+    if (1..=1000).contains(&count) {
+        Ok(count)
+    } else {
+        Err(RpcError::ProviderInit(format!(
+            "link value '{}' must be between 1 and 1000, found {}",
+            CONFIG_MAX_RECEIVE_COUNT, count
+        )))
+    }
+}
+
+/// reject retention periods outside the 1 minute to 14 day range sqs allows
+fn validate_retention(seconds: i32) -> RpcResult<i32> {
+    if (60..=1_209_600).contains(&seconds) {
+        Ok(seconds)
+    } else {
+        Err(RpcError::ProviderInit(format!(
+            "link value '{}' must be between 60 and 1209600 seconds, found {}",
+            CONFIG_MESSAGE_RETENTION_SECONDS, seconds
+        )))
+    }
+}
+
+/// sqs only accepts a fixed set of values for some string attributes; catch
+/// a typo at link time instead of on the first create_queue
+fn validate_enum(key: &str, value: String, allowed: &[&str]) -> RpcResult<String> {
+    if allowed.contains(&value.as_str()) {
+        Ok(value)
+    } else {
+        Err(RpcError::ProviderInit(format!(
+            "link value '{}' must be one of {:?}, found \"{}\"",
+            key, allowed, value
+        )))
+    }
+}
+
+/// An idle pause beyond a minute would hold messages longer than any
+/// latency-tolerant caller plausibly wants
+fn validate_idle_backoff_ms(value: u64) -> RpcResult<u64> {
+    if value > 60_000 {
+        return Err(RpcError::ProviderInit(format!(
+            "link value '{}' must be at most 60000 (one minute)",
+            CONFIG_IDLE_BACKOFF_MS
+        )));
+    }
+    Ok(value)
+}
+
+/// Check the `queue_url` override: it must actually be a url (a bare name
+/// would silently fall back to resolution), and it cannot be combined with
+/// the settings whose whole purpose is resolution or discovery.
+fn validate_queue_url_override(values: &HashMap<String, String>) -> RpcResult<Option<String>> {
+    let url = match get_opt(values, CONFIG_QUEUE_URL) {
+        Some(url) => url,
+        None => return Ok(None),
+    };
+    if !url.starts_with("http://") && !url.starts_with("https://") {
+        return Err(RpcError::ProviderInit(format!(
+            "invalid {} '{}': must be a full http(s) queue url",
+            CONFIG_QUEUE_URL, url
+        )));
+    }
+    for conflicting in [CONFIG_QUEUE_NAME, CONFIG_QUEUE_NAMES, CONFIG_QUEUE_NAME_PREFIX] {
+        if values.contains_key(conflicting) {
+            return Err(RpcError::ProviderInit(format!(
+                "'{}' bypasses queue resolution and cannot be combined with '{}'",
+                CONFIG_QUEUE_URL, conflicting
+            )));
+        }
+    }
+    Ok(Some(url))
+}
+
+/// Body-hash deduplication and sqs's own content-based deduplication both
+/// hash the body; setting both is a contradiction in whose hash wins, so
+/// refuse the link rather than silently preferring one.
+fn validate_dedup_from_body_hash(from_body_hash: bool, content_based: bool) -> RpcResult<bool> {
+    if from_body_hash && content_based {
+        return Err(RpcError::ProviderInit(format!(
+            "'{}' and '{}' both deduplicate on the body; set only one",
+            CONFIG_DEDUP_FROM_BODY_HASH, CONFIG_CONTENT_BASED_DEDUPLICATION
+        )));
+    }
+    Ok(from_body_hash)
+}
+
+/// A failover region only means something when there is a failover queue to
+/// publish to; catch the dangling setting at link time.
+fn validate_failover_region(
+    region: Option<String>,
+    has_failover_queue: bool,
+) -> RpcResult<Option<String>> {
+    if region.is_some() && !has_failover_queue {
+        return Err(RpcError::ProviderInit(format!(
+            "'{}' requires '{}' to name the queue in that region",
+            CONFIG_FAILOVER_REGION, CONFIG_FAILOVER_QUEUE_NAME
+        )));
+    }
+    Ok(region)
+}
+
+/// A dispatch timeout at or beyond the visibility timeout defeats its own
+/// purpose: sqs would re-deliver the message while the original handler is
+/// still running it. Catch the contradiction at link time.
+fn validate_dispatch_timeout_ms(
+    timeout_ms: u64,
+    visibility_timeout_seconds: Option<i32>,
+) -> RpcResult<u64> {
+    if let Some(visibility) = visibility_timeout_seconds {
+        if timeout_ms > 0 && timeout_ms >= (visibility.max(0) as u64) * 1_000 {
+            return Err(RpcError::ProviderInit(format!(
+                "'{}' ({} ms) must be shorter than '{}' ({} s) so a timed-out message is not \
+                 redelivered while its handler is still running",
+                CONFIG_DISPATCH_TIMEOUT_MS,
+                timeout_ms,
+                CONFIG_VISIBILITY_TIMEOUT_SECONDS,
+                visibility
+            )));
+        }
+    }
+    Ok(timeout_ms)
+}
+
+/// an operation timeout at or under the long-poll wait would time every
+/// receive out before sqs could possibly answer
+fn validate_operation_timeout_ms(timeout_ms: u64, wait_time_seconds: i32) -> RpcResult<u64> {
+    if timeout_ms <= (wait_time_seconds.max(0) as u64) * 1_000 {
+        return Err(RpcError::ProviderInit(format!(
+            "'{}' ({} ms) must exceed '{}' ({} s) or every long poll would time out",
+            CONFIG_OPERATION_TIMEOUT_MS, timeout_ms, CONFIG_WAIT_TIME_SECONDS, wait_time_seconds
+        )));
+    }
+    Ok(timeout_ms)
+}
+
+/// consumer_count must land in 1..=MAX_CONSUMER_COUNT
+fn validate_consumer_count(value: u64) -> RpcResult<u32> {
+    if !(1..=MAX_CONSUMER_COUNT).contains(&value) {
+        return Err(RpcError::ProviderInit(format!(
+            "invalid {} '{}': must be between 1 and {}",
+            CONFIG_CONSUMER_COUNT, value, MAX_CONSUMER_COUNT
+        )));
+    }
+    Ok(value as u32)
+}
+
+fn validate_positive(key: &str, value: u64) -> RpcResult<u64> {
+    if value >= 1 {
+        Ok(value)
+    } else {
+        Err(RpcError::ProviderInit(format!(
+            "link value '{}' must be at least 1",
+            key
+        )))
+    }
+}
+
+/// zero concurrent handlers would deadlock the receive loop
+fn validate_max_concurrent_handlers(limit: u64) -> RpcResult<usize> {
+    if limit >= 1 {
+        Ok(limit.min(usize::MAX as u64) as usize)
+    } else {
+        Err(RpcError::ProviderInit(format!(
+            "link value '{}' must be at least 1",
+            CONFIG_MAX_CONCURRENT_HANDLERS
+        )))
+    }
+}
+
+/// at least one processing attempt must be allowed before quarantining
+fn validate_max_processing_attempts(attempts: u64) -> RpcResult<u32> {
+    if attempts >= 1 {
+        Ok(attempts.min(u32::MAX as u64) as u32)
+    } else {
+        Err(RpcError::ProviderInit(format!(
+            "link value '{}' must be at least 1",
+            CONFIG_MAX_PROCESSING_ATTEMPTS
+        )))
+    }
+}
+
+/// the kms data key reuse period must sit in the 60 second to 24 hour range
+/// sqs accepts
+fn validate_kms_reuse_period(seconds: i32) -> RpcResult<i32> {
+    if (60..=86_400).contains(&seconds) {
+        Ok(seconds)
+    } else {
+        Err(RpcError::ProviderInit(format!(
+            "link value '{}' must be between 60 and 86400 seconds, found {}",
+            CONFIG_KMS_DATA_KEY_REUSE_PERIOD, seconds
+        )))
+    }
+}
+
+/// aws account ids are exactly twelve digits; anything else means the link
+/// value was mistyped and every get_queue_url call would fail
+fn validate_account_id(account_id: String) -> RpcResult<String> {
+    if account_id.len() == 12 && account_id.bytes().all(|b| b.is_ascii_digit()) {
+        Ok(account_id)
+    } else {
+        Err(RpcError::ProviderInit(format!(
+            "link value '{}' must be a 12-digit aws account id, found \"{}\"",
+            CONFIG_QUEUE_OWNER_ACCOUNT_ID, account_id
+        )))
+    }
+}
+
+/// Parse a `subscribe_filter` link value: comma-separated attribute=value
+/// pairs a message must all match to be dispatched
+fn parse_subscribe_filter(value: &str) -> RpcResult<HashMap<String, String>> {
+    let mut filter = HashMap::new();
+    for pair in value.split(',').filter(|p| !p.trim().is_empty()) {
+        let (name, expected) = pair.split_once('=').ok_or_else(|| {
+            RpcError::ProviderInit(format!(
+                "link value '{}' entry \"{}\" is not of the form attribute=value",
+                CONFIG_SUBSCRIBE_FILTER, pair
+            ))
+        })?;
+        let (name, expected) = (name.trim(), expected.trim());
+        if name.is_empty() {
+            return Err(RpcError::ProviderInit(format!(
+                "link value '{}' has an entry with an empty attribute name",
+                CONFIG_SUBSCRIBE_FILTER
+            )));
+        }
+        filter.insert(name.to_string(), expected.to_string());
+    }
+    Ok(filter)
+}
+
+/// Split a comma-separated list of subject patterns, dropping empty entries
+fn parse_subject_patterns(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|p| !p.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Parse a `queue_tags` value of the form `k1=v1,k2=v2`, enforcing the sqs
+/// tag limits: at most 50 tags, keys of 1-128 characters, values up to 256.
+fn parse_queue_tags(value: &str) -> RpcResult<HashMap<String, String>> {
+    let mut tags = HashMap::new();
+    for pair in value.split(',').filter(|p| !p.trim().is_empty()) {
+        let (key, tag_value) = pair.split_once('=').ok_or_else(|| {
+            RpcError::ProviderInit(format!(
+                "link value '{}' entry \"{}\" is not of the form key=value",
+                CONFIG_QUEUE_TAGS, pair
+            ))
+        })?;
+        let (key, tag_value) = (key.trim(), tag_value.trim());
+        if key.is_empty() || key.len() > 128 {
+            return Err(RpcError::ProviderInit(format!(
+                "link value '{}' tag key \"{}\" must be 1-128 characters",
+                CONFIG_QUEUE_TAGS, key
+            )));
+        }
+        if tag_value.len() > 256 {
+            return Err(RpcError::ProviderInit(format!(
+                "link value '{}' tag value for \"{}\" exceeds 256 characters",
+                CONFIG_QUEUE_TAGS, key
+            )));
+        }
+        tags.insert(key.to_string(), tag_value.to_string());
+    }
+    if tags.len() > 50 {
+        return Err(RpcError::ProviderInit(format!(
+            "link value '{}' exceeds the 50 tags sqs allows",
+            CONFIG_QUEUE_TAGS
+        )));
+    }
+    Ok(tags)
+}
+
+/// Reject message group ids outside the 1-128 character alphanumeric and
+/// punctuation set sqs accepts. Also applied to group ids the provider
+/// derives at publish time, since a subject or default may be anything.
+pub(crate) fn validate_group_id(group_id: String) -> RpcResult<String> {
+    if group_id.is_empty() || group_id.len() > 128 {
+        return Err(RpcError::InvalidParameter(format!(
+            "message group id \"{}\" must be 1-128 characters",
+            group_id
+        )));
+    }
+    if let Some(bad) = group_id
+        .chars()
+        .find(|c| !c.is_ascii_alphanumeric() && !c.is_ascii_punctuation())
+    {
+        return Err(RpcError::InvalidParameter(format!(
+            "message group id \"{}\" contains disallowed character '{}'",
+            group_id, bad
+        )));
+    }
+    Ok(group_id)
+}
+
+/// reject delivery delays outside the 0-900 second range sqs allows
+fn validate_delay(seconds: i32) -> RpcResult<i32> {
+    if (0..=900).contains(&seconds) {
+        Ok(seconds)
+    } else {
+        Err(RpcError::ProviderInit(format!(
+            "link value '{}' must be between 0 and 900 seconds, found {}",
+            CONFIG_DELAY_SECONDS, seconds
+        )))
+    }
+}
+
+/// clamp a configured wait time into the 0-20 second range sqs allows
+fn clamp_wait_time(seconds: i32) -> i32 {
+    let clamped = seconds.clamp(0, 20);
+    if clamped != seconds {
+        warn!(
+            "{} {} is outside the 0-20 range sqs allows; clamping to {}",
+            CONFIG_WAIT_TIME_SECONDS, seconds, clamped
+        );
+    }
+    clamped
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use std::collections::HashMap;
+
+    use super::{
+        clamp_wait_time, CredentialsSource, DispatchErrorPolicy, EnvelopeFormat, GroupIdStrategy,
+        RateLimitBehavior, SQSConfig, DEFAULT_WAIT_TIME_SECONDS,
+    };
+    use wasmbus_rpc::core::LinkDefinition;
+
+    pub(crate) fn link_with_values(values: &[(&str, &str)]) -> LinkDefinition {
+        let mut ld = LinkDefinition::default();
+        ld.actor_id = String::from("test-actor");
+        ld.link_name = String::from("default");
+        ld.contract_id = String::from("wasmcloud:messaging");
+        ld.values = values
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect::<HashMap<String, String>>();
+        ld
+    }
+
+    #[test]
+    fn test_from_link_missing_queue_name() {
+        let ld = link_with_values(&[("aws_region", "us-east-1")]);
+        let err = SQSConfig::from_link(&ld).expect_err("config without queue_name should fail");
+        assert!(err.to_string().contains("queue_name"));
+    }
+
+    #[test]
+    fn test_from_link_empty_queue_name() {
+        // an empty or whitespace-only value is as good as a missing one
+        let ld = link_with_values(&[("queue_name", "  ")]);
+        assert!(SQSConfig::from_link(&ld).is_err());
+    }
+
+    #[test]
+    fn test_from_link_minimal() {
+        let ld = link_with_values(&[("queue_name", "orders")]);
+        let config = SQSConfig::from_link(&ld).unwrap();
+        assert_eq!(config.queue_name, "orders");
+        assert!(config.aws_region.is_none());
+        assert!(config.access_key_id.is_none());
+        assert!(!config.create_queue_if_missing);
+        assert!(!config.message_auto_delete);
+        assert_eq!(config.wait_time_seconds, DEFAULT_WAIT_TIME_SECONDS);
+    }
+
+    #[test]
+    fn test_from_link_full() {
+        let ld = link_with_values(&[
+            ("queue_name", "orders"),
+            ("aws_region", "eu-west-2"),
+            ("access_key_id", "AKIDEXAMPLE"),
+            ("secret_access_key", "sssh"),
+            ("session_token", "tok"),
+            ("create_queue_if_missing", "true"),
+            ("message_auto_delete", "false"),
+            ("wait_time_seconds", "5"),
+        ]);
+        let config = SQSConfig::from_link(&ld).unwrap();
+        assert_eq!(config.aws_region.as_deref(), Some("eu-west-2"));
+        assert_eq!(config.access_key_id.as_deref(), Some("AKIDEXAMPLE"));
+        assert_eq!(config.secret_access_key.as_deref(), Some("sssh"));
+        assert_eq!(config.session_token.as_deref(), Some("tok"));
+        assert!(config.create_queue_if_missing);
+        assert!(!config.message_auto_delete);
+        assert_eq!(config.wait_time_seconds, 5);
+    }
+
+    #[test]
+    fn test_from_link_bad_bool() {
+        let ld = link_with_values(&[("queue_name", "orders"), ("message_auto_delete", "yes")]);
+        let err = SQSConfig::from_link(&ld).expect_err("non true/false boolean should fail");
+        assert!(err.to_string().contains("message_auto_delete"));
+    }
+
+    #[test]
+    fn test_from_link_partial_credentials() {
+        let ld = link_with_values(&[("queue_name", "orders"), ("access_key_id", "AKIDEXAMPLE")]);
+        let err = SQSConfig::from_link(&ld).expect_err("lone access_key_id should fail");
+        assert!(err.to_string().contains("secret_access_key"));
+    }
+
+    /// Credentials on the link win over the ambient environment
+    #[tokio::test]
+    async fn test_per_link_credentials_override_env() {
+        use aws_types::credentials::ProvideCredentials;
+        std::env::set_var("AWS_REGION", "us-east-1");
+        std::env::set_var("AWS_ACCESS_KEY_ID", "env-key");
+        std::env::set_var("AWS_SECRET_ACCESS_KEY", "env-secret");
+
+        let config = SQSConfig {
+            queue_name: String::from("q"),
+            access_key_id: Some(String::from("link-key")),
+            secret_access_key: Some(String::from("link-secret")),
+            ..Default::default()
+        };
+        let creds = config
+            .configure_aws()
+            .await
+            .credentials_provider()
+            .unwrap()
+            .provide_credentials()
+            .await
+            .unwrap();
+        assert_eq!(creds.access_key_id(), "link-key");
+
+        let config = SQSConfig {
+            queue_name: String::from("q"),
+            ..Default::default()
+        };
+        let creds = config
+            .configure_aws()
+            .await
+            .credentials_provider()
+            .unwrap()
+            .provide_credentials()
+            .await
+            .unwrap();
+        assert_eq!(creds.access_key_id(), "env-key");
+    }
+
+    #[test]
+    fn test_wait_time_clamped_with_warning() {
+        let ld = link_with_values(&[("queue_name", "orders"), ("wait_time_seconds", "45")]);
+        assert_eq!(SQSConfig::from_link(&ld).unwrap().wait_time_seconds, 20);
+
+        let ld = link_with_values(&[("queue_name", "orders"), ("wait_time_seconds", "-3")]);
+        assert_eq!(SQSConfig::from_link(&ld).unwrap().wait_time_seconds, 0);
+
+        let ld = link_with_values(&[("queue_name", "orders"), ("wait_time_seconds", "twenty")]);
+        assert!(SQSConfig::from_link(&ld).is_err());
+
+        assert_eq!(clamp_wait_time(10), 10);
+    }
+
+    #[test]
+    fn test_delivery_mode() {
+        use super::DeliveryMode;
+        let ld = link_with_values(&[("queue_name", "q"), ("delivery_mode", "at_most_once")]);
+        let config = SQSConfig::from_link(&ld).unwrap();
+        assert_eq!(config.delivery_mode, DeliveryMode::AtMostOnce);
+
+        let ld = link_with_values(&[("queue_name", "q")]);
+        let config = SQSConfig::from_link(&ld).unwrap();
+        assert_eq!(config.delivery_mode, DeliveryMode::AtLeastOnce);
+
+        let ld = link_with_values(&[("queue_name", "q"), ("delivery_mode", "exactly_once")]);
+        assert!(SQSConfig::from_link(&ld).is_err());
+    }
+
+    #[test]
+    fn test_client_timeout_options() {
+        let ld = link_with_values(&[
+            ("queue_name", "q"),
+            ("connect_timeout_ms", "500"),
+            ("operation_timeout_ms", "30000"),
+            ("max_attempts", "2"),
+        ]);
+        let config = SQSConfig::from_link(&ld).unwrap();
+        assert_eq!(config.connect_timeout_ms, Some(500));
+        assert_eq!(config.operation_timeout_ms, Some(30_000));
+        assert_eq!(config.max_attempts, Some(2));
+
+        for key in ["connect_timeout_ms", "operation_timeout_ms", "max_attempts"] {
+            let ld = link_with_values(&[("queue_name", "q"), (key, "0")]);
+            assert!(SQSConfig::from_link(&ld).is_err(), "accepted {}=0", key);
+        }
+
+        // an operation timeout inside the long-poll wait would fail every
+        // receive; accepted only once the wait is shortened to fit
+        let ld = link_with_values(&[("queue_name", "q"), ("operation_timeout_ms", "1000")]);
+        assert!(SQSConfig::from_link(&ld).is_err());
+        let ld = link_with_values(&[
+            ("queue_name", "q"),
+            ("operation_timeout_ms", "1000"),
+            ("wait_time_seconds", "0"),
+        ]);
+        assert_eq!(
+            SQSConfig::from_link(&ld).unwrap().operation_timeout_ms,
+            Some(1_000)
+        );
+    }
+
+    #[test]
+    fn test_assume_role_options() {
+        let ld = link_with_values(&[
+            ("queue_name", "q"),
+            ("assume_role_arn", "arn:aws:iam::123456789012:role/shared"),
+            ("assume_role_external_id", "trust-me"),
+        ]);
+        let config = SQSConfig::from_link(&ld).unwrap();
+        assert_eq!(
+            config.assume_role_arn.as_deref(),
+            Some("arn:aws:iam::123456789012:role/shared")
+        );
+        assert_eq!(config.assume_role_external_id.as_deref(), Some("trust-me"));
+
+        // a session name or external id without a role is a mistake
+        let ld = link_with_values(&[("queue_name", "q"), ("assume_role_external_id", "x")]);
+        assert!(SQSConfig::from_link(&ld).is_err());
+
+        // and the role must at least look like an arn
+        let ld = link_with_values(&[("queue_name", "q"), ("assume_role_arn", "my-role")]);
+        assert!(SQSConfig::from_link(&ld).is_err());
+    }
+
+    #[test]
+    fn test_credentials_source_precedence() {
+        // static keys beat a profile, a profile beats the environment
+        let config = SQSConfig {
+            access_key_id: Some(String::from("key")),
+            secret_access_key: Some(String::from("secret")),
+            aws_profile: Some(String::from("dev")),
+            ..Default::default()
+        };
+        assert_eq!(config.credentials_source(), CredentialsSource::StaticKeys);
+
+        let config = SQSConfig {
+            aws_profile: Some(String::from("dev")),
+            ..Default::default()
+        };
+        assert_eq!(
+            config.credentials_source(),
+            CredentialsSource::Profile(String::from("dev"))
+        );
+
+        assert_eq!(
+            SQSConfig::default().credentials_source(),
+            CredentialsSource::Environment
+        );
+    }
+
+    #[test]
+    fn test_credential_source_selection() {
+        // an explicit selection beats the implicit precedence: env wins
+        // here even though static keys are also present
+        let ld = link_with_values(&[
+            ("queue_name", "q"),
+            ("credential_source", "env"),
+            ("access_key_id", "AKIDEXAMPLE"),
+            ("secret_access_key", "secret"),
+        ]);
+        let config = SQSConfig::from_link(&ld).unwrap();
+        assert_eq!(config.credentials_source(), CredentialsSource::EnvOnly);
+        // static selects only the link's keys, no other source involved
+        let ld = link_with_values(&[
+            ("queue_name", "q"),
+            ("credential_source", "static"),
+            ("access_key_id", "AKIDEXAMPLE"),
+            ("secret_access_key", "secret"),
+            ("aws_profile", "dev"),
+        ]);
+        let config = SQSConfig::from_link(&ld).unwrap();
+        assert_eq!(config.credentials_source(), CredentialsSource::StaticKeys);
+        // static without keys has nothing to install
+        let ld = link_with_values(&[("queue_name", "q"), ("credential_source", "static")]);
+        assert!(SQSConfig::from_link(&ld).is_err());
+        // profile needs the profile name
+        let ld = link_with_values(&[("queue_name", "q"), ("credential_source", "profile")]);
+        assert!(SQSConfig::from_link(&ld).is_err());
+        let ld = link_with_values(&[("queue_name", "q"), ("credential_source", "imds")]);
+        let config = SQSConfig::from_link(&ld).unwrap();
+        assert_eq!(config.credentials_source(), CredentialsSource::Imds);
+        let ld = link_with_values(&[("queue_name", "q"), ("credential_source", "wrong")]);
+        assert!(SQSConfig::from_link(&ld).is_err());
+    }
+
+    #[test]
+    fn test_queue_owner_account_id() {
+        let ld = link_with_values(&[
+            ("queue_name", "q"),
+            ("queue_owner_account_id", "123456789012"),
+        ]);
+        let config = SQSConfig::from_link(&ld).unwrap();
+        assert_eq!(
+            config.queue_owner_account_id.as_deref(),
+            Some("123456789012")
+        );
+
+        // an empty value is treated as unset, like every other optional field
+        for bad in ["12345678901", "1234567890123", "12345678901a"] {
+            let ld = link_with_values(&[("queue_name", "q"), ("queue_owner_account_id", bad)]);
+            assert!(SQSConfig::from_link(&ld).is_err(), "accepted \"{}\"", bad);
+        }
+    }
+
+    #[test]
+    fn test_max_processing_attempts() {
+        let ld = link_with_values(&[
+            ("queue_name", "q"),
+            ("dead_letter_queue_name", "q-dlq"),
+            ("max_processing_attempts", "4"),
+        ]);
+        let config = SQSConfig::from_link(&ld).unwrap();
+        assert_eq!(config.max_processing_attempts, Some(4));
+
+        // quarantining needs a dead-letter queue to quarantine into
+        let ld = link_with_values(&[("queue_name", "q"), ("max_processing_attempts", "4")]);
+        assert!(SQSConfig::from_link(&ld).is_err());
+
+        let ld = link_with_values(&[
+            ("queue_name", "q"),
+            ("dead_letter_queue_name", "q-dlq"),
+            ("max_processing_attempts", "0"),
+        ]);
+        assert!(SQSConfig::from_link(&ld).is_err());
+    }
+
+    #[test]
+    fn test_high_throughput_fifo_options() {
+        let ld = link_with_values(&[
+            ("queue_name", "orders.fifo"),
+            ("deduplication_scope", "messageGroup"),
+            ("fifo_throughput_limit", "perMessageGroupId"),
+        ]);
+        let config = SQSConfig::from_link(&ld).unwrap();
+        assert_eq!(config.deduplication_scope.as_deref(), Some("messageGroup"));
+        assert_eq!(
+            config.fifo_throughput_limit.as_deref(),
+            Some("perMessageGroupId")
+        );
+
+        // the attributes only exist on fifo queues
+        let ld = link_with_values(&[
+            ("queue_name", "orders"),
+            ("deduplication_scope", "messageGroup"),
+        ]);
+        assert!(SQSConfig::from_link(&ld).is_err());
+
+        // and only with the values sqs accepts
+        let ld = link_with_values(&[
+            ("queue_name", "orders.fifo"),
+            ("fifo_throughput_limit", "unlimited"),
+        ]);
+        assert!(SQSConfig::from_link(&ld).is_err());
+    }
+
+    #[test]
+    fn test_partition_endpoints() {
+        let config = SQSConfig {
+            aws_region: Some(String::from("us-gov-west-1")),
+            ..Default::default()
+        };
+        let endpoint = config.partition_endpoint("sqs").unwrap();
+        assert_eq!(
+            endpoint.uri().host(),
+            Some("sqs.us-gov-west-1.amazonaws.com")
+        );
+
+        let config = SQSConfig {
+            aws_region: Some(String::from("cn-north-1")),
+            ..Default::default()
+        };
+        let endpoint = config.partition_endpoint("sns").unwrap();
+        assert_eq!(endpoint.uri().host(), Some("sns.cn-north-1.amazonaws.com.cn"));
+
+        // commercial regions stay with the sdk's own resolver, and an
+        // explicit endpoint_url takes precedence in the client builder
+        let config = SQSConfig {
+            aws_region: Some(String::from("us-east-1")),
+            ..Default::default()
+        };
+        assert!(config.partition_endpoint("sqs").is_none());
+        let config = SQSConfig {
+            aws_region: Some(String::from("us-gov-east-1")),
+            endpoint_url: Some(String::from("http://localhost:4566")),
+            ..Default::default()
+        };
+        assert!(config.endpoint().unwrap().is_some());
+        assert!(config.partition_endpoint("sqs").is_some());
+    }
+
+    #[test]
+    fn test_publish_rate_limit_options() {
+        let ld = link_with_values(&[("queue_name", "q")]);
+        let config = SQSConfig::from_link(&ld).unwrap();
+        assert!(config.publish_rate_limit.is_none());
+        assert_eq!(config.rate_limit_behavior, RateLimitBehavior::Block);
+        assert_eq!(config.rate_limit_wait_ms, 1_000);
+
+        let ld = link_with_values(&[
+            ("queue_name", "q"),
+            ("publish_rate_limit", "50"),
+            ("rate_limit_behavior", "error"),
+            ("rate_limit_wait_ms", "250"),
+        ]);
+        let config = SQSConfig::from_link(&ld).unwrap();
+        assert_eq!(config.publish_rate_limit, Some(50));
+        assert_eq!(config.rate_limit_behavior, RateLimitBehavior::Error);
+        assert_eq!(config.rate_limit_wait_ms, 250);
+
+        for (key, bad) in [
+            ("publish_rate_limit", "0"),
+            ("rate_limit_behavior", "queue"),
+            ("rate_limit_wait_ms", "0"),
+        ] {
+            let ld = link_with_values(&[("queue_name", "q"), (key, bad)]);
+            assert!(SQSConfig::from_link(&ld).is_err(), "accepted {}={}", key, bad);
+        }
+    }
+
+    #[test]
+    fn test_failover_options() {
+        let ld = link_with_values(&[("queue_name", "q")]);
+        let config = SQSConfig::from_link(&ld).unwrap();
+        assert_eq!(config.failover_queue_name, None);
+        assert_eq!(config.failover_threshold, 3);
+        let ld = link_with_values(&[
+            ("queue_name", "q"),
+            ("failover_queue_name", "q-dr"),
+            ("failover_region", "us-west-2"),
+            ("failover_threshold", "5"),
+            ("failover_probe_interval_ms", "10000"),
+        ]);
+        let config = SQSConfig::from_link(&ld).unwrap();
+        assert_eq!(config.failover_queue_name.as_deref(), Some("q-dr"));
+        assert_eq!(config.failover_region.as_deref(), Some("us-west-2"));
+        assert_eq!(config.failover_threshold, 5);
+        assert_eq!(config.failover_probe_interval_ms, 10_000);
+        // a region without a queue has nothing to point at
+        let ld = link_with_values(&[("queue_name", "q"), ("failover_region", "us-west-2")]);
+        assert!(SQSConfig::from_link(&ld).is_err());
+        // thresholds must be positive: zero would never close the circuit
+        let ld = link_with_values(&[
+            ("queue_name", "q"),
+            ("failover_queue_name", "q-dr"),
+            ("failover_threshold", "0"),
+        ]);
+        assert!(SQSConfig::from_link(&ld).is_err());
+    }
+
+    #[test]
+    fn test_dispatch_timeout_options() {
+        let ld = link_with_values(&[("queue_name", "q")]);
+        assert_eq!(SQSConfig::from_link(&ld).unwrap().dispatch_timeout_ms, 0);
+        let ld = link_with_values(&[
+            ("queue_name", "q"),
+            ("dispatch_timeout_ms", "5000"),
+            ("visibility_timeout_seconds", "30"),
+        ]);
+        assert_eq!(SQSConfig::from_link(&ld).unwrap().dispatch_timeout_ms, 5_000);
+        // a timeout that outlives the visibility window invites
+        // double-processing; refuse the link
+        let ld = link_with_values(&[
+            ("queue_name", "q"),
+            ("dispatch_timeout_ms", "30000"),
+            ("visibility_timeout_seconds", "30"),
+        ]);
+        assert!(SQSConfig::from_link(&ld).is_err());
+    }
+
+    #[test]
+    fn test_idle_backoff_options() {
+        let ld = link_with_values(&[("queue_name", "q")]);
+        assert_eq!(SQSConfig::from_link(&ld).unwrap().idle_backoff_ms, 0);
+        let ld = link_with_values(&[("queue_name", "q"), ("idle_backoff_ms", "500")]);
+        assert_eq!(SQSConfig::from_link(&ld).unwrap().idle_backoff_ms, 500);
+        let ld = link_with_values(&[("queue_name", "q"), ("idle_backoff_ms", "60001")]);
+        assert!(SQSConfig::from_link(&ld).is_err());
+    }
+
+    #[test]
+    fn test_idle_notify_options() {
+        let ld = link_with_values(&[("queue_name", "q")]);
+        assert_eq!(SQSConfig::from_link(&ld).unwrap().idle_notify_polls, 0);
+        let ld = link_with_values(&[("queue_name", "q"), ("idle_notify_polls", "3")]);
+        assert_eq!(SQSConfig::from_link(&ld).unwrap().idle_notify_polls, 3);
+    }
+
+    #[test]
+    fn test_message_ttl_options() {
+        let ld = link_with_values(&[("queue_name", "q")]);
+        assert_eq!(SQSConfig::from_link(&ld).unwrap().message_ttl_seconds, 0);
+        let ld = link_with_values(&[("queue_name", "q"), ("message_ttl_seconds", "300")]);
+        assert_eq!(SQSConfig::from_link(&ld).unwrap().message_ttl_seconds, 300);
+        let ld = link_with_values(&[("queue_name", "q"), ("message_ttl_seconds", "soon")]);
+        assert!(SQSConfig::from_link(&ld).is_err());
+    }
+
+    #[test]
+    fn test_subscribe_filter() {
+        let ld = link_with_values(&[
+            ("queue_name", "q"),
+            ("subscribe_filter", "type=order, region=eu"),
+            ("delete_filtered", "true"),
+        ]);
+        let config = SQSConfig::from_link(&ld).unwrap();
+        assert_eq!(config.subscribe_filter.len(), 2);
+        assert_eq!(config.subscribe_filter["type"], "order");
+        assert_eq!(config.subscribe_filter["region"], "eu");
+        assert!(config.delete_filtered);
+
+        // no filter by default, and malformed entries are rejected
+        let ld = link_with_values(&[("queue_name", "q")]);
+        let config = SQSConfig::from_link(&ld).unwrap();
+        assert!(config.subscribe_filter.is_empty());
+        assert!(!config.delete_filtered);
+        let ld = link_with_values(&[("queue_name", "q"), ("subscribe_filter", "type")]);
+        assert!(SQSConfig::from_link(&ld).is_err());
+        let ld = link_with_values(&[("queue_name", "q"), ("subscribe_filter", "=order")]);
+        assert!(SQSConfig::from_link(&ld).is_err());
+    }
+
+    #[test]
+    fn test_publish_acl_options() {
+        let ld = link_with_values(&[("queue_name", "q")]);
+        let config = SQSConfig::from_link(&ld).unwrap();
+        assert!(config.publish_allow.is_empty());
+        assert!(config.publish_deny.is_empty());
+        let ld = link_with_values(&[
+            ("queue_name", "q"),
+            ("publish_allow", "orders, invoices-*"),
+            ("publish_deny", "invoices-internal"),
+        ]);
+        let config = SQSConfig::from_link(&ld).unwrap();
+        assert_eq!(config.publish_allow, vec!["orders", "invoices-*"]);
+        assert_eq!(config.publish_deny, vec!["invoices-internal"]);
+    }
+
+    #[test]
+    fn test_envelope_format_options() {
+        let ld = link_with_values(&[("queue_name", "q")]);
+        assert_eq!(
+            SQSConfig::from_link(&ld).unwrap().envelope_format,
+            EnvelopeFormat::Native
+        );
+        let ld = link_with_values(&[("queue_name", "q"), ("envelope_format", "json")]);
+        assert_eq!(
+            SQSConfig::from_link(&ld).unwrap().envelope_format,
+            EnvelopeFormat::Json
+        );
+        let ld = link_with_values(&[("queue_name", "q"), ("envelope_format", "xml")]);
+        assert!(SQSConfig::from_link(&ld).is_err());
+    }
+
+    #[test]
+    fn test_consumer_count_options() {
+        let ld = link_with_values(&[("queue_name", "q")]);
+        assert_eq!(SQSConfig::from_link(&ld).unwrap().consumer_count, 1);
+        let ld = link_with_values(&[("queue_name", "q"), ("consumer_count", "4")]);
+        assert_eq!(SQSConfig::from_link(&ld).unwrap().consumer_count, 4);
+        for bad in ["0", "65"] {
+            let ld = link_with_values(&[("queue_name", "q"), ("consumer_count", bad)]);
+            assert!(SQSConfig::from_link(&ld).is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn test_queue_url_override() {
+        let url = "https://sqs.us-east-1.amazonaws.com/123456789012/orders";
+        let ld = link_with_values(&[("queue_url", url)]);
+        let config = SQSConfig::from_link(&ld).unwrap();
+        assert_eq!(config.queue_name, url);
+        assert_eq!(config.bindings.len(), 1);
+        assert_eq!(config.bindings[0].name, url);
+        // a bare name would quietly re-enter resolution; reject it
+        let ld = link_with_values(&[("queue_url", "orders")]);
+        assert!(SQSConfig::from_link(&ld).is_err());
+        // resolution and discovery settings contradict the override
+        for conflicting in ["queue_name", "queue_names", "queue_name_prefix"] {
+            let ld = link_with_values(&[("queue_url", url), (conflicting, "orders")]);
+            assert!(SQSConfig::from_link(&ld).is_err(), "{}", conflicting);
+        }
+    }
+
+    #[test]
+    fn test_dedup_from_body_hash_options() {
+        let ld = link_with_values(&[("queue_name", "q.fifo")]);
+        assert!(!SQSConfig::from_link(&ld).unwrap().dedup_from_body_hash);
+        let ld = link_with_values(&[("queue_name", "q.fifo"), ("dedup_from_body_hash", "true")]);
+        assert!(SQSConfig::from_link(&ld).unwrap().dedup_from_body_hash);
+        // two body-hash schemes at once is a contradiction
+        let ld = link_with_values(&[
+            ("queue_name", "q.fifo"),
+            ("dedup_from_body_hash", "true"),
+            ("content_based_deduplication", "true"),
+        ]);
+        assert!(SQSConfig::from_link(&ld).is_err());
+    }
+
+    #[test]
+    fn test_queue_tags() {
+        let ld = link_with_values(&[
+            ("queue_name", "q"),
+            ("queue_tags", "team=platform, cost-center=1234"),
+            ("reconcile_tags", "true"),
+        ]);
+        let config = SQSConfig::from_link(&ld).unwrap();
+        assert_eq!(config.queue_tags.len(), 2);
+        assert_eq!(config.queue_tags["team"], "platform");
+        assert_eq!(config.queue_tags["cost-center"], "1234");
+        assert!(config.reconcile_tags);
+
+        // malformed pairs and oversized keys/values are rejected
+        let ld = link_with_values(&[("queue_name", "q"), ("queue_tags", "team")]);
+        assert!(SQSConfig::from_link(&ld).is_err());
+        let long_key = format!("{}=v", "k".repeat(129));
+        let ld = link_with_values(&[("queue_name", "q"), ("queue_tags", &long_key)]);
+        assert!(SQSConfig::from_link(&ld).is_err());
+        let long_value = format!("k={}", "v".repeat(257));
+        let ld = link_with_values(&[("queue_name", "q"), ("queue_tags", &long_value)]);
+        assert!(SQSConfig::from_link(&ld).is_err());
+
+        // an empty value is an empty map, not an error
+        let ld = link_with_values(&[("queue_name", "q"), ("queue_tags", "")]);
+        assert!(SQSConfig::from_link(&ld).unwrap().queue_tags.is_empty());
+    }
+
+    #[test]
+    fn test_large_payload_options() {
+        // no offloading unless a bucket is named
+        let ld = link_with_values(&[("queue_name", "q")]);
+        let config = SQSConfig::from_link(&ld).unwrap();
+        assert!(config.large_payload_bucket.is_none());
+        assert_eq!(config.large_payload_threshold, 262_144);
+
+        let ld = link_with_values(&[
+            ("queue_name", "q"),
+            ("large_payload_bucket", "overflow-bucket"),
+            ("large_payload_threshold", "65536"),
+        ]);
+        let config = SQSConfig::from_link(&ld).unwrap();
+        assert_eq!(config.large_payload_bucket.as_deref(), Some("overflow-bucket"));
+        assert_eq!(config.large_payload_threshold, 65_536);
+
+        // zero and beyond-the-cap thresholds are misconfigurations
+        for bad in ["0", "262145"] {
+            let ld = link_with_values(&[
+                ("queue_name", "q"),
+                ("large_payload_threshold", bad),
+            ]);
+            assert!(SQSConfig::from_link(&ld).is_err(), "accepted {}", bad);
+        }
+    }
+
+    #[test]
+    fn test_log_body_options() {
+        // off by default, with the stock preview budget
+        let ld = link_with_values(&[("queue_name", "q")]);
+        let config = SQSConfig::from_link(&ld).unwrap();
+        assert!(!config.log_message_bodies);
+        assert_eq!(config.log_body_preview_bytes, 256);
+        assert!(config.log_body_redact_pattern.is_none());
+
+        let ld = link_with_values(&[
+            ("queue_name", "q"),
+            ("log_message_bodies", "true"),
+            ("log_body_preview_bytes", "64"),
+            ("log_body_redact_pattern", r"\d{16}"),
+        ]);
+        let config = SQSConfig::from_link(&ld).unwrap();
+        assert!(config.log_message_bodies);
+        assert_eq!(config.log_body_preview_bytes, 64);
+        assert_eq!(config.log_body_redact_pattern.as_deref(), Some(r"\d{16}"));
+
+        // a pattern that doesn't compile is caught at link time
+        let ld = link_with_values(&[
+            ("queue_name", "q"),
+            ("log_body_redact_pattern", "(unclosed"),
+        ]);
+        assert!(SQSConfig::from_link(&ld).is_err());
+    }
+
+    #[test]
+    fn test_queue_name_prefix_options() {
+        let ld = link_with_values(&[("queue_name", "q")]);
+        let config = SQSConfig::from_link(&ld).unwrap();
+        assert_eq!(config.queue_name_prefix, None);
+        assert_eq!(config.prefix_refresh_seconds, 300);
+
+        let ld = link_with_values(&[
+            ("queue_name", "q"),
+            ("queue_name_prefix", "tenant-"),
+            ("prefix_refresh_seconds", "60"),
+        ]);
+        let config = SQSConfig::from_link(&ld).unwrap();
+        assert_eq!(config.queue_name_prefix.as_deref(), Some("tenant-"));
+        assert_eq!(config.prefix_refresh_seconds, 60);
+
+        let ld = link_with_values(&[("queue_name", "q"), ("prefix_refresh_seconds", "0")]);
+        assert!(SQSConfig::from_link(&ld).is_err());
+    }
+
+    #[test]
+    fn test_dispatch_error_policy() {
+        let ld = link_with_values(&[("queue_name", "q")]);
+        let config = SQSConfig::from_link(&ld).unwrap();
+        assert_eq!(config.dispatch_error_policy, DispatchErrorPolicy::Redeliver);
+
+        let ld = link_with_values(&[("queue_name", "q"), ("dispatch_error_policy", "fast_retry")]);
+        let config = SQSConfig::from_link(&ld).unwrap();
+        assert_eq!(config.dispatch_error_policy, DispatchErrorPolicy::FastRetry);
+
+        let ld = link_with_values(&[("queue_name", "q"), ("dispatch_error_policy", "drop")]);
+        let config = SQSConfig::from_link(&ld).unwrap();
+        assert_eq!(config.dispatch_error_policy, DispatchErrorPolicy::Drop);
+
+        let ld = link_with_values(&[("queue_name", "q"), ("dispatch_error_policy", "retry")]);
+        assert!(SQSConfig::from_link(&ld).is_err());
+    }
+
+    #[test]
+    fn test_group_id_from_options() {
+        let ld = link_with_values(&[("queue_name", "q.fifo"), ("group_id_from", "subject")]);
+        let config = SQSConfig::from_link(&ld).unwrap();
+        assert_eq!(config.group_id_from, GroupIdStrategy::Subject);
+
+        // static derivation is meaningless without a default group id
+        let ld = link_with_values(&[("queue_name", "q.fifo"), ("group_id_from", "static")]);
+        assert!(SQSConfig::from_link(&ld).is_err());
+        let ld = link_with_values(&[
+            ("queue_name", "q.fifo"),
+            ("group_id_from", "static"),
+            ("default_message_group_id", "orders"),
+        ]);
+        let config = SQSConfig::from_link(&ld).unwrap();
+        assert_eq!(config.default_message_group_id.as_deref(), Some("orders"));
+
+        let ld = link_with_values(&[("queue_name", "q.fifo"), ("group_id_from", "sometimes")]);
+        assert!(SQSConfig::from_link(&ld).is_err());
+        let ld = link_with_values(&[
+            ("queue_name", "q.fifo"),
+            ("default_message_group_id", "not a group id"),
+        ]);
+        assert!(SQSConfig::from_link(&ld).is_err());
+    }
+
+    #[test]
+    fn test_enable_sns_publish() {
+        let ld = link_with_values(&[("queue_name", "q"), ("enable_sns_publish", "true")]);
+        let config = SQSConfig::from_link(&ld).unwrap();
+        assert!(config.enable_sns_publish);
+        let ld = link_with_values(&[("queue_name", "q")]);
+        let config = SQSConfig::from_link(&ld).unwrap();
+        assert!(!config.enable_sns_publish);
+    }
+
+    #[test]
+    fn test_kms_options() {
+        let ld = link_with_values(&[
+            ("queue_name", "q"),
+            ("kms_master_key_id", "alias/my-key"),
+            ("kms_data_key_reuse_period", "300"),
+        ]);
+        let config = SQSConfig::from_link(&ld).unwrap();
+        assert_eq!(config.kms_master_key_id.as_deref(), Some("alias/my-key"));
+        assert_eq!(config.kms_data_key_reuse_period, Some(300));
+
+        // a reuse period without a key id is a configuration mistake
+        let ld = link_with_values(&[("queue_name", "q"), ("kms_data_key_reuse_period", "300")]);
+        assert!(SQSConfig::from_link(&ld).is_err());
+
+        // the period must sit inside the range sqs accepts
+        for bad in ["59", "86401"] {
+            let ld = link_with_values(&[
+                ("queue_name", "q"),
+                ("kms_master_key_id", "alias/my-key"),
+                ("kms_data_key_reuse_period", bad),
+            ]);
+            assert!(SQSConfig::from_link(&ld).is_err(), "accepted {}", bad);
+        }
+    }
+
+    #[test]
+    fn test_retention_and_delay_bounds() {
+        let ld = link_with_values(&[
+            ("queue_name", "orders"),
+            ("message_retention_seconds", "3600"),
+            ("delay_seconds", "30"),
+        ]);
+        let config = SQSConfig::from_link(&ld).unwrap();
+        assert_eq!(config.message_retention_seconds, 3600);
+        assert_eq!(config.delay_seconds, 30);
+
+        // defaults track what sqs itself would use
+        let ld = link_with_values(&[("queue_name", "orders")]);
+        let config = SQSConfig::from_link(&ld).unwrap();
+        assert_eq!(config.message_retention_seconds, 345_600);
+        assert_eq!(config.delay_seconds, 0);
+
+        for (key, bad) in [
+            ("message_retention_seconds", "59"),
+            ("message_retention_seconds", "1209601"),
+            ("delay_seconds", "-1"),
+            ("delay_seconds", "901"),
+        ] {
+            let ld = link_with_values(&[("queue_name", "orders"), (key, bad)]);
+            assert!(SQSConfig::from_link(&ld).is_err());
+        }
+    }
+
+    #[test]
+    fn test_max_receive_count_bounds() {
+        let ld = link_with_values(&[
+            ("queue_name", "orders"),
+            ("dead_letter_queue_name", "orders-dlq"),
+            ("max_receive_count", "3"),
+        ]);
+        let config = SQSConfig::from_link(&ld).unwrap();
+        assert_eq!(config.dead_letter_queue_name.as_deref(), Some("orders-dlq"));
+        assert_eq!(config.max_receive_count, 3);
+
+        for bad in ["0", "1001"] {
+            let ld = link_with_values(&[("queue_name", "orders"), ("max_receive_count", bad)]);
+            assert!(SQSConfig::from_link(&ld).is_err());
+        }
+    }
+
+    #[test]
+    fn test_queue_names_weights() {
+        use super::{QueueBinding, QueueRole};
+        let ld = link_with_values(&[("queue_names", "orders:subscribe:3, audit:subscribe")]);
+        let config = SQSConfig::from_link(&ld).unwrap();
+        assert_eq!(
+            config.bindings,
+            vec![
+                QueueBinding {
+                    name: String::from("orders"),
+                    role: QueueRole::Subscribe,
+                    weight: 3,
+                    endpoint_url: None,
+                },
+                QueueBinding {
+                    name: String::from("audit"),
+                    role: QueueRole::Subscribe,
+                    weight: 1,
+                    endpoint_url: None,
+                },
+            ]
+        );
+
+        // weights must be positive integers, and still need a valid role
+        for bad in ["orders:subscribe:0", "orders:subscribe:fast", "orders:often:2"] {
+            let ld = link_with_values(&[("queue_names", bad)]);
+            assert!(SQSConfig::from_link(&ld).is_err(), "accepted {}", bad);
+        }
+    }
+
+    #[test]
+    fn test_queue_endpoints() {
+        let ld = link_with_values(&[
+            ("queue_names", "orders:publish, audit:subscribe"),
+            ("queue_endpoints", "audit=http://localhost:4567"),
+            ("endpoint_url", "http://localhost:4566"),
+        ]);
+        let config = SQSConfig::from_link(&ld).unwrap();
+        assert_eq!(config.bindings[0].endpoint_url, None);
+        assert_eq!(
+            config.bindings[1].endpoint_url.as_deref(),
+            Some("http://localhost:4567")
+        );
+
+        // the override also applies to the implicit single-queue binding
+        let ld = link_with_values(&[
+            ("queue_name", "orders"),
+            ("queue_endpoints", "orders=http://localhost:4567"),
+        ]);
+        let config = SQSConfig::from_link(&ld).unwrap();
+        assert_eq!(
+            config.bindings[0].endpoint_url.as_deref(),
+            Some("http://localhost:4567")
+        );
+
+        // malformed entries and unbound queue names are rejected
+        let ld = link_with_values(&[
+            ("queue_name", "orders"),
+            ("queue_endpoints", "http://localhost:4567"),
+        ]);
+        assert!(SQSConfig::from_link(&ld).is_err());
+        let ld = link_with_values(&[
+            ("queue_name", "orders"),
+            ("queue_endpoints", "refunds=http://localhost:4567"),
+        ]);
+        assert!(SQSConfig::from_link(&ld).is_err());
+    }
+
+    #[test]
+    fn test_queue_names_roles() {
+        use super::{QueueBinding, QueueRole};
+        let ld = link_with_values(&[(
+            "queue_names",
+            "orders:publish, audit:subscribe,logs",
+        )]);
+        let config = SQSConfig::from_link(&ld).unwrap();
+        assert_eq!(
+            config.bindings,
+            vec![
+                QueueBinding {
+                    name: String::from("orders"),
+                    role: QueueRole::Publish,
+                    weight: 1,
+                    endpoint_url: None,
+                },
+                QueueBinding {
+                    name: String::from("audit"),
+                    role: QueueRole::Subscribe,
+                    weight: 1,
+                    endpoint_url: None,
+                },
+                QueueBinding {
+                    name: String::from("logs"),
+                    role: QueueRole::Both,
+                    weight: 1,
+                    endpoint_url: None,
+                },
+            ]
+        );
+        // the primary queue falls back to the first publish-role binding
+        assert_eq!(config.queue_name, "orders");
+
+        // a bare queue_name still becomes a single both-role binding
+        let ld = link_with_values(&[("queue_name", "orders")]);
+        let config = SQSConfig::from_link(&ld).unwrap();
+        assert_eq!(config.bindings.len(), 1);
+        assert!(config.bindings[0].publishes() && config.bindings[0].subscribes());
+
+        let ld = link_with_values(&[("queue_names", "orders:everything")]);
+        assert!(SQSConfig::from_link(&ld).is_err());
+    }
+
+    #[test]
+    fn test_endpoint_url() {
+        // a local sqs stand-in is the typical use for an endpoint override
+        let ld = link_with_values(&[
+            ("queue_name", "orders"),
+            ("endpoint_url", "http://localhost:4566"),
+        ]);
+        let config = SQSConfig::from_link(&ld).unwrap();
+        assert_eq!(config.endpoint_url.as_deref(), Some("http://localhost:4566"));
+        assert!(config.endpoint().unwrap().is_some());
+
+        let ld = link_with_values(&[("queue_name", "orders"), ("endpoint_url", "ht tp://bad")]);
+        assert!(SQSConfig::from_link(&ld).is_err());
+    }
+
+    #[test]
+    fn test_visibility_timeout_bounds() {
+        let ld = link_with_values(&[("queue_name", "orders")]);
+        assert_eq!(SQSConfig::from_link(&ld).unwrap().visibility_timeout_seconds, None);
+
+        let ld = link_with_values(&[("queue_name", "orders"), ("visibility_timeout_seconds", "30")]);
+        assert_eq!(
+            SQSConfig::from_link(&ld).unwrap().visibility_timeout_seconds,
+            Some(30)
+        );
+
+        for bad in ["-1", "43201"] {
+            let ld =
+                link_with_values(&[("queue_name", "orders"), ("visibility_timeout_seconds", bad)]);
+            assert!(SQSConfig::from_link(&ld).is_err());
+        }
+    }
+
+    #[test]
+    fn test_max_number_of_messages_bounds() {
+        let ld = link_with_values(&[("queue_name", "orders"), ("max_number_of_messages", "10")]);
+        assert_eq!(SQSConfig::from_link(&ld).unwrap().max_number_of_messages, 10);
+
+        let ld = link_with_values(&[("queue_name", "orders")]);
+        assert_eq!(SQSConfig::from_link(&ld).unwrap().max_number_of_messages, 1);
+
+        for bad in ["0", "11", "-1"] {
+            let ld = link_with_values(&[("queue_name", "orders"), ("max_number_of_messages", bad)]);
+            let err = SQSConfig::from_link(&ld)
+                .expect_err("out-of-range batch size should be rejected");
+            assert!(err.to_string().contains("max_number_of_messages"));
+        }
+    }
+
+    #[test]
+    fn test_serde_default_booleans() {
+        // booleans default to false when absent from json as well
+        let config: SQSConfig = serde_json::from_str(r#"{"queue_name": "orders"}"#).unwrap();
+        assert!(!config.create_queue_if_missing);
+        assert!(!config.message_auto_delete);
+        assert_eq!(config.wait_time_seconds, DEFAULT_WAIT_TIME_SECONDS);
+    }
+}
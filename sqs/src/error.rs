@@ -0,0 +1,141 @@
+//! Structured errors for the sqs capability provider, mapped onto the rpc
+//! error variants the host understands.
+//!
+use wasmbus_rpc::error::RpcError;
+
+/// Everything that can go wrong between a link definition and a message on
+/// the wire. Each variant carries the context a log line or test needs;
+/// converting to [`RpcError`] picks the variant the host treats correctly
+/// (deny vs retry vs caller error).
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) enum SqsProviderError {
+    /// a required setting or identity was absent from the invocation
+    ConfigMissing(String),
+    /// the aws client could not be constructed for a link
+    ClientBuild(String),
+    /// a queue named by a link or subject does not resolve to a url
+    QueueNotFound(String),
+    /// sqs failed to hand over messages
+    ReceiveFailed(String),
+    /// sqs (or sns) refused a publish
+    SendFailed(String),
+    /// the linked actor's handler returned an error
+    DispatchFailed(String),
+    /// the link's publish rate limit rejected or timed out a publish
+    Throttled(String),
+    /// the link's publish acl denies the requested subject
+    PublishDenied(String),
+}
+
+impl std::fmt::Display for SqsProviderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SqsProviderError::ConfigMissing(context) => {
+                write!(f, "missing configuration: {}", context)
+            }
+            SqsProviderError::ClientBuild(context) => {
+                write!(f, "unable to build aws client: {}", context)
+            }
+            SqsProviderError::QueueNotFound(context) => {
+                write!(f, "queue not found: {}", context)
+            }
+            SqsProviderError::ReceiveFailed(context) => {
+                write!(f, "sqs receive failed: {}", context)
+            }
+            SqsProviderError::SendFailed(context) => {
+                write!(f, "send failed: {}", context)
+            }
+            SqsProviderError::DispatchFailed(context) => {
+                write!(f, "actor failed to handle message: {}", context)
+            }
+            SqsProviderError::Throttled(context) => {
+                write!(f, "publish throttled: {}", context)
+            }
+            SqsProviderError::PublishDenied(context) => {
+                write!(f, "publish not authorized: {}", context)
+            }
+        }
+    }
+}
+
+impl std::error::Error for SqsProviderError {}
+
+impl From<SqsProviderError> for RpcError {
+    fn from(e: SqsProviderError) -> Self {
+        let text = e.to_string();
+        match e {
+            // misconfiguration can never succeed on retry
+            SqsProviderError::ClientBuild(_) => RpcError::ProviderInit(text),
+            // the caller named something that doesn't exist or may not use
+            SqsProviderError::ConfigMissing(_)
+            | SqsProviderError::QueueNotFound(_)
+            | SqsProviderError::PublishDenied(_) => RpcError::InvalidParameter(text),
+            // transient aws or actor failures, worth retrying
+            SqsProviderError::ReceiveFailed(_)
+            | SqsProviderError::SendFailed(_)
+            | SqsProviderError::DispatchFailed(_)
+            | SqsProviderError::Throttled(_) => RpcError::Other(text),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::SqsProviderError;
+    use wasmbus_rpc::error::RpcError;
+
+    #[test]
+    fn test_variant_rpc_mapping() {
+        let cases = [
+            (
+                SqsProviderError::ConfigMissing(String::from("no actor in request")),
+                RpcError::InvalidParameter(String::new()),
+            ),
+            (
+                SqsProviderError::ClientBuild(String::from("unable to assume role")),
+                RpcError::ProviderInit(String::new()),
+            ),
+            (
+                SqsProviderError::QueueNotFound(String::from("subject 'orders'")),
+                RpcError::InvalidParameter(String::new()),
+            ),
+            (
+                SqsProviderError::ReceiveFailed(String::from("throttled")),
+                RpcError::Other(String::new()),
+            ),
+            (
+                SqsProviderError::SendFailed(String::from("access denied")),
+                RpcError::Other(String::new()),
+            ),
+            (
+                SqsProviderError::DispatchFailed(String::from("handler panicked")),
+                RpcError::Other(String::new()),
+            ),
+            (
+                SqsProviderError::Throttled(String::from("limit of 10/s exceeded")),
+                RpcError::Other(String::new()),
+            ),
+            (
+                SqsProviderError::PublishDenied(String::from("subject 'other-tenant'")),
+                RpcError::InvalidParameter(String::new()),
+            ),
+        ];
+        for (error, expected) in cases {
+            let mapped = RpcError::from(error);
+            assert_eq!(
+                std::mem::discriminant(&mapped),
+                std::mem::discriminant(&expected),
+                "wrong rpc variant for {:?}",
+                mapped
+            );
+        }
+    }
+
+    #[test]
+    fn test_display_carries_context() {
+        let error = SqsProviderError::QueueNotFound(String::from("subject 'orders'"));
+        assert_eq!(error.to_string(), "queue not found: subject 'orders'");
+        let mapped = RpcError::from(error);
+        assert!(mapped.to_string().contains("subject 'orders'"));
+    }
+}
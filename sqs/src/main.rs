@@ -0,0 +1,6723 @@
+//! AWS SQS implementation for wasmcloud:messaging.
+//!
+use std::{
+    collections::HashMap,
+    convert::Infallible,
+    sync::atomic::{AtomicBool, AtomicU64, Ordering},
+    sync::Arc,
+    time::Duration,
+};
+
+use aws_sdk_s3 as s3;
+use aws_sdk_sns as sns;
+use aws_sdk_sqs as sqs;
+use serde::{Deserialize, Serialize};
+use tokio::sync::{mpsc, RwLock};
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, error, instrument, warn, Instrument};
+use wasmbus_rpc::{
+    core::{HealthCheckRequest, HealthCheckResponse, LinkDefinition},
+    provider::prelude::*,
+};
+use wasmcloud_interface_messaging::{
+    MessageSubscriber, MessageSubscriberSender, Messaging, MessagingReceiver, PubMessage,
+    ReplyMessage, RequestMessage, SubMessage,
+};
+
+mod config;
+mod error;
+use config::{
+    BodyEncoding, DeliveryMode, DispatchErrorPolicy, EnvelopeFormat, GroupIdStrategy, QueueBinding,
+    QueueRole, RateLimitBehavior, SQSConfig,
+};
+use error::SqsProviderError;
+
+/// envelope attribute carrying the x-ray trace header, both on publish (the
+/// caller's header wins over the ambient environment) and on receive
+const XRAY_TRACE_ATTRIBUTE: &str = "aws_trace_header";
+/// env var the x-ray sdk and lambda runtime use for the active trace header
+const XRAY_TRACE_ENV: &str = "_X_AMZN_TRACE_ID";
+
+/// message attribute carrying the caller's correlation id, surfaced on the
+/// dispatch span so traces line up across services
+const CORRELATION_ATTRIBUTE: &str = "correlation-id";
+
+/// env var naming an actor to fall back on when an invocation carries no
+/// actor identity. A transition crutch for hosts that don't propagate the
+/// context yet: its use is always warned about, and it should go away once
+/// no deployment needs it.
+const DEFAULT_ACTOR_ENV: &str = "SQS_DEFAULT_ACTOR";
+
+/// how long one health probe's verdict is served before sqs is asked again
+const HEALTH_CACHE_TTL: Duration = Duration::from_secs(10);
+
+/// first delay of the receive loop's failure backoff
+const RECEIVE_BACKOFF_BASE_MS: u64 = 100;
+
+/// message attribute marking an offloaded message: its value is the original
+/// payload size and the body is an s3 pointer instead of the payload
+const S3_POINTER_ATTRIBUTE: &str = "extended_payload_size";
+
+/// the most bytes sqs accepts in one message, counting the body and every
+/// attribute's name, type and value; the same cap applies to fifo queues
+const SQS_MAX_MESSAGE_BYTES: usize = 262_144;
+
+/// most entries sqs accepts in a single send_message_batch call
+const MAX_BATCH_ENTRIES: usize = 10;
+/// publishes that can be waiting for the flusher before publish backpressures
+const BATCH_CHANNEL_CAPACITY: usize = 1024;
+
+/// envelope attribute naming the fifo message group for a publish
+const GROUP_ID_ATTRIBUTE: &str = "message_group_id";
+/// envelope attribute carrying an explicit fifo deduplication id
+const DEDUP_ID_ATTRIBUTE: &str = "message_deduplication_id";
+
+/// w3c trace context message attributes carried across the sqs hop
+const TRACEPARENT_ATTRIBUTE: &str = "traceparent";
+const TRACESTATE_ATTRIBUTE: &str = "tracestate";
+
+/// publishing to this subject purges the link's queue instead of sending a
+/// message; only honored when the link sets allow_purge
+const CONTROL_PURGE_SUBJECT: &str = "__control/purge";
+/// publishing to this subject pauses the link's receive loops for
+/// maintenance without tearing down the link; publishes keep flowing
+const CONTROL_PAUSE_SUBJECT: &str = "__control/pause";
+/// publishing to this subject resumes receive loops parked by
+/// [`CONTROL_PAUSE_SUBJECT`]
+const CONTROL_RESUME_SUBJECT: &str = "__control/resume";
+/// how often a paused receive loop re-checks whether it has been resumed
+const PAUSE_POLL_MS: u64 = 500;
+/// requesting on this subject returns the queue's approximate depth as a
+/// json [`QueueDepth`] instead of consuming a message
+const CONTROL_DEPTH_SUBJECT: &str = "__control/depth";
+/// request subject returning the ids sqs assigned to this link's most recent
+/// direct publish, since the messaging contract discards them
+const CONTROL_LAST_PUBLISH_SUBJECT: &str = "__control/last_publish";
+/// request subject that replays quarantined messages from the link's dead
+/// letter queue back onto its main queue, gated by allow_dlq_replay. The
+/// request body optionally carries the maximum number of messages to move.
+const CONTROL_DLQ_REPLAY_SUBJECT: &str = "__control/dlq_replay";
+/// how many messages a dlq replay moves when the request body names no limit
+const DEFAULT_DLQ_REPLAY_LIMIT: u64 = 100;
+/// subject of the synthetic message dispatched to an actor when its queue
+/// has been empty for idle_notify_polls consecutive polls
+const EVENT_QUEUE_IDLE_SUBJECT: &str = "__event/queue_idle";
+
+/// envelope attribute surfacing how many times sqs has delivered a message
+const RECEIVE_COUNT_ATTRIBUTE: &str = "approximate_receive_count";
+/// envelope attribute surfacing when sqs first accepted a message (epoch ms)
+const SENT_TIMESTAMP_ATTRIBUTE: &str = "sent_timestamp";
+/// envelope attribute surfacing the id sqs assigned to a delivered message
+const MESSAGE_ID_ATTRIBUTE: &str = "message_id";
+/// envelope attribute surfacing which queue a delivered message came from,
+/// for actors linked to several queues through one provider
+const SOURCE_QUEUE_ATTRIBUTE: &str = "source_queue";
+
+/// envelope attribute carrying a per-message delivery delay in seconds
+const DELAY_ATTRIBUTE: &str = "delay_seconds";
+/// message attribute naming the epoch second after which a message is stale;
+/// the receive loop deletes stamped messages past it instead of dispatching
+const EXPIRES_AT_ATTRIBUTE: &str = "expires-at";
+/// envelope attribute some sdk extended clients use for an initial message
+/// timer; rejected here because sqs cannot honor it (see
+/// [`reject_initial_visibility`])
+const INITIAL_VISIBILITY_ATTRIBUTE: &str = "initial_visibility_seconds";
+
+/// message attribute naming the queue a reply to this message should go to
+const REPLY_TO_ATTRIBUTE: &str = "reply_to";
+
+/// message attribute marking how the body was encoded on the wire
+const ENCODING_ATTRIBUTE: &str = "wasmcloud.body.encoding";
+const ENCODING_UTF8: &str = "utf8";
+const ENCODING_BASE64: &str = "base64";
+/// standard transport-encoding attribute set alongside [`ENCODING_ATTRIBUTE`]
+/// on base64 bodies, and honored on receive, so non-wasmcloud producers and
+/// consumers can interoperate with binary payloads
+const CONTENT_TRANSFER_ENCODING_ATTRIBUTE: &str = "content-transfer-encoding";
+
+/// env var holding link values (a json object of setting -> value) to
+/// validate instead of starting the provider; see [`validate_link_values`]
+const VALIDATE_CONFIG_ENV: &str = "SQS_VALIDATE_CONFIG";
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // dry-run mode: validate a link config and report instead of serving.
+    // operators catch region/queue typos here before a deploy links anything
+    if let Ok(raw) = std::env::var(VALIDATE_CONFIG_ENV) {
+        let values: HashMap<String, String> = serde_json::from_str(&raw)
+            .map_err(|e| format!("{} must be a json object of link values: {}", VALIDATE_CONFIG_ENV, e))?;
+        let report = tokio::runtime::Runtime::new()?.block_on(validate_link_values(&values));
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        std::process::exit(if report.passed { 0 } else { 1 });
+    }
+    // handle lattice control messages and forward rpc to the provider dispatch
+    // returns when provider receives a shutdown control message
+    provider_main(
+        SqsMessagingProvider::default(),
+        Some("SQS Messaging Provider".to_string()),
+    )?;
+
+    eprintln!("SQS messaging provider exiting");
+    Ok(())
+}
+
+/// One step of a dry-run validation: what was checked and how it went
+#[derive(Debug, Serialize)]
+struct ValidationCheck {
+    name: String,
+    passed: bool,
+    detail: String,
+}
+
+/// The full dry-run report, one check per stage. `passed` is the rollup an
+/// exit code or CI gate wants; the checks carry the specifics.
+#[derive(Debug, Serialize)]
+struct ValidationReport {
+    passed: bool,
+    checks: Vec<ValidationCheck>,
+}
+
+fn check(name: impl Into<String>, result: Result<String, String>) -> ValidationCheck {
+    let name = name.into();
+    match result {
+        Ok(detail) => ValidationCheck {
+            name,
+            passed: true,
+            detail,
+        },
+        Err(detail) => ValidationCheck {
+            name,
+            passed: false,
+            detail,
+        },
+    }
+}
+
+/// Validate a set of link values the way put_link would, without linking:
+/// parse the config, build the aws client, and probe each bound queue with a
+/// read-only get_queue_attributes. Nothing is created or mutated - a queue
+/// that create_queue_if_missing would create still reports as missing here.
+async fn validate_link_values(values: &HashMap<String, String>) -> ValidationReport {
+    let mut checks = Vec::new();
+    let mut ld = LinkDefinition::default();
+    ld.actor_id = String::from("dry-run");
+    ld.link_name = String::from("default");
+    ld.contract_id = String::from("wasmcloud:messaging");
+    ld.values = values.clone();
+    let config = match SQSConfig::from_link(&ld) {
+        Ok(config) => {
+            checks.push(check("config", Ok(String::from("link values parsed"))));
+            config
+        }
+        Err(e) => {
+            checks.push(check("config", Err(e.to_string())));
+            return ValidationReport {
+                passed: false,
+                checks,
+            };
+        }
+    };
+    let client = match SqsMessagingProvider::build_client(&config).await {
+        Ok(client) => {
+            checks.push(check("client", Ok(String::from("aws client built"))));
+            Some(client)
+        }
+        Err(e) => {
+            checks.push(check("client", Err(e.to_string())));
+            None
+        }
+    };
+    if let Some(client) = client {
+        for binding in &config.bindings {
+            let name = format!("queue '{}'", binding.name);
+            checks.push(check(name, probe_queue(&client, &config, &binding.name).await));
+        }
+    }
+    ValidationReport {
+        passed: checks.iter().all(|c| c.passed),
+        checks,
+    }
+}
+
+/// Resolve and probe one queue read-only for the dry-run report
+async fn probe_queue(
+    client: &sqs::Client,
+    config: &SQSConfig,
+    queue_name: &str,
+) -> Result<String, String> {
+    let queue_url = match queue_url_from_identifier(queue_name).map_err(|e| e.to_string())? {
+        Some(queue_url) => {
+            // same mismatch resolve_queue rejects at link time: a url in the
+            // wrong region fails every call with an opaque signature error
+            if let (Some(url_region), Some(client_region)) =
+                (queue_url_region(&queue_url), config.aws_region.as_deref())
+            {
+                if url_region != client_region {
+                    return Err(format!(
+                        "queue is in region {} but aws_region is {}",
+                        url_region, client_region
+                    ));
+                }
+            }
+            queue_url
+        }
+        None => client
+            .get_queue_url()
+            .queue_name(queue_name)
+            .set_queue_owner_aws_account_id(config.queue_owner_account_id.clone())
+            .send()
+            .await
+            .map_err(|e| format!("get_queue_url failed: {}", sdk_error_string(&e)))?
+            .queue_url()
+            .map(|u| u.to_string())
+            .ok_or_else(|| String::from("get_queue_url returned no url"))?,
+    };
+    client
+        .get_queue_attributes()
+        .queue_url(&queue_url)
+        .attribute_names(sqs::model::QueueAttributeName::QueueArn)
+        .send()
+        .await
+        .map_err(|e| format!("get_queue_attributes failed: {}", sdk_error_string(&e)))?;
+    Ok(format!("reachable at {}", queue_url))
+}
+
+/// Encode a message body for sqs, which only carries text. The default
+/// (`auto`) sends valid utf-8 as-is and base64-encodes anything else, marking
+/// it via [`ENCODING_ATTRIBUTE`] so receivers can round-trip arbitrary bytes;
+/// links can pin the strategy to `utf8` (reject binary) or `base64` (always
+/// encode) instead.
+fn encode_body(body: &[u8], mode: BodyEncoding) -> RpcResult<(String, &'static str)> {
+    match mode {
+        BodyEncoding::Base64 => Ok((base64::encode(body), ENCODING_BASE64)),
+        BodyEncoding::Utf8 => match std::str::from_utf8(body) {
+            Ok(s) => Ok((s.to_string(), ENCODING_UTF8)),
+            Err(e) => Err(RpcError::InvalidParameter(format!(
+                "body_encoding is utf8 but the payload is not valid utf-8: {}",
+                e
+            ))),
+        },
+        BodyEncoding::Auto => Ok(match std::str::from_utf8(body) {
+            Ok(s) => (s.to_string(), ENCODING_UTF8),
+            Err(_) => (base64::encode(body), ENCODING_BASE64),
+        }),
+    }
+}
+
+/// Decode a received sqs message body, honoring the encoding attribute set by
+/// [`encode_body`]. Messages without the attribute are treated as plain text.
+fn decode_body(message: &sqs::model::Message) -> RpcResult<Vec<u8>> {
+    let body = message.body().unwrap_or_default();
+    let attrs = message.message_attributes();
+    let encoding = attrs
+        .and_then(|attrs| attrs.get(ENCODING_ATTRIBUTE))
+        .and_then(|attr| attr.string_value())
+        .or_else(|| {
+            // foreign producers mark binary bodies with the standard
+            // content-transfer-encoding convention instead of ours
+            attrs
+                .and_then(|attrs| attrs.get(CONTENT_TRANSFER_ENCODING_ATTRIBUTE))
+                .and_then(|attr| attr.string_value())
+                .filter(|value| value.eq_ignore_ascii_case(ENCODING_BASE64))
+        })
+        .unwrap_or(ENCODING_UTF8);
+    match encoding {
+        ENCODING_BASE64 => base64::decode(body).map_err(|e| {
+            RpcError::Other(format!(
+                "message is marked base64 but the body failed to decode: {}",
+                e
+            ))
+        }),
+        _ => Ok(body.as_bytes().to_vec()),
+    }
+}
+
+/// Approximate backlog of a queue, answered on [`CONTROL_DEPTH_SUBJECT`]
+/// requests so actors can shed load or scale on their own signal. The counts
+/// are sqs's own approximations and may lag slightly.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct QueueDepth {
+    /// messages available for receive (ApproximateNumberOfMessages)
+    messages: u64,
+    /// messages leased out and not yet deleted (..NotVisible)
+    not_visible: u64,
+    /// messages still inside their delivery delay (..Delayed)
+    delayed: u64,
+}
+
+/// The ids sqs (or sns) assigned to the most recent publish on a link,
+/// answered on [`CONTROL_LAST_PUBLISH_SUBJECT`] requests. `Messaging::publish`
+/// returns nothing, so actors that need to correlate or dedupe query this
+/// instead. Batched publishes are not recorded: their send happens after the
+/// publish call has already returned.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+struct LastPublish {
+    /// destination the message went to: a queue url or a topic arn
+    destination: String,
+    /// MessageId assigned by the service
+    message_id: Option<String>,
+    /// SequenceNumber, present only for fifo queues and topics
+    sequence_number: Option<String>,
+}
+
+/// Pull the three depth counters out of a get_queue_attributes response;
+/// missing or unparsable values count as zero rather than failing the query
+fn depth_from_attributes(
+    attributes: Option<&HashMap<sqs::model::QueueAttributeName, String>>,
+) -> QueueDepth {
+    let count = |name: sqs::model::QueueAttributeName| {
+        attributes
+            .and_then(|attrs| attrs.get(&name))
+            .and_then(|value| value.parse().ok())
+            .unwrap_or_default()
+    };
+    QueueDepth {
+        messages: count(sqs::model::QueueAttributeName::ApproximateNumberOfMessages),
+        not_visible: count(sqs::model::QueueAttributeName::ApproximateNumberOfMessagesNotVisible),
+        delayed: count(sqs::model::QueueAttributeName::ApproximateNumberOfMessagesDelayed),
+    }
+}
+
+/// True when a publish subject names an sns topic rather than a queue
+fn is_sns_topic_arn(subject: &str) -> bool {
+    subject.starts_with("arn:") && subject.split(':').nth(2) == Some("sns")
+}
+
+/// Shape of an sns notification as delivered into a subscribed sqs queue.
+/// Only the fields the provider cares about; everything else is ignored.
+#[derive(Debug, Deserialize)]
+struct SnsEnvelope {
+    #[serde(rename = "Type")]
+    kind: String,
+    #[serde(rename = "Message")]
+    message: String,
+    #[serde(rename = "MessageAttributes", default)]
+    attributes: HashMap<String, SnsAttributeValue>,
+}
+
+/// one sns message attribute; the type marker is ignored, sns delivers
+/// everything stringly anyway
+#[derive(Debug, Deserialize)]
+struct SnsAttributeValue {
+    #[serde(rename = "Value")]
+    value: String,
+}
+
+/// Unwrap an sns notification body into the inner message and its
+/// attributes. None when the body isn't an sns notification, letting the
+/// caller fall back to the raw body.
+fn unwrap_sns_notification(body: &[u8]) -> Option<(Vec<u8>, HashMap<String, String>)> {
+    let envelope: SnsEnvelope = serde_json::from_slice(body).ok()?;
+    if envelope.kind != "Notification" {
+        return None;
+    }
+    let attributes = envelope
+        .attributes
+        .into_iter()
+        .map(|(name, attribute)| (name, attribute.value))
+        .collect();
+    Some((envelope.message.into_bytes(), attributes))
+}
+
+/// Well-known json envelope letting actors attach sqs message attributes to a
+/// publish and read them back on receive, since the wasmcloud:messaging types
+/// carry no attribute field of their own. A published body of the form
+/// `{"attributes": {"content-type": "..."}, "body": "..."}` has its attributes
+/// set on the sqs message; received messages with attributes are delivered in
+/// the same shape. Attributes on binary (non utf-8) payloads are dropped.
+#[derive(Debug, Serialize, Deserialize)]
+struct MessageEnvelope {
+    attributes: HashMap<String, String>,
+    body: String,
+    /// attributes whose declared sqs data type is not String; kept apart so
+    /// plain string attributes stay the simple map they have always been
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    typed_attributes: Vec<TypedAttribute>,
+}
+
+/// One message attribute with its declared sqs data type preserved: Number
+/// (and custom `Number.*` labels) carry their text in `value`, Binary (and
+/// `Binary.*`) carry base64 in `binary_value`
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+struct TypedAttribute {
+    name: String,
+    data_type: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    value: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    binary_value: Option<String>,
+}
+
+/// Turn an envelope's typed attribute into the sqs value it declares,
+/// decoding base64 for the binary types
+fn typed_attribute_value(attr: &TypedAttribute) -> RpcResult<sqs::model::MessageAttributeValue> {
+    let builder = sqs::model::MessageAttributeValue::builder().data_type(&attr.data_type);
+    if attr.data_type.starts_with("Binary") {
+        let encoded = attr.binary_value.as_deref().ok_or_else(|| {
+            RpcError::InvalidParameter(format!(
+                "binary attribute '{}' has no binary_value",
+                attr.name
+            ))
+        })?;
+        let bytes = base64::decode(encoded).map_err(|e| {
+            RpcError::InvalidParameter(format!(
+                "binary attribute '{}' is not valid base64: {}",
+                attr.name, e
+            ))
+        })?;
+        Ok(builder.binary_value(sqs::types::Blob::new(bytes)).build())
+    } else {
+        let value = attr.value.clone().ok_or_else(|| {
+            RpcError::InvalidParameter(format!("attribute '{}' has no value", attr.name))
+        })?;
+        Ok(builder.string_value(value).build())
+    }
+}
+
+/// The same conversion for sns publishes
+fn sns_typed_attribute_value(
+    attr: &TypedAttribute,
+) -> RpcResult<sns::model::MessageAttributeValue> {
+    let value = typed_attribute_value(attr)?;
+    let builder = sns::model::MessageAttributeValue::builder().data_type(&attr.data_type);
+    Ok(match value.binary_value() {
+        Some(blob) => builder
+            .binary_value(sns::types::Blob::new(blob.as_ref().to_vec()))
+            .build(),
+        None => builder
+            .string_value(value.string_value().unwrap_or_default())
+            .build(),
+    })
+}
+
+/// Split a published body into payload and message attributes, unwrapping the
+/// [`MessageEnvelope`] when present and passing everything else through as-is.
+fn unwrap_envelope(body: &[u8]) -> (Vec<u8>, HashMap<String, String>, Vec<TypedAttribute>) {
+    match serde_json::from_slice::<MessageEnvelope>(body) {
+        Ok(envelope) => (
+            envelope.body.into_bytes(),
+            envelope.attributes,
+            envelope.typed_attributes,
+        ),
+        Err(_) => (body.to_vec(), HashMap::new(), Vec::new()),
+    }
+}
+
+/// Collect the string-typed message attributes from a received message,
+/// minus the provider's own encoding marker. Number and Binary attributes go
+/// through [`collect_typed_attributes`] with their declared type intact.
+fn collect_attributes(message: &sqs::model::Message) -> HashMap<String, String> {
+    message
+        .message_attributes()
+        .map(|attrs| {
+            attrs
+                .iter()
+                .filter(|(name, value)| {
+                    name.as_str() != ENCODING_ATTRIBUTE
+                        && name.as_str() != CONTENT_TRANSFER_ENCODING_ATTRIBUTE
+                        && name.as_str() != S3_POINTER_ATTRIBUTE
+                        && value.data_type().unwrap_or("String") == "String"
+                })
+                .filter_map(|(name, value)| {
+                    value.string_value().map(|v| (name.clone(), v.to_string()))
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Collect the attributes whose declared type is not String, preserved as
+/// typed envelope entries instead of being coerced to strings
+fn collect_typed_attributes(message: &sqs::model::Message) -> Vec<TypedAttribute> {
+    message
+        .message_attributes()
+        .map(|attrs| {
+            attrs
+                .iter()
+                .filter(|(name, value)| {
+                    name.as_str() != ENCODING_ATTRIBUTE
+                        && name.as_str() != CONTENT_TRANSFER_ENCODING_ATTRIBUTE
+                        && name.as_str() != S3_POINTER_ATTRIBUTE
+                        && value.data_type().unwrap_or("String") != "String"
+                })
+                .map(|(name, value)| TypedAttribute {
+                    name: name.clone(),
+                    data_type: value.data_type().unwrap_or_default().to_string(),
+                    value: value.string_value().map(str::to_string),
+                    binary_value: value.binary_value().map(|b| base64::encode(b.as_ref())),
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// How many times sqs has delivered this message, from the
+/// ApproximateReceiveCount system attribute. None when sqs didn't return it.
+fn receive_count(message: &sqs::model::Message) -> Option<u32> {
+    message
+        .attributes()
+        .and_then(|attrs| attrs.get(&sqs::model::MessageSystemAttributeName::ApproximateReceiveCount))
+        .and_then(|count| count.parse().ok())
+}
+
+/// Milliseconds a message spent in the queue before dispatch, from the
+/// SentTimestamp system attribute. Clock skew between sqs and this host can
+/// make the difference negative; that saturates to zero rather than failing.
+fn queue_latency_ms(message: &sqs::model::Message, now_epoch_ms: u64) -> Option<u64> {
+    message
+        .attributes()
+        .and_then(|attrs| attrs.get(&sqs::model::MessageSystemAttributeName::SentTimestamp))
+        .and_then(|sent| sent.parse::<u64>().ok())
+        .map(|sent| now_epoch_ms.saturating_sub(sent))
+}
+
+/// Copy the system attributes worth surfacing to actors into the envelope
+/// attribute map: the delivery count (for poison-message detection) and the
+/// original send timestamp (for latency measurements).
+fn collect_system_attributes(
+    message: &sqs::model::Message,
+    attributes: &mut HashMap<String, String>,
+) {
+    if let Some(count) = receive_count(message) {
+        attributes.insert(RECEIVE_COUNT_ATTRIBUTE.to_string(), count.to_string());
+    }
+    if let Some(sent) = message
+        .attributes()
+        .and_then(|attrs| attrs.get(&sqs::model::MessageSystemAttributeName::SentTimestamp))
+    {
+        attributes.insert(SENT_TIMESTAMP_ATTRIBUTE.to_string(), sent.clone());
+    }
+}
+
+/// Copy a delivery's provenance into the envelope attribute map: the queue
+/// it was received from and the id sqs assigned, so an actor can act on a
+/// message's origin without calling back to the provider.
+fn collect_source_metadata(
+    queue_name: &str,
+    message: &sqs::model::Message,
+    attributes: &mut HashMap<String, String>,
+) {
+    attributes.insert(SOURCE_QUEUE_ATTRIBUTE.to_string(), queue_name.to_string());
+    if let Some(id) = message.message_id() {
+        attributes.insert(MESSAGE_ID_ATTRIBUTE.to_string(), id.to_string());
+    }
+}
+
+/// Surface a received message's x-ray trace header into the actor's envelope
+fn collect_xray_trace_header(
+    message: &sqs::model::Message,
+    attributes: &mut HashMap<String, String>,
+) {
+    if let Some(header) = message
+        .attributes()
+        .and_then(|attrs| attrs.get(&sqs::model::MessageSystemAttributeName::AwsTraceHeader))
+    {
+        attributes.insert(XRAY_TRACE_ATTRIBUTE.to_string(), header.clone());
+    }
+}
+
+/// The x-ray trace header for an outgoing message: an explicit envelope
+/// attribute first, the ambient environment second
+fn xray_trace_header(attributes: &mut HashMap<String, String>) -> Option<String> {
+    attributes
+        .remove(XRAY_TRACE_ATTRIBUTE)
+        .or_else(|| std::env::var(XRAY_TRACE_ENV).ok().filter(|v| !v.is_empty()))
+}
+
+/// A log-safe preview of a message body: lossy utf-8, redaction pattern
+/// applied first, then truncated to the configured byte budget. Only ever
+/// called when `log_message_bodies` is on.
+fn body_preview(body: &[u8], limit: usize, redact: Option<&regex::Regex>) -> String {
+    let text = String::from_utf8_lossy(body);
+    let text = match redact {
+        Some(pattern) => pattern.replace_all(&text, "[REDACTED]").into_owned(),
+        None => text.into_owned(),
+    };
+    if text.len() <= limit {
+        return text;
+    }
+    let mut cut = limit;
+    while cut > 0 && !text.is_char_boundary(cut) {
+        cut -= 1;
+    }
+    format!("{}…", &text[..cut])
+}
+
+/// The compiled redaction pattern for a link, if one was configured. The
+/// pattern was validated at link time, so a compile failure here only means
+/// the preview is skipped.
+fn redact_pattern(config: &SQSConfig) -> Option<regex::Regex> {
+    config
+        .log_body_redact_pattern
+        .as_deref()
+        .and_then(|p| regex::Regex::new(p).ok())
+}
+
+/// Build the x-ray system attribute for a send
+fn xray_system_attribute(header: String) -> sqs::model::MessageSystemAttributeValue {
+    sqs::model::MessageSystemAttributeValue::builder()
+        .data_type("String")
+        .string_value(header)
+        .build()
+}
+
+/// The correlation id a publisher stamped on a message, if any
+fn correlation_id(message: &sqs::model::Message) -> Option<String> {
+    message
+        .message_attributes()?
+        .get(CORRELATION_ATTRIBUTE)?
+        .string_value()
+        .map(|v| v.to_string())
+}
+
+/// The span covering one received batch; every message's dispatch span nests
+/// under it so a trace shows the batch as a unit
+fn batch_span(queue_url: &str, batch_size: usize, actor_id: &str) -> tracing::Span {
+    tracing::debug_span!("sqs_batch", %queue_url, batch_size, %actor_id)
+}
+
+/// The span for one message's dispatch, parented to its batch explicitly:
+/// handlers run on spawned tasks, where contextual parenting wouldn't attach
+fn message_span(batch: &tracing::Span, message: &sqs::model::Message) -> tracing::Span {
+    tracing::debug_span!(
+        parent: batch,
+        "dispatch_message",
+        message_id = ?message.message_id(),
+        receipt_handle = ?message.receipt_handle(),
+        correlation_id = ?correlation_id(message),
+    )
+}
+
+/// Dispatch a batch of received messages to the actor, running at most
+/// `limit` handlers at a time. Awaiting the permit before each spawn is the
+/// backpressure: while the actor is saturated, no further handler starts and
+/// the loop doesn't get back to receive_message for more. Returns the receipt
+/// handles of the handled and failed messages separately so the caller can
+/// acknowledge one set and apply the dispatch error policy to the other.
+async fn dispatch_batch<F, Fut>(
+    messages: Vec<sqs::model::Message>,
+    limit: usize,
+    dispatch: F,
+) -> (Vec<Option<String>>, Vec<String>)
+where
+    F: Fn(sqs::model::Message) -> Fut,
+    Fut: std::future::Future<Output = bool> + Send + 'static,
+{
+    let semaphore = Arc::new(tokio::sync::Semaphore::new(limit.max(1)));
+    let mut in_flight = Vec::with_capacity(messages.len());
+    for message in messages {
+        let permit = match semaphore.clone().acquire_owned().await {
+            Ok(permit) => permit,
+            // the semaphore is never closed; this arm only keeps a future
+            // tokio change from panicking the loop
+            Err(_) => break,
+        };
+        let receipt = message.receipt_handle().map(|r| r.to_string());
+        let handler = dispatch(message);
+        in_flight.push(tokio::spawn(async move {
+            let handled = handler.await;
+            drop(permit);
+            (handled, receipt)
+        }));
+    }
+    let mut handled = Vec::new();
+    let mut failed = Vec::new();
+    for task in in_flight {
+        match task.await {
+            Ok((true, receipt)) => handled.push(receipt),
+            Ok((false, Some(receipt))) => failed.push(receipt),
+            _ => {}
+        }
+    }
+    (handled, failed)
+}
+
+/// The rpc context for a dispatch to the linked actor. Carrying the actor id
+/// lets the host attribute the invocation (and any logs the actor emits) to
+/// the right actor instead of an anonymous provider call.
+fn dispatch_context(link_def: &LinkDefinition) -> Context {
+    Context {
+        actor: Some(link_def.actor_id.clone()),
+        ..Default::default()
+    }
+}
+
+/// The whole-message wire format used when a link sets envelope_format=json:
+/// everything a [`PubMessage`] carries, in one self-describing document, so
+/// rich metadata round-trips without touching sqs message attributes.
+#[derive(Debug, Serialize, Deserialize)]
+struct JsonEnvelope {
+    subject: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    reply_to: Option<String>,
+    /// base64 of the raw body bytes
+    body: String,
+}
+
+/// Serialize a publish into the json envelope wire format
+fn to_json_envelope(msg: &PubMessage) -> String {
+    serde_json::to_string(&JsonEnvelope {
+        subject: msg.subject.clone(),
+        reply_to: msg.reply_to.clone(),
+        body: base64::encode(&msg.body),
+    })
+    // a struct of strings cannot fail to serialize
+    .unwrap_or_default()
+}
+
+/// Parse a received json envelope back into the message the actor sees;
+/// None when the body is not a (valid) envelope
+fn from_json_envelope(body: &[u8]) -> Option<SubMessage> {
+    let envelope: JsonEnvelope = serde_json::from_slice(body).ok()?;
+    let body = base64::decode(envelope.body).ok()?;
+    Some(SubMessage {
+        body,
+        reply_to: envelope.reply_to,
+        subject: envelope.subject,
+    })
+}
+
+/// True when `subject` matches `pattern`, where '*' matches any run of
+/// characters (including none). Everything else is literal.
+fn subject_pattern_matches(pattern: &str, subject: &str) -> bool {
+    let mut segments = pattern.split('*');
+    let first = segments.next().unwrap_or_default();
+    if !subject.starts_with(first) {
+        return false;
+    }
+    let mut pos = first.len();
+    let mut middle: Vec<&str> = segments.collect();
+    let last = match middle.pop() {
+        Some(last) => last,
+        // no '*' at all: the pattern is a literal
+        None => return subject.len() == pos,
+    };
+    for segment in middle {
+        if segment.is_empty() {
+            continue;
+        }
+        match subject[pos..].find(segment) {
+            Some(found) => pos += found + segment.len(),
+            None => return false,
+        }
+    }
+    if last.is_empty() {
+        return true;
+    }
+    subject.len() >= pos + last.len() && subject.ends_with(last)
+}
+
+/// Whether the link's acl lets a publish go to `subject`. The denylist is
+/// checked first; an empty allowlist allows anything not denied. The empty
+/// subject (the link's own queue) is matched like any other.
+fn publish_authorized(subject: &str, allow: &[String], deny: &[String]) -> bool {
+    if deny.iter().any(|p| subject_pattern_matches(p, subject)) {
+        return false;
+    }
+    allow.is_empty() || allow.iter().any(|p| subject_pattern_matches(p, subject))
+}
+
+/// True when a message carries every attribute=value pair the link's
+/// subscribe filter demands. An empty filter matches everything; a message
+/// missing a filtered attribute never matches.
+fn matches_subscribe_filter(
+    message: &sqs::model::Message,
+    filter: &HashMap<String, String>,
+) -> bool {
+    if filter.is_empty() {
+        return true;
+    }
+    let attributes = message.message_attributes();
+    filter.iter().all(|(name, expected)| {
+        attributes
+            .and_then(|attrs| attrs.get(name))
+            .and_then(|value| value.string_value())
+            .map(|value| value == expected)
+            .unwrap_or(false)
+    })
+}
+
+/// Seconds since the unix epoch, the clock expires-at stamps are written
+/// and read on
+fn epoch_seconds() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+/// True when a message carries an [`EXPIRES_AT_ATTRIBUTE`] stamp already in
+/// the past. Unstamped or unreadable stamps never expire: dropping a message
+/// is only safe when its publisher clearly asked for a ttl.
+fn message_expired(message: &sqs::model::Message, now_epoch: u64) -> bool {
+    message
+        .message_attributes()
+        .and_then(|attrs| attrs.get(EXPIRES_AT_ATTRIBUTE))
+        .and_then(|value| value.string_value())
+        .and_then(|value| value.trim().parse::<u64>().ok())
+        .map(|expires_at| expires_at <= now_epoch)
+        .unwrap_or(false)
+}
+
+/// True when sqs has already delivered this message more times than the link
+/// allows, i.e. dispatching it again would almost certainly fail again
+fn exceeded_processing_attempts(message: &sqs::model::Message, max_attempts: Option<u32>) -> bool {
+    match (max_attempts, receive_count(message)) {
+        (Some(max), Some(count)) => count > max,
+        _ => false,
+    }
+}
+
+/// Resolve (once per loop) the url of the queue poison messages are moved
+/// into. None when resolution fails; the message stays put and is retried
+/// after the next redelivery.
+async fn quarantine_queue_url(
+    client: &sqs::Client,
+    queue_name: &str,
+    cache: &mut Option<String>,
+) -> Option<String> {
+    if cache.is_none() {
+        match client.get_queue_url().queue_name(queue_name).send().await {
+            Ok(resolved) => *cache = resolved.queue_url().map(|u| u.to_string()),
+            Err(e) => warn!(
+                error = %sdk_error_string(&e),
+                "unable to resolve the dead-letter queue url"
+            ),
+        }
+    }
+    cache.clone()
+}
+
+/// Move a poison message to the dead-letter queue: forward the raw body and
+/// attributes, then delete the original so sqs stops redelivering it. The
+/// delete only happens after a successful forward, so a failure here never
+/// loses the message.
+async fn quarantine_message(
+    client: &sqs::Client,
+    dlq_url: &str,
+    queue_url: &str,
+    message: &sqs::model::Message,
+) {
+    let mut send = client
+        .send_message()
+        .queue_url(dlq_url)
+        .message_body(message.body().unwrap_or_default());
+    if let Some(attrs) = message.message_attributes() {
+        for (name, value) in attrs {
+            send = send.message_attributes(name, value.clone());
+        }
+    }
+    if let Err(e) = send.send().await {
+        warn!(
+            error = %sdk_error_string(&e),
+            "unable to forward poison message to the dead-letter queue; leaving it in place"
+        );
+        return;
+    }
+    if let Some(receipt_handle) = message.receipt_handle() {
+        if let Err(e) = client
+            .delete_message()
+            .queue_url(queue_url)
+            .receipt_handle(receipt_handle)
+            .send()
+            .await
+        {
+            warn!(
+                error = %sdk_error_string(&e),
+                "unable to delete quarantined message from the source queue"
+            );
+        }
+    }
+}
+
+/// Messages one receive pulls for a binding: the link's base batch size
+/// scaled by the binding's polling weight, kept inside sqs's 1-10 range.
+/// Heavier queues drain faster per poll; every binding still has its own
+/// receive loop, so none of them can starve another.
+fn weighted_batch_size(base: i32, weight: u32) -> i32 {
+    base.saturating_mul(weight.min(i32::MAX as u32) as i32)
+        .clamp(1, 10)
+}
+
+/// The ReceiveRequestAttemptId for the next fifo receive: the cached id from
+/// a failed attempt when there is one, a fresh one otherwise. Reusing the id
+/// on retry makes the receive idempotent - sqs returns the same lease instead
+/// of double-leasing the messages to the retried call.
+fn next_attempt_id(cache: &mut Option<String>) -> String {
+    cache
+        .get_or_insert_with(|| {
+            static COUNTER: AtomicU64 = AtomicU64::new(0);
+            let nanos = std::time::SystemTime::now()
+                .duration_since(std::time::SystemTime::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_nanos();
+            format!("{:x}-{:x}", nanos, COUNTER.fetch_add(1, Ordering::Relaxed))
+        })
+        .clone()
+}
+
+/// Async token bucket pacing a link's publishes. Tokens refill continuously
+/// at the configured rate, with one second's worth of burst capacity, so a
+/// quiet link can absorb a spike before pacing kicks in.
+#[derive(Debug)]
+struct TokenBucket {
+    rate: f64,
+    capacity: f64,
+    /// remaining tokens and the instant they were last refilled
+    state: tokio::sync::Mutex<(f64, std::time::Instant)>,
+}
+
+impl TokenBucket {
+    fn new(rate_per_second: u32) -> Self {
+        let rate = f64::from(rate_per_second.max(1));
+        TokenBucket {
+            rate,
+            capacity: rate,
+            state: tokio::sync::Mutex::new((rate, std::time::Instant::now())),
+        }
+    }
+
+    /// Take one token, or report how long until one is available
+    async fn try_take(&self) -> Result<(), Duration> {
+        let mut state = self.state.lock().await;
+        let (tokens, refilled) = &mut *state;
+        let now = std::time::Instant::now();
+        *tokens =
+            (*tokens + now.duration_since(*refilled).as_secs_f64() * self.rate).min(self.capacity);
+        *refilled = now;
+        if *tokens >= 1.0 {
+            *tokens -= 1.0;
+            return Ok(());
+        }
+        Err(Duration::from_secs_f64((1.0 - *tokens) / self.rate))
+    }
+
+    /// Take one token according to the link's behavior: wait for one (up to
+    /// `max_wait`) or fail straight away
+    async fn take(&self, behavior: RateLimitBehavior, max_wait: Duration) -> RpcResult<()> {
+        let start = std::time::Instant::now();
+        loop {
+            let wait = match self.try_take().await {
+                Ok(()) => return Ok(()),
+                Err(wait) => wait,
+            };
+            if behavior == RateLimitBehavior::Error || start.elapsed() + wait > max_wait {
+                return Err(SqsProviderError::Throttled(format!(
+                    "link allows {} publishes per second",
+                    self.rate
+                ))
+                .into());
+            }
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+/// AIMD controller shrinking a queue's dispatch concurrency and receive
+/// batch size when sqs throttles, and growing them back one step per
+/// successful poll. Shared by all of a queue's poll loops so they back off
+/// together instead of each rediscovering the limit.
+#[derive(Debug)]
+struct AdaptiveConcurrency {
+    max_handlers: u64,
+    max_batch: u64,
+    handlers: AtomicU64,
+    batch: AtomicU64,
+}
+
+impl AdaptiveConcurrency {
+    fn new(max_handlers: usize, max_batch: i32) -> Self {
+        let max_handlers = (max_handlers as u64).max(1);
+        let max_batch = max_batch.max(1) as u64;
+        AdaptiveConcurrency {
+            max_handlers,
+            max_batch,
+            handlers: AtomicU64::new(max_handlers),
+            batch: AtomicU64::new(max_batch),
+        }
+    }
+
+    /// multiplicative decrease: halve both limits, never below one
+    fn on_throttle(&self) {
+        for limit in [&self.handlers, &self.batch] {
+            let _ = limit.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |v| {
+                Some((v / 2).max(1))
+            });
+        }
+    }
+
+    /// additive increase: one step back toward the configured ceilings
+    fn on_success(&self) {
+        let _ = self
+            .handlers
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |v| {
+                Some((v + 1).min(self.max_handlers))
+            });
+        let _ = self
+            .batch
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |v| {
+                Some((v + 1).min(self.max_batch))
+            });
+    }
+
+    /// current effective dispatch concurrency
+    fn handlers(&self) -> usize {
+        self.handlers.load(Ordering::Relaxed) as usize
+    }
+
+    /// current effective receive batch size
+    fn batch(&self) -> i32 {
+        self.batch.load(Ordering::Relaxed) as i32
+    }
+}
+
+/// Whether an aws error text reports throttling, the one retryable failure
+/// where retrying at the same rate actively makes things worse
+fn is_throttling_error(error_text: &str) -> bool {
+    error_text.contains("Throttl") || error_text.contains("OverLimit")
+}
+
+/// Circuit breaker deciding whether a link's publishes go to the primary
+/// queue or its failover. Consecutive primary failures open the circuit;
+/// while open, one publish per probe interval is let through to the primary
+/// so the link fails back once the region recovers.
+#[derive(Debug)]
+struct FailoverBreaker {
+    threshold: u64,
+    probe_interval: Duration,
+    state: std::sync::Mutex<BreakerState>,
+}
+
+#[derive(Debug, Default)]
+struct BreakerState {
+    /// consecutive primary-send failures
+    failures: u64,
+    /// set while the circuit is open; refreshed when a probe is released
+    open_since: Option<std::time::Instant>,
+}
+
+impl FailoverBreaker {
+    fn new(threshold: u64, probe_interval: Duration) -> Self {
+        FailoverBreaker {
+            threshold: threshold.max(1),
+            probe_interval,
+            state: std::sync::Mutex::new(BreakerState::default()),
+        }
+    }
+
+    /// Whether the next publish should go to the failover queue. While the
+    /// circuit is open this lets a single publish through to the primary
+    /// each probe interval, restarting the interval so a failed probe does
+    /// not release a thundering herd.
+    fn route_to_failover(&self) -> bool {
+        let mut state = self.state.lock().unwrap();
+        match state.open_since {
+            None => false,
+            Some(since) if since.elapsed() >= self.probe_interval => {
+                state.open_since = Some(std::time::Instant::now());
+                false
+            }
+            Some(_) => true,
+        }
+    }
+
+    /// A primary send succeeded: close the circuit and forget the failures
+    fn record_success(&self) {
+        *self.state.lock().unwrap() = BreakerState::default();
+    }
+
+    /// A primary send failed; true when this failure opens the circuit
+    fn record_failure(&self) -> bool {
+        let mut state = self.state.lock().unwrap();
+        state.failures += 1;
+        if state.failures >= self.threshold && state.open_since.is_none() {
+            state.open_since = Some(std::time::Instant::now());
+            return true;
+        }
+        false
+    }
+}
+
+/// Wrap a decoded body in a [`MessageEnvelope`] when the message carried
+/// attributes worth surfacing to the actor.
+fn wrap_attributes(
+    body: Vec<u8>,
+    attributes: HashMap<String, String>,
+    typed_attributes: Vec<TypedAttribute>,
+) -> Vec<u8> {
+    if attributes.is_empty() && typed_attributes.is_empty() {
+        return body;
+    }
+    match String::from_utf8(body) {
+        Ok(text) => serde_json::to_vec(&MessageEnvelope {
+            attributes,
+            body: text,
+            typed_attributes,
+        })
+        .expect("envelope of string map and string serializes"),
+        Err(e) => {
+            debug!(
+                "dropping {} message attributes on binary payload",
+                attributes.len() + typed_attributes.len()
+            );
+            e.into_bytes()
+        }
+    }
+}
+
+/// fifo queues are distinguished by a mandatory url suffix
+fn is_fifo(queue_url: &str) -> bool {
+    queue_url.ends_with(".fifo")
+}
+
+/// Derive a message group id for a fifo publish that didn't carry one,
+/// according to the link's configured strategy. Returns None when the
+/// strategy has nothing to offer, which fifo_ids reports as an error.
+fn derive_group_id(config: &SQSConfig, subject: &str, payload: &[u8]) -> Option<String> {
+    match config.group_id_from {
+        GroupIdStrategy::Attribute | GroupIdStrategy::Static => {
+            config.default_message_group_id.clone()
+        }
+        GroupIdStrategy::Subject => {
+            (!subject.is_empty()).then(|| subject.to_string())
+        }
+        GroupIdStrategy::HashOfBody => {
+            use std::hash::{Hash, Hasher};
+            let mut hasher = std::collections::hash_map::DefaultHasher::new();
+            payload.hash(&mut hasher);
+            Some(format!("{:016x}", hasher.finish()))
+        }
+    }
+}
+
+/// The deduplication id a dedup_from_body_hash link derives for a payload:
+/// hex sha-256, so identical bodies collapse within sqs's dedup window
+fn body_hash_dedup_id(payload: &[u8]) -> String {
+    use sha2::Digest;
+    format!("{:x}", sha2::Sha256::digest(payload))
+}
+
+/// Pull the fifo routing ids out of a publish's envelope attributes. A group
+/// id is required on fifo queues, derived per the link's strategy when the
+/// message doesn't carry one; a deduplication id is required unless the queue
+/// deduplicates on content.
+fn fifo_ids(
+    attributes: &mut HashMap<String, String>,
+    content_based_deduplication: bool,
+    derived_group_id: Option<String>,
+    body_hash_dedup_id: Option<String>,
+) -> RpcResult<(String, Option<String>)> {
+    let group_id = match attributes.remove(GROUP_ID_ATTRIBUTE) {
+        Some(group_id) => group_id,
+        None => config::validate_group_id(derived_group_id.ok_or_else(|| {
+            RpcError::InvalidParameter(format!(
+                "publishing to a fifo queue requires a '{}' attribute",
+                GROUP_ID_ATTRIBUTE
+            ))
+        })?)?,
+    };
+    // an explicit per-message id always wins over the derived body hash
+    let dedup_id = attributes
+        .remove(DEDUP_ID_ATTRIBUTE)
+        .or(body_hash_dedup_id);
+    if dedup_id.is_none() && !content_based_deduplication {
+        return Err(RpcError::InvalidParameter(format!(
+            "fifo queue without content-based deduplication requires a '{}' attribute",
+            DEDUP_ID_ATTRIBUTE
+        )));
+    }
+    Ok((group_id, dedup_id))
+}
+
+/// The queue urls from a list_queues page that aren't subscribed yet, in
+/// listing order. Prefix discovery runs this against each refresh so queues
+/// created after link time get subscriptions and existing ones don't get a
+/// second one.
+fn new_queue_urls(known: &std::collections::HashSet<String>, listed: &[String]) -> Vec<String> {
+    listed
+        .iter()
+        .filter(|url| !known.contains(*url))
+        .cloned()
+        .collect()
+}
+
+/// Injector/extractor adapters over the provider's attribute maps so the
+/// otel propagator can read and write w3c trace headers on sqs messages
+struct AttributeInjector<'a>(&'a mut HashMap<String, String>);
+
+impl opentelemetry::propagation::Injector for AttributeInjector<'_> {
+    fn set(&mut self, key: &str, value: String) {
+        self.0.insert(key.to_string(), value);
+    }
+}
+
+struct AttributeExtractor<'a>(&'a HashMap<String, String>);
+
+impl opentelemetry::propagation::Extractor for AttributeExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).map(|v| v.as_str())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(|k| k.as_str()).collect()
+    }
+}
+
+/// inject the current span's trace context into a publish's attributes
+fn inject_trace_context(attributes: &mut HashMap<String, String>) {
+    use opentelemetry::propagation::TextMapPropagator;
+    use tracing_opentelemetry::OpenTelemetrySpanExt;
+    let propagator = opentelemetry::sdk::propagation::TraceContextPropagator::new();
+    propagator.inject_context(
+        &tracing::Span::current().context(),
+        &mut AttributeInjector(attributes),
+    );
+}
+
+/// make the trace context found on a received message the parent of the
+/// current span, so the actor dispatch continues the publisher's trace
+fn attach_trace_context(attributes: &HashMap<String, String>) {
+    use opentelemetry::propagation::TextMapPropagator;
+    use tracing_opentelemetry::OpenTelemetrySpanExt;
+    let propagator = opentelemetry::sdk::propagation::TraceContextPropagator::new();
+    let parent = propagator.extract(&AttributeExtractor(attributes));
+    tracing::Span::current().set_parent(parent);
+}
+
+/// Render an sdk error using the service's own message when one is present,
+/// since the top-level Display for a service error is just a generic label
+fn sdk_error_string<E: std::error::Error>(e: &sqs::types::SdkError<E>) -> String {
+    match e {
+        sqs::types::SdkError::ServiceError { err, .. } => err.to_string(),
+        other => other.to_string(),
+    }
+}
+
+/// How the provider should react to an aws failure, shared by the publish,
+/// receive and delete paths so an error code means the same thing
+/// everywhere.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum SdkErrorClass {
+    /// transient: throttling, 5xx, connection trouble - retry with backoff
+    Retryable,
+    /// the request can never succeed as issued: missing queue, bad parameter
+    Fatal,
+    /// the link's credentials or settings are wrong; retrying without a
+    /// rebuilt client (or a fixed link) is pointless
+    Config,
+}
+
+/// Classify a stringified sdk error. The sdk's typed errors are generic per
+/// operation, so like [`is_queue_missing`] this works on the rendered text,
+/// which carries the aws error code.
+fn classify_sdk_error(error_text: &str) -> SdkErrorClass {
+    if is_queue_missing(error_text)
+        || error_text.contains("InvalidParameterValue")
+        || error_text.contains("InvalidAttributeName")
+        || error_text.contains("InvalidMessageContents")
+        || error_text.contains("MessageTooLong")
+        || error_text.contains("BatchRequestTooLong")
+        || error_text.contains("ValidationError")
+    {
+        return SdkErrorClass::Fatal;
+    }
+    if is_credential_expired(error_text)
+        || error_text.contains("AccessDenied")
+        || error_text.contains("SignatureDoesNotMatch")
+        || error_text.contains("MissingAuthenticationToken")
+    {
+        return SdkErrorClass::Config;
+    }
+    // throttling, internal errors, timeouts and anything unrecognized:
+    // backoff-and-retry is the safe default for a transient system
+    SdkErrorClass::Retryable
+}
+
+/// Whether an sqs error text reports the queue itself missing, the one
+/// failure a stale cached url can cause and a re-resolve can fix
+fn is_queue_missing(error_text: &str) -> bool {
+    error_text.contains("NonExistentQueue") || error_text.contains("QueueDoesNotExist")
+}
+
+/// Whether an aws error text reports expired or invalid credentials, which a
+/// client rebuilt against a fresh credential resolution (or a fresh sts
+/// assume) can fix
+fn is_credential_expired(error_text: &str) -> bool {
+    error_text.contains("ExpiredToken")
+        || error_text.contains("InvalidClientTokenId")
+        || error_text.contains("UnrecognizedClientException")
+        || error_text.contains("security token included in the request is expired")
+}
+
+/// Per-actor operation counters, bumped with relaxed atomics so they cost
+/// nearly nothing on the hot path. Emitted through tracing after every poll
+/// so operators can watch throughput and error rates per link.
+#[derive(Debug, Default)]
+struct Metrics {
+    published: AtomicU64,
+    publish_err: AtomicU64,
+    received: AtomicU64,
+    dispatched: AtomicU64,
+    delete_ok: AtomicU64,
+    delete_err: AtomicU64,
+}
+
+impl Metrics {
+    fn incr(counter: &AtomicU64) {
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn add(counter: &AtomicU64, n: u64) {
+        counter.fetch_add(n, Ordering::Relaxed);
+    }
+
+    fn emit(&self, actor_id: &str) {
+        debug!(
+            %actor_id,
+            published = self.published.load(Ordering::Relaxed),
+            publish_err = self.publish_err.load(Ordering::Relaxed),
+            received = self.received.load(Ordering::Relaxed),
+            dispatched = self.dispatched.load(Ordering::Relaxed),
+            delete_ok = self.delete_ok.load(Ordering::Relaxed),
+            delete_err = self.delete_err.load(Ordering::Relaxed),
+            "sqs provider metrics"
+        );
+    }
+}
+
+/// Jittered exponential backoff for the receive loop. Each consecutive
+/// failure doubles the delay from the base up to the cap; each delay is
+/// jittered down by up to half to keep a fleet of providers from polling in
+/// lockstep. A successful poll resets the sequence.
+struct Backoff {
+    base: Duration,
+    cap: Duration,
+    failures: u32,
+}
+
+impl Backoff {
+    fn new(cap: Duration) -> Self {
+        let base = Duration::from_millis(RECEIVE_BACKOFF_BASE_MS);
+        Backoff {
+            cap: cap.max(base),
+            base,
+            failures: 0,
+        }
+    }
+
+    /// the delay to sleep before the next attempt, counting this failure
+    fn next_delay(&mut self) -> Duration {
+        let exp = self
+            .base
+            .saturating_mul(1u32 << self.failures.min(16))
+            .min(self.cap);
+        self.failures = self.failures.saturating_add(1);
+        // cheap jitter source; this doesn't need to be well-distributed
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.subsec_nanos())
+            .unwrap_or(0) as u64;
+        let half = exp.as_millis() as u64 / 2;
+        Duration::from_millis(half + half.saturating_sub(nanos % (half + 1)))
+    }
+
+    /// Jump straight to the cap, for failures retrying faster cannot fix
+    fn saturate(&mut self) -> Duration {
+        self.failures = self.failures.saturating_add(1).max(17);
+        self.cap
+    }
+
+    fn reset(&mut self) {
+        self.failures = 0;
+    }
+}
+
+/// How long a request's receive poll should wait. The caller's timeout wins
+/// when it is shorter than the configured long-poll window; a zero timeout
+/// means "use the configured wait".
+fn request_wait_seconds(timeout_ms: u64, configured: i32) -> i32 {
+    if timeout_ms == 0 {
+        configured
+    } else {
+        ((timeout_ms / 1000) as i32).min(configured)
+    }
+}
+
+/// Build the reply handed back from request: the body round-trips the
+/// received message's attributes, the subject echoes what was requested so the
+/// actor can correlate, and reply_to is populated when the sender named a
+/// reply destination.
+fn build_reply(
+    subject: &str,
+    message: &sqs::model::Message,
+    offloaded_body: Option<Vec<u8>>,
+) -> RpcResult<ReplyMessage> {
+    let mut attributes = collect_attributes(message);
+    let reply_to = attributes.remove(REPLY_TO_ATTRIBUTE);
+    let body = match offloaded_body {
+        Some(body) => body,
+        None => decode_body(message)?,
+    };
+    Ok(ReplyMessage {
+        body: wrap_attributes(body, attributes, collect_typed_attributes(message)),
+        reply_to,
+        subject: subject.to_string(),
+    })
+}
+
+/// Pull an optional per-message delivery delay out of a publish's envelope
+/// attributes, clamped to the 0-900 second range sqs allows. Fifo queues
+/// don't support per-message delay, so the attribute is an error there
+/// instead of being silently dropped.
+fn delay_from_attributes(
+    attributes: &mut HashMap<String, String>,
+    fifo: bool,
+) -> RpcResult<Option<i32>> {
+    let raw = match attributes.remove(DELAY_ATTRIBUTE) {
+        Some(raw) => raw,
+        None => return Ok(None),
+    };
+    if fifo {
+        return Err(RpcError::InvalidParameter(format!(
+            "'{}' is not supported on fifo queues; use a queue-level delay instead",
+            DELAY_ATTRIBUTE
+        )));
+    }
+    let seconds = raw.parse::<i32>().map_err(|_| {
+        RpcError::InvalidParameter(format!(
+            "attribute '{}' must be an integer, found \"{}\"",
+            DELAY_ATTRIBUTE, raw
+        ))
+    })?;
+    let clamped = seconds.clamp(0, 900);
+    if clamped != seconds {
+        warn!(
+            "{} {} is outside the 0-900 range sqs allows; clamping to {}",
+            DELAY_ATTRIBUTE, seconds, clamped
+        );
+    }
+    Ok(Some(clamped))
+}
+
+/// Fail a publish that asks for an initial visibility timeout. SendMessage
+/// returns no receipt handle, so there is nothing to pass to
+/// change_message_visibility - the message would be visible (and possibly
+/// consumed) before any follow-up call could hide it. A delivery delay is
+/// the one mechanism sqs actually provides for hiding a new message, so the
+/// error points the caller at [`DELAY_ATTRIBUTE`] instead of guessing.
+fn reject_initial_visibility(attributes: &HashMap<String, String>) -> RpcResult<()> {
+    if attributes.contains_key(INITIAL_VISIBILITY_ATTRIBUTE) {
+        return Err(RpcError::InvalidParameter(format!(
+            "'{}' cannot be honored: sqs send_message returns no receipt handle to change \
+             visibility with; use '{}' (0-900) for the same effect",
+            INITIAL_VISIBILITY_ATTRIBUTE, DELAY_ATTRIBUTE
+        )));
+    }
+    Ok(())
+}
+
+/// The attribute set applied when this provider creates a queue: retention
+/// and delay always, encryption when configured, and the fifo flags when the
+/// name marks the queue as fifo (sqs requires FifoQueue at creation time).
+fn create_queue_attributes(
+    config: &SQSConfig,
+    queue_name: &str,
+) -> Vec<(sqs::model::QueueAttributeName, String)> {
+    let mut attributes = vec![
+        (
+            sqs::model::QueueAttributeName::MessageRetentionPeriod,
+            config.message_retention_seconds.to_string(),
+        ),
+        (
+            sqs::model::QueueAttributeName::DelaySeconds,
+            config.delay_seconds.to_string(),
+        ),
+    ];
+    if let Some(key_id) = &config.kms_master_key_id {
+        attributes.push((sqs::model::QueueAttributeName::KmsMasterKeyId, key_id.clone()));
+        if let Some(period) = config.kms_data_key_reuse_period {
+            attributes.push((
+                sqs::model::QueueAttributeName::KmsDataKeyReusePeriodSeconds,
+                period.to_string(),
+            ));
+        }
+    }
+    if queue_name.ends_with(".fifo") {
+        attributes.push((
+            sqs::model::QueueAttributeName::FifoQueue,
+            String::from("true"),
+        ));
+        attributes.push((
+            sqs::model::QueueAttributeName::ContentBasedDeduplication,
+            config.content_based_deduplication.to_string(),
+        ));
+        if let Some(scope) = &config.deduplication_scope {
+            attributes.push((
+                sqs::model::QueueAttributeName::DeduplicationScope,
+                scope.clone(),
+            ));
+        }
+        if let Some(limit) = &config.fifo_throughput_limit {
+            attributes.push((
+                sqs::model::QueueAttributeName::FifoThroughputLimit,
+                limit.clone(),
+            ));
+        }
+    }
+    attributes
+}
+
+/// Interpret a configured queue identifier: a full queue url is used as-is,
+/// an `arn:aws:sqs:region:account:name` arn is converted to the matching url,
+/// and anything else is a bare name the caller resolves via get_queue_url
+/// (signalled by Ok(None)).
+/// The region embedded in a full sqs queue url, e.g.
+/// `https://sqs.eu-west-1.amazonaws.com/123/queue` -> `eu-west-1`. None for
+/// urls without one (legacy `queue.amazonaws.com`, localstack, custom
+/// endpoints), which are left to the configured client as-is.
+fn queue_url_region(url: &str) -> Option<String> {
+    let host = url
+        .strip_prefix("https://")
+        .or_else(|| url.strip_prefix("http://"))?
+        .split('/')
+        .next()?;
+    match host.split('.').collect::<Vec<_>>().as_slice() {
+        ["sqs", region, "amazonaws", "com"] | ["sqs", region, "amazonaws", "com", "cn"] => {
+            Some(region.to_string())
+        }
+        _ => None,
+    }
+}
+
+fn queue_url_from_identifier(value: &str) -> RpcResult<Option<String>> {
+    if value.starts_with("https://") || value.starts_with("http://") {
+        return Ok(Some(value.to_string()));
+    }
+    if let Some(rest) = value.strip_prefix("arn:") {
+        let parts: Vec<&str> = rest.split(':').collect();
+        match parts.as_slice() {
+            [partition, "sqs", region, account, name]
+                if !region.is_empty() && !account.is_empty() && !name.is_empty() =>
+            {
+                // the china partition serves sqs from its own domain; gov
+                // and commercial share the standard one
+                let domain = if *partition == "aws-cn" {
+                    "amazonaws.com.cn"
+                } else {
+                    "amazonaws.com"
+                };
+                return Ok(Some(format!(
+                    "https://sqs.{}.{}/{}/{}",
+                    region, domain, account, name
+                )));
+            }
+            _ => {
+                return Err(RpcError::InvalidParameter(format!(
+                    "'{}' is not a valid sqs queue arn",
+                    value
+                )));
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// the RedrivePolicy attribute value pointing a queue at its dead-letter queue
+fn redrive_policy(dlq_arn: &str, max_receive_count: i32) -> String {
+    serde_json::json!({
+        "deadLetterTargetArn": dlq_arn,
+        "maxReceiveCount": max_receive_count.to_string(),
+    })
+    .to_string()
+}
+
+/// build the string-typed message attribute value used for all attributes
+fn string_attribute(value: impl Into<String>) -> sqs::model::MessageAttributeValue {
+    sqs::model::MessageAttributeValue::builder()
+        .data_type("String")
+        .string_value(value)
+        .build()
+}
+
+/// Build an sns string message attribute
+fn sns_string_attribute(value: impl Into<String>) -> sns::model::MessageAttributeValue {
+    sns::model::MessageAttributeValue::builder()
+        .data_type("String")
+        .string_value(value)
+        .build()
+}
+
+/// A publish waiting in the coalescing buffer for its batch to fill or the
+/// flush timer to fire
+#[derive(Clone, Debug)]
+struct PendingMessage {
+    body: String,
+    encoding: &'static str,
+    attributes: HashMap<String, String>,
+    /// already converted to their declared sqs types at publish time, so the
+    /// flusher can't hit a conversion error it has no caller to report to
+    typed_attributes: Vec<(String, sqs::model::MessageAttributeValue)>,
+    fifo: Option<(String, Option<String>)>,
+    delay_seconds: Option<i32>,
+    trace_header: Option<String>,
+}
+
+/// Convert a buffered publish into a batch entry; the id only has to be
+/// unique within the batch, so the entry's index is enough
+fn batch_entry(id: usize, message: PendingMessage) -> sqs::model::SendMessageBatchRequestEntry {
+    let mut entry = sqs::model::SendMessageBatchRequestEntry::builder()
+        .id(id.to_string())
+        .message_body(message.body)
+        .message_attributes(ENCODING_ATTRIBUTE, string_attribute(message.encoding));
+    if message.encoding == ENCODING_BASE64 {
+        entry = entry.message_attributes(
+            CONTENT_TRANSFER_ENCODING_ATTRIBUTE,
+            string_attribute(ENCODING_BASE64),
+        );
+    }
+    for (name, value) in message.attributes {
+        entry = entry.message_attributes(name, string_attribute(value));
+    }
+    for (name, value) in message.typed_attributes {
+        entry = entry.message_attributes(name, value);
+    }
+    if let Some((group_id, dedup_id)) = message.fifo {
+        entry = entry.message_group_id(group_id);
+        if let Some(dedup_id) = dedup_id {
+            entry = entry.message_deduplication_id(dedup_id);
+        }
+    }
+    if let Some(delay_seconds) = message.delay_seconds {
+        entry = entry.delay_seconds(delay_seconds);
+    }
+    if let Some(header) = message.trace_header {
+        entry = entry.message_system_attributes(
+            sqs::model::MessageSystemAttributeNameForSends::AwsTraceHeader,
+            xray_system_attribute(header),
+        );
+    }
+    entry.build()
+}
+
+/// Add a publish to its queue's buffer, handing the buffer back once it holds
+/// a full sqs batch so the caller can flush it early
+fn buffer_pending(
+    buffers: &mut HashMap<String, Vec<PendingMessage>>,
+    queue_url: &str,
+    message: PendingMessage,
+) -> Option<Vec<PendingMessage>> {
+    let buffer = buffers.entry(queue_url.to_string()).or_default();
+    buffer.push(message);
+    (buffer.len() >= MAX_BATCH_ENTRIES).then(|| std::mem::take(buffer))
+}
+
+/// Send one buffered batch. Batched publishes are fire-and-forget from the
+/// actor's point of view, so failures - whole-call or per-entry - are logged
+/// rather than surfaced.
+async fn flush_batch(client: &sqs::Client, queue_url: &str, pending: Vec<PendingMessage>) {
+    debug!(%queue_url, count = pending.len(), "flushing publish batch");
+    let mut send = client.send_message_batch().queue_url(queue_url);
+    for (id, message) in pending.into_iter().enumerate() {
+        send = send.entries(batch_entry(id, message));
+    }
+    match send.send().await {
+        Ok(sent) => {
+            for failed in sent.failed().unwrap_or_default() {
+                error!(
+                    %queue_url,
+                    entry_id = ?failed.id(),
+                    code = ?failed.code(),
+                    message = ?failed.message(),
+                    "batched publish entry was rejected"
+                );
+            }
+        }
+        Err(e) => error!(error = %e, %queue_url, "sqs send_message_batch failed"),
+    }
+}
+
+/// Spawn the flusher that coalesces publishes into batches per destination
+/// queue, flushing a queue's buffer when it fills or on every flush interval,
+/// and draining whatever is left when the link is torn down
+fn spawn_flusher(
+    client: sqs::Client,
+    flush_interval: Duration,
+    cancel: CancellationToken,
+) -> (mpsc::Sender<(String, PendingMessage)>, JoinHandle<()>) {
+    let (tx, mut rx) = mpsc::channel::<(String, PendingMessage)>(BATCH_CHANNEL_CAPACITY);
+    let handle = tokio::spawn(async move {
+        let mut buffers: HashMap<String, Vec<PendingMessage>> = HashMap::new();
+        let mut tick = tokio::time::interval(flush_interval);
+        loop {
+            tokio::select! {
+                _ = cancel.cancelled() => break,
+                _ = tick.tick() => {
+                    for (queue_url, pending) in buffers.drain() {
+                        if !pending.is_empty() {
+                            flush_batch(&client, &queue_url, pending).await;
+                        }
+                    }
+                }
+                received = rx.recv() => match received {
+                    Some((queue_url, message)) => {
+                        if let Some(full) = buffer_pending(&mut buffers, &queue_url, message) {
+                            flush_batch(&client, &queue_url, full).await;
+                        }
+                    }
+                    None => break,
+                },
+            }
+        }
+        // drain anything still buffered so teardown doesn't drop messages
+        for (queue_url, pending) in buffers.drain() {
+            if !pending.is_empty() {
+                flush_batch(&client, &queue_url, pending).await;
+            }
+        }
+        debug!("sqs publish flusher exited");
+    });
+    (tx, handle)
+}
+
+/// An sqs client together with the queue it was linked against, resolved once
+/// at link time so publish/request don't have to guess at queue urls.
+#[derive(Clone, Debug)]
+struct SqsClientBundle {
+    client: sqs::Client,
+    queue_url: String,
+    config: SQSConfig,
+    /// signalled on delete_link/shutdown so the receive loop can exit after
+    /// finishing the poll it is currently in
+    cancel: CancellationToken,
+    /// handles of the background receive loops feeding the linked actor, one
+    /// per subscribe-role queue; shared so cheap clones of the bundle don't
+    /// tear the loops down on drop
+    poll_handles: Vec<Arc<JoinHandle<()>>>,
+    /// name and resolved url of every subscribe-role queue on this link
+    subscribe_queues: Vec<(String, String)>,
+    /// subject -> queue url mappings already resolved for this link, so
+    /// subject routing costs one get_queue_url per subject instead of per send
+    resolved_urls: Arc<RwLock<HashMap<String, String>>>,
+    /// present when batch_flush_ms is configured; publishes are handed to the
+    /// flusher task instead of being sent inline
+    batch_tx: Option<mpsc::Sender<(String, PendingMessage)>>,
+    /// handle of the flusher task, joined at shutdown like the receive loop
+    flush_handle: Option<Arc<JoinHandle<()>>>,
+    /// operation counters shared with this link's receive loop
+    metrics: Arc<Metrics>,
+    /// cache key of the shared client, released back on delete_link
+    client_key: String,
+    /// present when the link enables sns publishing; publishes whose subject
+    /// is a topic arn go here instead of sqs
+    sns_client: Option<sns::Client>,
+    /// ids of this link's most recent direct publish, shared across clones so
+    /// a later control request sees what an earlier publish recorded
+    last_publish: Arc<RwLock<Option<LastPublish>>>,
+    /// present when the link configures a large_payload_bucket; oversized
+    /// payloads are stored here and fetched back on receive
+    s3_client: Option<s3::Client>,
+    /// paces this link's publishes when publish_rate_limit is set; shared so
+    /// every clone of the bundle draws from the same budget
+    rate_limiter: Option<Arc<TokenBucket>>,
+    /// routes publishes between primary and failover queue; shared so every
+    /// clone of the bundle sees the same circuit state
+    failover: Option<Arc<FailoverBreaker>>,
+    /// client for the failover region; None reuses the primary's client
+    failover_client: Option<sqs::Client>,
+    /// resolved url of the failover queue
+    failover_queue_url: Option<String>,
+    /// held while an operator has paused the link's consumption; the receive
+    /// loops idle on it instead of fetching, shared across clones and loops
+    paused: Arc<AtomicBool>,
+}
+
+impl SqsClientBundle {
+    /// Park or release this link's receive loops. Pausing only stops new
+    /// fetches: dispatches already in flight finish, messages stay queued
+    /// for later, and publishes keep flowing.
+    fn set_paused(&self, paused: bool) {
+        warn!(paused, queue_url = %self.queue_url, "setting link consumption state");
+        self.paused.store(paused, Ordering::Relaxed);
+    }
+
+    /// Purge the link's primary queue, dropping every message in it. Gated
+    /// behind the allow_purge link setting so a stray publish to the control
+    /// subject can never empty a production queue.
+    async fn purge_queue(&self) -> RpcResult<()> {
+        if !self.config.allow_purge {
+            return Err(RpcError::InvalidParameter(format!(
+                "'{}' requires allow_purge=true on the link",
+                CONTROL_PURGE_SUBJECT
+            )));
+        }
+        if self.queue_url.is_empty() {
+            return Err(RpcError::InvalidParameter(
+                "link has no publish-role queue configured".to_string(),
+            ));
+        }
+        warn!(queue_url = %self.queue_url, "purging sqs queue");
+        self.client
+            .purge_queue()
+            .queue_url(&self.queue_url)
+            .send()
+            .await
+            .map_err(|e| {
+                RpcError::Other(format!("sqs purge_queue failed: {}", sdk_error_string(&e)))
+            })?;
+        Ok(())
+    }
+
+    /// Publish a message to an sns topic instead of an sqs queue, used when
+    /// the link enables sns publishing and the subject is a topic arn.
+    async fn publish_sns(&self, sns_client: &sns::Client, msg: &PubMessage) -> RpcResult<()> {
+        let (payload, mut attributes, typed_attributes) =
+            if self.config.envelope_format == EnvelopeFormat::Json {
+                // the whole message becomes the body; nothing rides on
+                // message attributes beyond the provider's own markers
+                (to_json_envelope(msg).into_bytes(), HashMap::new(), Vec::new())
+            } else {
+                unwrap_envelope(&msg.body)
+            };
+        if self.config.propagate_trace_context {
+            inject_trace_context(&mut attributes);
+        }
+        if self.config.message_ttl_seconds > 0 {
+            attributes
+                .entry(EXPIRES_AT_ATTRIBUTE.to_string())
+                .or_insert_with(|| {
+                    (epoch_seconds() + self.config.message_ttl_seconds).to_string()
+                });
+        }
+        let (body, encoding) = encode_body(&payload, self.config.body_encoding)?;
+        let mut publish = sns_client
+            .publish()
+            .topic_arn(&msg.subject)
+            .message(body)
+            .message_attributes(ENCODING_ATTRIBUTE, sns_string_attribute(encoding));
+        if encoding == ENCODING_BASE64 {
+            publish = publish.message_attributes(
+                CONTENT_TRANSFER_ENCODING_ATTRIBUTE,
+                sns_string_attribute(ENCODING_BASE64),
+            );
+        }
+        for (name, value) in attributes {
+            publish = publish.message_attributes(name, sns_string_attribute(value));
+        }
+        for attr in &typed_attributes {
+            publish = publish.message_attributes(&attr.name, sns_typed_attribute_value(attr)?);
+        }
+        let sent = publish.send().await.map_err(|e| {
+            Metrics::incr(&self.metrics.publish_err);
+            SqsProviderError::SendFailed(format!("sns publish failed: {}", sdk_error_string(&e)))
+        })?;
+        Metrics::incr(&self.metrics.published);
+        if let Some(message_id) = sent.message_id() {
+            tracing::Span::current().record("message_id", tracing::field::display(message_id));
+        }
+        self.record_publish(&msg.subject, sent.message_id(), sent.sequence_number())
+            .await;
+        debug!("published message to sns");
+        Ok(())
+    }
+
+    /// Store an oversized payload in the link's bucket and return the pointer
+    /// body sent in its place; the marker attribute carries the original size
+    /// so receivers (and other extended clients) know to fetch it back
+    async fn offload_payload(
+        &self,
+        bucket: &str,
+        payload: Vec<u8>,
+        attributes: &mut HashMap<String, String>,
+    ) -> RpcResult<Vec<u8>> {
+        let s3_client = self.s3_client.as_ref().ok_or_else(|| {
+            RpcError::ProviderInit(
+                "large_payload_bucket is set but the link has no s3 client".to_string(),
+            )
+        })?;
+        let key = large_payload_key(&self.config.queue_name);
+        let size = payload.len();
+        s3_client
+            .put_object()
+            .bucket(bucket)
+            .key(&key)
+            .body(s3::types::ByteStream::from(payload))
+            .send()
+            .await
+            .map_err(|e| {
+                Metrics::incr(&self.metrics.publish_err);
+                SqsProviderError::SendFailed(format!(
+                    "s3 put_object failed: {}",
+                    sdk_error_string(&e)
+                ))
+            })?;
+        debug!(%bucket, %key, size, "offloaded large payload to s3");
+        attributes.insert(S3_POINTER_ATTRIBUTE.to_string(), size.to_string());
+        Ok(s3_pointer_body(bucket, &key).into_bytes())
+    }
+
+    /// Answer a depth query: the approximate message counts of the link's
+    /// primary queue, serialized as json in the reply body.
+    async fn queue_depth(&self) -> RpcResult<ReplyMessage> {
+        if self.queue_url.is_empty() {
+            return Err(RpcError::InvalidParameter(
+                "link has no publish-role queue configured".to_string(),
+            ));
+        }
+        let attributes = self
+            .client
+            .get_queue_attributes()
+            .queue_url(&self.queue_url)
+            .attribute_names(sqs::model::QueueAttributeName::ApproximateNumberOfMessages)
+            .attribute_names(sqs::model::QueueAttributeName::ApproximateNumberOfMessagesNotVisible)
+            .attribute_names(sqs::model::QueueAttributeName::ApproximateNumberOfMessagesDelayed)
+            .send()
+            .await
+            .map_err(|e| {
+                RpcError::Other(format!(
+                    "sqs get_queue_attributes failed: {}",
+                    sdk_error_string(&e)
+                ))
+            })?;
+        let depth = depth_from_attributes(attributes.attributes());
+        let body = serde_json::to_vec(&depth)
+            .map_err(|e| RpcError::Ser(format!("serializing queue depth: {}", e)))?;
+        Ok(ReplyMessage {
+            body,
+            reply_to: None,
+            subject: CONTROL_DEPTH_SUBJECT.to_string(),
+        })
+    }
+
+    /// Move up to `limit` messages from the link's dead letter queue back
+    /// onto its main queue: receive from the dlq, send to the main queue
+    /// with attributes intact, then delete from the dlq. Requires the link
+    /// to opt in with allow_dlq_replay, mirroring the purge guard.
+    async fn replay_dlq(&self, limit: u64) -> RpcResult<ReplyMessage> {
+        if !self.config.allow_dlq_replay {
+            return Err(RpcError::InvalidParameter(format!(
+                "'{}' requires allow_dlq_replay=true on the link",
+                CONTROL_DLQ_REPLAY_SUBJECT
+            )));
+        }
+        let dlq_name = self.config.dead_letter_queue_name.as_ref().ok_or_else(|| {
+            RpcError::InvalidParameter(
+                "link has no dead_letter_queue_name to replay from".to_string(),
+            )
+        })?;
+        if self.queue_url.is_empty() {
+            return Err(RpcError::InvalidParameter(
+                "link has no publish-role queue configured".to_string(),
+            ));
+        }
+        let dlq_url = self
+            .client
+            .get_queue_url()
+            .queue_name(dlq_name)
+            .send()
+            .await
+            .map_err(|e| {
+                SqsProviderError::QueueNotFound(format!(
+                    "dead letter queue '{}': {}",
+                    dlq_name,
+                    sdk_error_string(&e)
+                ))
+            })?
+            .queue_url()
+            .ok_or_else(|| {
+                SqsProviderError::QueueNotFound(format!("dead letter queue '{}'", dlq_name))
+            })?
+            .to_string();
+        warn!(%dlq_url, limit, "replaying dead letter queue");
+        let mut replayed: u64 = 0;
+        while replayed < limit {
+            let batch = (limit - replayed).min(10) as i32;
+            let received = self
+                .client
+                .receive_message()
+                .queue_url(&dlq_url)
+                .max_number_of_messages(batch)
+                .wait_time_seconds(1)
+                .message_attribute_names("All")
+                .send()
+                .await
+                .map_err(|e| {
+                    SqsProviderError::ReceiveFailed(format!(
+                        "dlq replay receive: {}",
+                        sdk_error_string(&e)
+                    ))
+                })?;
+            let messages = received.messages.unwrap_or_default();
+            if messages.is_empty() {
+                break;
+            }
+            let mut receipts = Vec::with_capacity(messages.len());
+            for message in messages {
+                let mut send = self
+                    .client
+                    .send_message()
+                    .queue_url(&self.queue_url)
+                    .message_body(message.body().unwrap_or_default());
+                if let Some(attributes) = message.message_attributes() {
+                    for (name, value) in attributes {
+                        send = send.message_attributes(name, value.clone());
+                    }
+                }
+                send.send().await.map_err(|e| {
+                    SqsProviderError::SendFailed(format!(
+                        "dlq replay send: {}",
+                        sdk_error_string(&e)
+                    ))
+                })?;
+                replayed += 1;
+                if let Some(receipt) = message.receipt_handle() {
+                    receipts.push(receipt.to_string());
+                }
+            }
+            delete_batch(&self.client, &dlq_url, receipts, &self.metrics).await;
+        }
+        let body = serde_json::to_vec(&serde_json::json!({ "replayed": replayed }))
+            .map_err(|e| RpcError::Ser(format!("serializing replay result: {}", e)))?;
+        Ok(ReplyMessage {
+            body,
+            reply_to: None,
+            subject: CONTROL_DLQ_REPLAY_SUBJECT.to_string(),
+        })
+    }
+
+    /// Remember the ids the service assigned to a publish, for later
+    /// [`CONTROL_LAST_PUBLISH_SUBJECT`] queries.
+    async fn record_publish(
+        &self,
+        destination: &str,
+        message_id: Option<&str>,
+        sequence_number: Option<&str>,
+    ) {
+        *self.last_publish.write().await = Some(LastPublish {
+            destination: destination.to_string(),
+            message_id: message_id.map(str::to_string),
+            sequence_number: sequence_number.map(str::to_string),
+        });
+    }
+
+    /// Answer a last-publish query: the ids of the link's most recent direct
+    /// publish, serialized as json in the reply body. Erroring when nothing
+    /// has been published yet keeps "no record" distinct from a stale one.
+    async fn last_publish_reply(&self) -> RpcResult<ReplyMessage> {
+        let record = self.last_publish.read().await.clone().ok_or_else(|| {
+            RpcError::InvalidParameter(
+                "no message has been published on this link yet".to_string(),
+            )
+        })?;
+        let body = serde_json::to_vec(&record)
+            .map_err(|e| RpcError::Ser(format!("serializing last publish record: {}", e)))?;
+        Ok(ReplyMessage {
+            body,
+            reply_to: None,
+            subject: CONTROL_LAST_PUBLISH_SUBJECT.to_string(),
+        })
+    }
+
+    /// Pick the queue a publish should go to. Without subject routing - or
+    /// with an empty subject - that is always the queue the link was resolved
+    /// against; otherwise the subject names the queue and its url is resolved
+    /// once and cached.
+    async fn resolve_queue_url(&self, subject: &str) -> RpcResult<String> {
+        if !self.config.subject_routing || subject.is_empty() {
+            if self.queue_url.is_empty() {
+                return Err(RpcError::InvalidParameter(
+                    "link has no publish-role queue configured".to_string(),
+                ));
+            }
+            return Ok(self.queue_url.clone());
+        }
+        if self.config.cache_queue_url {
+            if let Some(url) = self.resolved_urls.read().await.get(subject) {
+                return Ok(url.clone());
+            }
+        }
+        let url = self
+            .client
+            .get_queue_url()
+            .queue_name(subject)
+            .set_queue_owner_aws_account_id(self.config.queue_owner_account_id.clone())
+            .send()
+            .await
+            .map_err(|e| {
+                SqsProviderError::QueueNotFound(format!(
+                    "unable to resolve queue url for subject '{}': {}",
+                    subject, e
+                ))
+            })?
+            .queue_url()
+            .map(|u| u.to_string())
+            .ok_or_else(|| {
+                SqsProviderError::QueueNotFound(format!(
+                    "sqs returned no queue url for subject '{}'",
+                    subject
+                ))
+            })?;
+        if self.config.cache_queue_url {
+            self.resolved_urls
+                .write()
+                .await
+                .insert(subject.to_string(), url.clone());
+        }
+        Ok(url)
+    }
+
+    /// Forget a cached queue url, typically because sqs just reported the
+    /// queue gone; the next resolve fetches a fresh one
+    async fn invalidate_queue_url(&self, subject: &str) {
+        self.resolved_urls.write().await.remove(subject);
+    }
+}
+
+/// SQS implementation for wasmcloud:messaging
+#[derive(Default, Clone, Provider)]
+#[services(Messaging)]
+struct SqsMessagingProvider {
+    // Store sqs client and resolved queue url per actor. This is a tokio
+    // RwLock rather than std's: it can be held across awaits and does not
+    // poison, so a panic in one task can never brick the map for the rest of
+    // the provider.
+    actors: Arc<RwLock<HashMap<String, SqsClientBundle>>>,
+    // Clients shared by every link with the same region, credentials and
+    // endpoint, refcounted so the last delete_link drops the connection pool.
+    clients: Arc<RwLock<HashMap<String, CachedClient>>>,
+    // The last health probe and when it ran, so frequent host health polls
+    // don't each turn into a round of get_queue_attributes calls.
+    health: Arc<RwLock<Option<(std::time::Instant, HealthCheckResponse)>>>,
+}
+
+/// a shared sqs client plus the number of links currently using it
+struct CachedClient {
+    client: sqs::Client,
+    refs: usize,
+}
+
+/// Cache key grouping links that would build identical clients: the same
+/// region, credentials and endpoint override. The secret key is paired with
+/// the access key id by config validation, so the id alone fingerprints the
+/// credentials without copying the secret into the key. The profile is part
+/// of the key because it selects a different credentials provider entirely,
+/// and the assume-role settings because they stack an sts provider on top:
+/// links that assume different roles (or none) must never share a client.
+fn client_cache_key(config: &SQSConfig) -> String {
+    // an explicit credential_source overrides the provider the other fields
+    // would imply, so env/imds/ecs links in one region get distinct clients
+    let source = config
+        .credential_source
+        .as_ref()
+        .map(|source| format!("{:?}", source))
+        .unwrap_or_default();
+    // the client's timeout and retry budget are baked into its SdkConfig,
+    // so links tuned differently can't share one
+    let tuning = format!(
+        "{:?}|{:?}|{:?}",
+        config.connect_timeout_ms, config.operation_timeout_ms, config.max_attempts
+    );
+    [
+        source.as_str(),
+        tuning.as_str(),
+        config.aws_region.as_deref().unwrap_or_default(),
+        config.access_key_id.as_deref().unwrap_or_default(),
+        config.session_token.as_deref().unwrap_or_default(),
+        config.endpoint_url.as_deref().unwrap_or_default(),
+        config.aws_profile.as_deref().unwrap_or_default(),
+        config.assume_role_arn.as_deref().unwrap_or_default(),
+        config.assume_role_session_name.as_deref().unwrap_or_default(),
+        config.assume_role_external_id.as_deref().unwrap_or_default(),
+    ]
+    .join("\u{1f}")
+}
+
+// use default implementations of provider message handlers
+impl ProviderDispatch for SqsMessagingProvider {}
+
+impl SqsMessagingProvider {
+    /// Build an sqs client for a link, preferring the region, credentials and
+    /// endpoint configured on the link over whatever the ambient environment
+    /// resolves to
+    async fn build_client(config: &SQSConfig) -> RpcResult<sqs::Client> {
+        let aws_config = config.configure_aws().await;
+        // an assume-role misconfiguration (bad arn, missing trust, wrong
+        // external id) should deny the link now, not fail the first send
+        if config.assume_role_arn.is_some() {
+            use aws_types::credentials::ProvideCredentials;
+            if let Some(provider) = aws_config.credentials_provider() {
+                provider.provide_credentials().await.map_err(|e| {
+                    SqsProviderError::ClientBuild(format!("unable to assume role: {}", e))
+                })?;
+            }
+        }
+        let mut builder = sqs::config::Builder::from(&aws_config);
+        if let Some(endpoint) = config.endpoint()? {
+            builder = builder.endpoint_resolver(endpoint);
+        } else if let Some(endpoint) = config.partition_endpoint("sqs") {
+            // gov/cn partitions get their hostnames pinned explicitly so a
+            // region like us-gov-west-1 never resolves a commercial endpoint
+            builder = builder.endpoint_resolver(endpoint);
+        }
+        Ok(sqs::Client::from_conf(builder.build()))
+    }
+
+    /// Build the companion sns client for a link that enables sns publishing,
+    /// sharing the link's region, credentials and endpoint override
+    async fn build_sns_client(config: &SQSConfig) -> RpcResult<sns::Client> {
+        let aws_config = config.configure_aws().await;
+        let mut builder = sns::config::Builder::from(&aws_config);
+        if let Some(endpoint) = config.endpoint()? {
+            builder = builder.endpoint_resolver(endpoint);
+        } else if let Some(endpoint) = config.partition_endpoint("sns") {
+            builder = builder.endpoint_resolver(endpoint);
+        }
+        Ok(sns::Client::from_conf(builder.build()))
+    }
+
+    /// A dedicated client for a binding that lives behind its own endpoint.
+    /// None means the binding uses the link's shared client: either no
+    /// override is set, or it matches the link-level endpoint anyway.
+    async fn binding_client(
+        config: &SQSConfig,
+        binding: &QueueBinding,
+    ) -> RpcResult<Option<sqs::Client>> {
+        match &binding.endpoint_url {
+            Some(endpoint) if config.endpoint_url.as_ref() != Some(endpoint) => {
+                let patched = SQSConfig {
+                    endpoint_url: Some(endpoint.clone()),
+                    ..config.clone()
+                };
+                Ok(Some(Self::build_client(&patched).await?))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Resolve a link's failover queue and breaker, when it configures one.
+    /// A failover_region gets its own client against that region; otherwise
+    /// the primary's client doubles for the (same-region) failover queue.
+    async fn build_failover(
+        client: &sqs::Client,
+        config: &SQSConfig,
+    ) -> RpcResult<(Option<Arc<FailoverBreaker>>, Option<sqs::Client>, Option<String>)> {
+        let failover_name = match &config.failover_queue_name {
+            Some(name) => name,
+            None => return Ok((None, None, None)),
+        };
+        let failover_client = match &config.failover_region {
+            Some(region) if config.aws_region.as_ref() != Some(region) => {
+                let patched = SQSConfig {
+                    aws_region: Some(region.clone()),
+                    ..config.clone()
+                };
+                Some(Self::build_client(&patched).await?)
+            }
+            _ => None,
+        };
+        let patched;
+        let (resolve_client, resolve_config) = match (&failover_client, &config.failover_region) {
+            (Some(failover_client), Some(region)) => {
+                patched = SQSConfig {
+                    aws_region: Some(region.clone()),
+                    ..config.clone()
+                };
+                (failover_client, &patched)
+            }
+            _ => (client, config),
+        };
+        let failover_url = Self::resolve_queue(resolve_client, resolve_config, failover_name)
+            .await?
+            .ok_or_else(|| {
+                RpcError::ProviderInit(format!(
+                    "failover queue '{}' does not exist",
+                    failover_name
+                ))
+            })?;
+        let breaker = Arc::new(FailoverBreaker::new(
+            config.failover_threshold,
+            Duration::from_millis(config.failover_probe_interval_ms),
+        ));
+        Ok((Some(breaker), failover_client, Some(failover_url)))
+    }
+
+    /// Build the companion s3 client for a link that offloads large payloads,
+    /// sharing the link's region, credentials and endpoint override
+    async fn build_s3_client(config: &SQSConfig) -> RpcResult<s3::Client> {
+        let aws_config = config.configure_aws().await;
+        let mut builder = s3::config::Builder::from(&aws_config);
+        if let Some(endpoint) = config.endpoint()? {
+            builder = builder.endpoint_resolver(endpoint);
+        } else if let Some(endpoint) = config.partition_endpoint("s3") {
+            builder = builder.endpoint_resolver(endpoint);
+        }
+        Ok(s3::Client::from_conf(builder.build()))
+    }
+
+    /// Hand out the shared client for this config, building one only if no
+    /// other link is already using an identical region/credentials/endpoint
+    /// combination.
+    async fn checkout_client(&self, config: &SQSConfig) -> RpcResult<(sqs::Client, String)> {
+        let key = client_cache_key(config);
+        let mut clients = self.clients.write().await;
+        if let Some(cached) = clients.get_mut(&key) {
+            cached.refs += 1;
+            return Ok((cached.client.clone(), key));
+        }
+        let client = Self::build_client(config).await?;
+        clients.insert(
+            key.clone(),
+            CachedClient {
+                client: client.clone(),
+                refs: 1,
+            },
+        );
+        Ok((client, key))
+    }
+
+    /// Release one link's reference to a shared client, dropping the client
+    /// once no link uses it any more.
+    async fn release_client(&self, key: &str) {
+        let mut clients = self.clients.write().await;
+        if let Some(cached) = clients.get_mut(key) {
+            cached.refs -= 1;
+            if cached.refs == 0 {
+                clients.remove(key);
+            }
+        }
+    }
+
+    /// Rebuild a link's client after its credentials expired, re-running the
+    /// credential provider (including a fresh sts assume for assume-role
+    /// links). The shared cache and every bundle using the old client are
+    /// updated so later calls don't trip over the same expiry.
+    async fn refresh_client(&self, config: &SQSConfig, client_key: &str) -> RpcResult<sqs::Client> {
+        let client = Self::build_client(config).await?;
+        if let Some(cached) = self.clients.write().await.get_mut(client_key) {
+            cached.client = client.clone();
+        }
+        let mut actors = self.actors.write().await;
+        for bundle in actors.values_mut() {
+            if bundle.client_key == client_key {
+                bundle.client = client.clone();
+            }
+        }
+        Ok(client)
+    }
+
+    /// Resolve every bound queue, start the receive loops and register the
+    /// actor's bundle. Ok(false) denies the link without registering anything;
+    /// the caller returns the checked-out client on any non-success.
+    async fn start_link(
+        &self,
+        ld: &LinkDefinition,
+        config: SQSConfig,
+        client: sqs::Client,
+        client_key: String,
+    ) -> RpcResult<bool> {
+        // resolve every bound queue once at link time; publish/request use
+        // these urls directly instead of picking arbitrary queues off the account
+        let mut resolved = Vec::with_capacity(config.bindings.len());
+        for binding in &config.bindings {
+            let binding_client = Self::binding_client(&config, binding)
+                .await?
+                .unwrap_or_else(|| client.clone());
+            match Self::resolve_queue(&binding_client, &config, &binding.name).await? {
+                Some(url) => resolved.push((binding.clone(), url, binding_client)),
+                None => return Ok(false),
+            }
+        }
+        // the primary publish target, with its client: a binding-level
+        // endpoint on the primary queue redirects publishes too
+        let (queue_url, client) = resolved
+            .iter()
+            .find(|(binding, _, _)| binding.publishes())
+            .map(|(_, url, binding_client)| (url.clone(), binding_client.clone()))
+            .unwrap_or((String::default(), client));
+
+        // attach the dead-letter queue before any receive loop starts, so a
+        // poison message can never be redelivered forever
+        if config.dead_letter_queue_name.is_some() {
+            if let Some((_, main_url, main_client)) = resolved
+                .iter()
+                .find(|(binding, _, _)| binding.name == config.queue_name)
+            {
+                Self::configure_dead_letter_queue(main_client, &config, main_url).await?;
+            }
+        }
+
+        // start one background receive loop per subscribe-role queue
+        let cancel = CancellationToken::new();
+        let metrics = Arc::new(Metrics::default());
+        let (batch_tx, flush_handle) = if config.batch_flush_ms > 0 {
+            let (tx, handle) = spawn_flusher(
+                client.clone(),
+                Duration::from_millis(config.batch_flush_ms),
+                cancel.clone(),
+            );
+            (Some(tx), Some(Arc::new(handle)))
+        } else {
+            (None, None)
+        };
+        let s3_client = match &config.large_payload_bucket {
+            Some(_) => Some(Self::build_s3_client(&config).await?),
+            None => None,
+        };
+        let subscribe_queues: Vec<(String, String)> = resolved
+            .iter()
+            .filter(|(binding, _, _)| binding.subscribes())
+            .map(|(binding, url, _)| (binding.name.clone(), url.clone()))
+            .collect();
+        let paused = Arc::new(AtomicBool::new(false));
+        let mut poll_handles: Vec<Arc<JoinHandle<()>>> = resolved
+            .iter()
+            .filter(|(binding, _, _)| binding.subscribes())
+            .flat_map(|(binding, url, binding_client)| {
+                Self::spawn_consumers(
+                    binding_client.clone(),
+                    s3_client.clone(),
+                    binding.clone(),
+                    url.clone(),
+                    config.clone(),
+                    cancel.clone(),
+                    metrics.clone(),
+                    paused.clone(),
+                    ld,
+                )
+            })
+            .collect();
+        if config.queue_name_prefix.is_some() {
+            let known = resolved
+                .iter()
+                .filter(|(binding, _, _)| binding.subscribes())
+                .map(|(_, url, _)| url.clone())
+                .collect();
+            poll_handles.push(Arc::new(Self::discover_queues(
+                client.clone(),
+                s3_client.clone(),
+                config.clone(),
+                cancel.clone(),
+                metrics.clone(),
+                paused.clone(),
+                ld,
+                known,
+            )));
+        }
+
+        let sns_client = if config.enable_sns_publish {
+            Some(Self::build_sns_client(&config).await?)
+        } else {
+            None
+        };
+        let config_rate_limiter = config
+            .publish_rate_limit
+            .map(|rate| Arc::new(TokenBucket::new(rate)));
+        let (failover, failover_client, failover_queue_url) =
+            Self::build_failover(&client, &config).await?;
+
+        self.replace_bundle(
+            &ld.actor_id,
+            SqsClientBundle {
+                client,
+                queue_url,
+                config,
+                cancel,
+                poll_handles,
+                subscribe_queues,
+                resolved_urls: Arc::default(),
+                batch_tx,
+                flush_handle,
+                metrics,
+                client_key,
+                sns_client,
+                last_publish: Arc::default(),
+                s3_client,
+                rate_limiter: config_rate_limiter,
+                failover,
+                failover_client,
+                failover_queue_url,
+                paused,
+            },
+        )
+        .await;
+
+        Ok(true)
+    }
+
+    /// Register an actor's bundle, tearing the previous one down when the
+    /// link is an update rather than a first link. The new entry goes into
+    /// the map before the old loops are cancelled, so there is no window in
+    /// which the actor is linked but unserved; the old loops finish the poll
+    /// they are in, so messages already received under the old settings are
+    /// still dispatched and acknowledged.
+    async fn replace_bundle(&self, actor_id: &str, bundle: SqsClientBundle) {
+        let replaced = self
+            .actors
+            .write()
+            .await
+            .insert(actor_id.to_string(), bundle);
+        if let Some(old) = replaced {
+            debug!(%actor_id, "relink: stopping the previous receive loops");
+            old.cancel.cancel();
+            self.release_client(&old.client_key).await;
+        }
+    }
+
+    /// Spawn the discovery loop for a queue_name_prefix link: list every
+    /// queue matching the prefix, start a receive loop for each one not
+    /// already subscribed, and repeat at the configured refresh interval so
+    /// queues created after link time get picked up. The spawned receive
+    /// loops share the link's cancellation token, so shutdown stops them
+    /// even though their handles aren't tracked individually.
+    #[allow(clippy::too_many_arguments)]
+    fn discover_queues(
+        client: sqs::Client,
+        s3_client: Option<s3::Client>,
+        config: SQSConfig,
+        cancel: CancellationToken,
+        metrics: Arc<Metrics>,
+        paused: Arc<AtomicBool>,
+        ld: &LinkDefinition,
+        mut known: std::collections::HashSet<String>,
+    ) -> JoinHandle<()> {
+        let link_def = ld.to_owned();
+        let refresh = Duration::from_secs(config.prefix_refresh_seconds);
+        tokio::spawn(async move {
+            let prefix = config.queue_name_prefix.clone().unwrap_or_default();
+            loop {
+                match client.list_queues().queue_name_prefix(&prefix).send().await {
+                    Ok(listed) => {
+                        let listed: Vec<String> = listed
+                            .queue_urls()
+                            .unwrap_or_default()
+                            .iter()
+                            .map(|url| url.to_string())
+                            .collect();
+                        for url in new_queue_urls(&known, &listed) {
+                            debug!(
+                                actor_id = %link_def.actor_id,
+                                queue_url = %url,
+                                "subscribing to newly discovered queue"
+                            );
+                            let binding = QueueBinding {
+                                name: url.clone(),
+                                role: QueueRole::Subscribe,
+                                weight: 1,
+                                endpoint_url: None,
+                            };
+                            Self::spawn_consumers(
+                                client.clone(),
+                                s3_client.clone(),
+                                binding,
+                                url.clone(),
+                                config.clone(),
+                                cancel.clone(),
+                                metrics.clone(),
+                                paused.clone(),
+                                &link_def,
+                            );
+                            known.insert(url);
+                        }
+                    }
+                    Err(e) => {
+                        warn!(
+                            actor_id = %link_def.actor_id,
+                            error = %sdk_error_string(&e),
+                            "sqs list_queues failed; retrying at the next refresh"
+                        );
+                    }
+                }
+                tokio::select! {
+                    _ = cancel.cancelled() => break,
+                    _ = tokio::time::sleep(refresh) => {}
+                }
+            }
+            debug!(actor_id = %link_def.actor_id, "queue discovery loop exited");
+        })
+    }
+
+    /// Spawn the link's poll loops for one queue: consumer_count parallel
+    /// copies of [`Self::subscribe`], each with its own receive cursor.
+    /// They share the metrics and the cancel token, so counters aggregate
+    /// across loops and one cancellation stops them all.
+    #[allow(clippy::too_many_arguments)]
+    fn spawn_consumers(
+        client: sqs::Client,
+        s3_client: Option<s3::Client>,
+        binding: QueueBinding,
+        queue_url: String,
+        config: SQSConfig,
+        cancel: CancellationToken,
+        metrics: Arc<Metrics>,
+        paused: Arc<AtomicBool>,
+        ld: &LinkDefinition,
+    ) -> Vec<Arc<JoinHandle<()>>> {
+        let adaptive = Arc::new(AdaptiveConcurrency::new(
+            config.max_concurrent_handlers,
+            config.max_number_of_messages,
+        ));
+        (0..config.consumer_count.max(1))
+            .map(|_| {
+                Arc::new(Self::subscribe(
+                    client.clone(),
+                    s3_client.clone(),
+                    binding.clone(),
+                    queue_url.clone(),
+                    config.clone(),
+                    cancel.clone(),
+                    metrics.clone(),
+                    adaptive.clone(),
+                    paused.clone(),
+                    ld,
+                ))
+            })
+            .collect()
+    }
+
+    /// Spawn the receive loop that long-polls the linked queue and forwards
+    /// each message to the actor's message handler. The loop exits once the
+    /// cancellation token is signalled, finishing any poll already in flight
+    /// so messages are never half-dispatched.
+    #[allow(clippy::too_many_arguments)]
+    fn subscribe(
+        client: sqs::Client,
+        s3_client: Option<s3::Client>,
+        binding: QueueBinding,
+        queue_url: String,
+        config: SQSConfig,
+        cancel: CancellationToken,
+        metrics: Arc<Metrics>,
+        adaptive: Arc<AdaptiveConcurrency>,
+        paused: Arc<AtomicBool>,
+        ld: &LinkDefinition,
+    ) -> JoinHandle<()> {
+        let link_def = ld.to_owned();
+        let queue_name = binding.name;
+        let batch_size = weighted_batch_size(config.max_number_of_messages, binding.weight);
+        let mut backoff = Backoff::new(Duration::from_secs(config.receive_backoff_max_seconds));
+        // resolved on first use when client-side quarantine is configured
+        let mut dlq_url: Option<String> = None;
+        let fifo = is_fifo(&queue_url);
+        // held across failed fifo receives so the retry reuses the same
+        // attempt id; cleared once a receive succeeds
+        let mut attempt_id: Option<String> = None;
+        // consecutive polls that returned nothing, for idle notification
+        let mut empty_polls: u64 = 0;
+        tokio::spawn(async move {
+            loop {
+                // parked by the pause control subject: recheck shortly
+                // instead of fetching, so messages stay queued until resume
+                if paused.load(Ordering::Relaxed) {
+                    tokio::select! {
+                        _ = cancel.cancelled() => break,
+                        _ = tokio::time::sleep(Duration::from_millis(PAUSE_POLL_MS)) => {}
+                    }
+                    continue;
+                }
+                let attempt = fifo.then(|| next_attempt_id(&mut attempt_id));
+                let received = tokio::select! {
+                    _ = cancel.cancelled() => break,
+                    received = client
+                        .receive_message()
+                        .queue_url(&queue_url)
+                        .wait_time_seconds(config.wait_time_seconds)
+                        .max_number_of_messages(batch_size.min(adaptive.batch()))
+                        .set_visibility_timeout(config.visibility_timeout_seconds)
+                        .message_attribute_names("All")
+                        .attribute_names(sqs::model::QueueAttributeName::All)
+                        .set_receive_request_attempt_id(attempt)
+                        .send() => received,
+                };
+                let received = match received {
+                    Ok(received) => {
+                        backoff.reset();
+                        adaptive.on_success();
+                        attempt_id = None;
+                        received
+                    }
+                    Err(e) => {
+                        let error_text = sdk_error_string(&e);
+                        if is_throttling_error(&error_text) {
+                            adaptive.on_throttle();
+                        }
+                        // fatal and config errors won't clear on their own;
+                        // retry at the backoff cap instead of hammering a
+                        // queue that is gone or credentials that are wrong
+                        let delay = match classify_sdk_error(&error_text) {
+                            SdkErrorClass::Retryable => backoff.next_delay(),
+                            SdkErrorClass::Fatal | SdkErrorClass::Config => backoff.saturate(),
+                        };
+                        warn!(
+                            error = %error_text,
+                            actor_id = %link_def.actor_id,
+                            delay_ms = delay.as_millis() as u64,
+                            "sqs receive_message failed; backing off before retrying"
+                        );
+                        tokio::select! {
+                            _ = cancel.cancelled() => break,
+                            _ = tokio::time::sleep(delay) => {}
+                        }
+                        continue;
+                    }
+                };
+                // dispatch the full batch before checking for cancellation
+                // again, then acknowledge everything that was handled in one
+                // delete call instead of one per message
+                let messages = received.messages().unwrap_or_default();
+                Metrics::add(&metrics.received, messages.len() as u64);
+                if messages.is_empty() {
+                    empty_polls += 1;
+                    // one notification per idle stretch: the count keeps
+                    // climbing past the threshold without firing again
+                    if idle_event_due(empty_polls, config.idle_notify_polls) {
+                        debug!(
+                            actor_id = %link_def.actor_id,
+                            empty_polls,
+                            "queue went idle; notifying the actor"
+                        );
+                        let actor = MessageSubscriberSender::for_actor(&link_def);
+                        if let Err(e) = actor
+                            .handle_message(
+                                &dispatch_context(&link_def),
+                                &idle_notification(&queue_name, empty_polls),
+                            )
+                            .await
+                        {
+                            warn!(error = %e, "actor failed to handle the idle notification");
+                        }
+                    }
+                    // even long polling returns after the wait time on an
+                    // idle queue; links that opt in trade a little latency
+                    // for fewer empty requests. A busy queue never pauses.
+                    if config.idle_backoff_ms > 0 {
+                        tokio::select! {
+                            _ = cancel.cancelled() => break,
+                            _ = tokio::time::sleep(Duration::from_millis(config.idle_backoff_ms)) => {}
+                        }
+                    }
+                    continue;
+                }
+                empty_polls = 0;
+                let mut to_dispatch = Vec::with_capacity(messages.len());
+                let mut filtered_receipts = Vec::new();
+                let mut expired_receipts = Vec::new();
+                for message in messages {
+                    // a message past its expires-at stamp is deleted unread;
+                    // sqs only has queue-level retention, so per-message ttl
+                    // is enforced here at receive time
+                    if message_expired(message, epoch_seconds()) {
+                        debug!(
+                            message_id = %message.message_id().unwrap_or_default(),
+                            "dropping expired message"
+                        );
+                        if let Some(receipt) = message.receipt_handle() {
+                            expired_receipts.push(receipt.to_string());
+                        }
+                        continue;
+                    }
+                    // a non-matching message is someone else's: left for the
+                    // queue's other consumers unless the link says otherwise
+                    if !matches_subscribe_filter(message, &config.subscribe_filter) {
+                        if config.delete_filtered {
+                            if let Some(receipt) = message.receipt_handle() {
+                                filtered_receipts.push(receipt.to_string());
+                            }
+                        }
+                        continue;
+                    }
+                    // a message that keeps failing is quarantined instead of
+                    // crash-looping the actor until the queue's own redrive
+                    // policy (if any) kicks in
+                    if exceeded_processing_attempts(message, config.max_processing_attempts) {
+                        if let Some(dlq_name) = config.dead_letter_queue_name.as_deref() {
+                            if let Some(dlq) =
+                                quarantine_queue_url(&client, dlq_name, &mut dlq_url).await
+                            {
+                                warn!(
+                                    actor_id = %link_def.actor_id,
+                                    message_id = %message.message_id().unwrap_or_default(),
+                                    "message exceeded max_processing_attempts; moving it to the dead-letter queue"
+                                );
+                                quarantine_message(&client, &dlq, &queue_url, message).await;
+                                continue;
+                            }
+                        }
+                    }
+                    to_dispatch.push(message.clone());
+                }
+                if !filtered_receipts.is_empty() {
+                    delete_batch(&client, &queue_url, filtered_receipts, &metrics).await;
+                }
+                if !expired_receipts.is_empty() {
+                    delete_batch(&client, &queue_url, expired_receipts, &metrics).await;
+                }
+                // at-most-once acknowledges up front: a handler crash after
+                // this point loses the message instead of redelivering it
+                if config.delivery_mode == DeliveryMode::AtMostOnce {
+                    let receipts: Vec<String> = to_dispatch
+                        .iter()
+                        .filter_map(|m| m.receipt_handle().map(|r| r.to_string()))
+                        .collect();
+                    if !receipts.is_empty() {
+                        delete_batch(&client, &queue_url, receipts, &metrics).await;
+                    }
+                }
+                // receipt -> pointer of each offloaded message, so the s3
+                // object can be removed once the message is acknowledged
+                let offloaded: HashMap<String, (String, String)> = to_dispatch
+                    .iter()
+                    .filter(|m| is_offloaded(m))
+                    .filter_map(|m| {
+                        let receipt = m.receipt_handle()?.to_string();
+                        let pointer = parse_s3_pointer(m.body().unwrap_or_default())?;
+                        Some((receipt, pointer))
+                    })
+                    .collect();
+                let batch = batch_span(&queue_url, to_dispatch.len(), &link_def.actor_id);
+                let (handled, failed) = dispatch_batch(to_dispatch, adaptive.handlers(), {
+                    let link_def = link_def.clone();
+                    let config = config.clone();
+                    let queue_name = queue_name.clone();
+                    let client = client.clone();
+                    let s3_client = s3_client.clone();
+                    let queue_url = queue_url.clone();
+                    move |message| {
+                        let span = message_span(&batch, &message);
+                        let link_def = link_def.clone();
+                        let config = config.clone();
+                        let queue_name = queue_name.clone();
+                        // a heartbeat only makes sense while the message is
+                        // still ours to acknowledge
+                        let heartbeat = (config.visibility_heartbeat
+                            && config.delivery_mode == DeliveryMode::AtLeastOnce)
+                            .then(|| message.receipt_handle().map(|r| r.to_string()))
+                            .flatten()
+                            .map(|receipt| {
+                                spawn_heartbeat(
+                                    client.clone(),
+                                    queue_url.clone(),
+                                    receipt,
+                                    &config,
+                                )
+                            });
+                        let s3_client = s3_client.clone();
+                        async move {
+                            let handled = dispatch_message(
+                                &link_def,
+                                &config,
+                                &queue_name,
+                                &message,
+                                s3_client.as_ref(),
+                            )
+                            .await;
+                            if let Some(heartbeat) = heartbeat {
+                                heartbeat.abort();
+                            }
+                            handled
+                        }
+                        .instrument(span)
+                    }
+                })
+                .await;
+                Metrics::add(&metrics.dispatched, handled.len() as u64);
+                if config.message_auto_delete && config.delivery_mode == DeliveryMode::AtLeastOnce {
+                    let handled_receipts: Vec<String> = handled.into_iter().flatten().collect();
+                    if !handled_receipts.is_empty() {
+                        // once deleted, nothing references the pointer
+                        // objects of handled offloaded messages any more
+                        if let Some(s3_client) = &s3_client {
+                            for receipt in &handled_receipts {
+                                if let Some((bucket, key)) = offloaded.get(receipt) {
+                                    delete_offloaded_object(s3_client, bucket, key).await;
+                                }
+                            }
+                        }
+                        delete_batch(&client, &queue_url, handled_receipts, &metrics).await;
+                    }
+                }
+                // at-most-once already acknowledged up front, so failed
+                // messages are gone regardless of the configured policy
+                if !failed.is_empty() && config.delivery_mode == DeliveryMode::AtLeastOnce {
+                    match config.dispatch_error_policy {
+                        DispatchErrorPolicy::Redeliver => {}
+                        DispatchErrorPolicy::FastRetry => {
+                            fast_retry_batch(&client, &queue_url, failed).await
+                        }
+                        DispatchErrorPolicy::Drop => {
+                            delete_batch(&client, &queue_url, failed, &metrics).await
+                        }
+                    }
+                }
+                metrics.emit(&link_def.actor_id);
+                debug!(
+                    actor_id = %link_def.actor_id,
+                    effective_handlers = adaptive.handlers(),
+                    effective_batch = adaptive.batch(),
+                    "adaptive concurrency"
+                );
+            }
+            debug!(actor_id = %link_def.actor_id, "sqs receive loop exited");
+        })
+    }
+
+    /// Probe every linked actor's queue with a lightweight
+    /// get_queue_attributes call. Healthy means every probe succeeded; the
+    /// message lists each actor whose queue could not be reached.
+    async fn probe_actor_queues(&self) -> HealthCheckResponse {
+        let probes: Vec<(String, sqs::Client, String)> = {
+            let rd = self.actors.read().await;
+            rd.iter()
+                .filter_map(|(actor_id, bundle)| {
+                    // a subscribe-only link has no publish queue; probe the
+                    // first queue it polls instead
+                    let queue_url = if !bundle.queue_url.is_empty() {
+                        Some(bundle.queue_url.clone())
+                    } else {
+                        bundle.subscribe_queues.first().map(|(_, url)| url.clone())
+                    };
+                    queue_url.map(|url| (actor_id.clone(), bundle.client.clone(), url))
+                })
+                .collect()
+        };
+        let mut failures = Vec::new();
+        for (actor_id, client, queue_url) in probes {
+            if let Err(e) = client
+                .get_queue_attributes()
+                .queue_url(&queue_url)
+                .attribute_names(sqs::model::QueueAttributeName::QueueArn)
+                .send()
+                .await
+            {
+                failures.push(format!("{}: {}", actor_id, sdk_error_string(&e)));
+            }
+        }
+        if failures.is_empty() {
+            HealthCheckResponse {
+                healthy: true,
+                message: None,
+            }
+        } else {
+            HealthCheckResponse {
+                healthy: false,
+                message: Some(format!("degraded: {}", failures.join("; "))),
+            }
+        }
+    }
+
+    /// look up the sqs client and queue for the actor that sent the current message
+    async fn bundle_for_actor(&self, ctx: &Context) -> RpcResult<SqsClientBundle> {
+        let fallback;
+        let actor_id = match ctx.actor.as_ref() {
+            Some(actor_id) => actor_id,
+            None => {
+                fallback = std::env::var(DEFAULT_ACTOR_ENV)
+                    .ok()
+                    .filter(|v| !v.is_empty());
+                let actor_id = fallback.as_ref().ok_or_else(|| {
+                    SqsProviderError::ConfigMissing("no actor in request".to_string())
+                })?;
+                warn!(
+                    %actor_id,
+                    "invocation carried no actor identity; falling back to {}", DEFAULT_ACTOR_ENV
+                );
+                actor_id
+            }
+        };
+
+        // get read lock on actor-client hashmap to get the bundle, then drop it
+        let rd = self.actors.read().await;
+        rd.get(actor_id).cloned().ok_or_else(|| {
+            SqsProviderError::ConfigMissing(format!("actor not linked:{}", actor_id)).into()
+        })
+    }
+
+    /// Resolve a queue's url, creating the queue when the link opts in.
+    /// Ok(None) means the link should be denied (already logged); Err means a
+    /// transient aws failure worth retrying.
+    async fn resolve_queue(
+        client: &sqs::Client,
+        config: &SQSConfig,
+        queue_name: &str,
+    ) -> RpcResult<Option<String>> {
+        // full urls and arns name an existing queue directly; only a bare
+        // name goes through get_queue_url (and possibly create_queue)
+        if let Some(queue_url) = queue_url_from_identifier(queue_name)? {
+            // a url whose region disagrees with the client would fail every
+            // call with an opaque signature error; name the mismatch instead
+            if let (Some(url_region), Some(client_region)) =
+                (queue_url_region(&queue_url), config.aws_region.as_deref())
+            {
+                if url_region != client_region {
+                    return Err(RpcError::InvalidParameter(format!(
+                        "queue url '{}' is in region {} but the link configures aws_region {}; \
+                         set aws_region to the queue's region or bind the queue by name",
+                        queue_url, url_region, client_region
+                    )));
+                }
+            }
+            return Ok(Some(queue_url));
+        }
+        let mut created = false;
+        let queue_url = match client
+            .get_queue_url()
+            .queue_name(queue_name)
+            .set_queue_owner_aws_account_id(config.queue_owner_account_id.clone())
+            .send()
+            .await
+        {
+            Ok(resolved) => resolved.queue_url().map(|u| u.to_string()),
+            Err(sqs::types::SdkError::ServiceError { err, .. })
+                if err.is_queue_does_not_exist() =>
+            {
+                if config.create_queue_if_missing {
+                    debug!(%queue_name, "creating missing sqs queue");
+                    created = true;
+                    // create_queue is idempotent for identical attributes, so a
+                    // race between two links creating the same queue is harmless
+                    let mut create = client.create_queue().queue_name(queue_name);
+                    for (name, value) in create_queue_attributes(config, queue_name) {
+                        create = create.attributes(name, value);
+                    }
+                    create
+                        .send()
+                        .await
+                        .map_err(|e| {
+                            RpcError::ProviderInit(format!(
+                                "unable to create sqs queue '{}': {}",
+                                queue_name, e
+                            ))
+                        })?
+                        .queue_url()
+                        .map(|u| u.to_string())
+                } else {
+                    error!(
+                        %queue_name,
+                        "denying link: queue does not exist and create_queue_if_missing is not set"
+                    );
+                    return Ok(None);
+                }
+            }
+            Err(e) => {
+                return Err(RpcError::ProviderInit(format!(
+                    "unable to resolve sqs queue '{}': {}",
+                    queue_name, e
+                )))
+            }
+        };
+        let queue_url = queue_url.ok_or_else(|| {
+            RpcError::ProviderInit(format!("sqs returned no url for queue '{}'", queue_name))
+        })?;
+        // tag queues we just provisioned; pre-existing queues keep whatever
+        // tags they have unless the link asks for reconciliation
+        if !config.queue_tags.is_empty() && (created || config.reconcile_tags) {
+            Self::tag_queue(client, &queue_url, &config.queue_tags).await;
+        }
+        Ok(Some(queue_url))
+    }
+
+    /// Apply the link's cost-allocation tags to a queue. Tagging is best
+    /// effort: a failure is logged but never takes the link down, since the
+    /// queue itself is usable.
+    async fn tag_queue(client: &sqs::Client, queue_url: &str, tags: &HashMap<String, String>) {
+        let mut tag = client.tag_queue().queue_url(queue_url);
+        for (key, value) in tags {
+            tag = tag.tags(key, value);
+        }
+        if let Err(e) = tag.send().await {
+            warn!(
+                %queue_url,
+                error = %sdk_error_string(&e),
+                "unable to tag queue"
+            );
+        }
+    }
+
+    /// Ensure the configured dead-letter queue exists and point the main
+    /// queue's redrive policy at it. The dlq is created unconditionally:
+    /// naming one in the link is the opt-in.
+    async fn configure_dead_letter_queue(
+        client: &sqs::Client,
+        config: &SQSConfig,
+        queue_url: &str,
+    ) -> RpcResult<()> {
+        let dlq_name = config
+            .dead_letter_queue_name
+            .as_ref()
+            .expect("caller checked dead_letter_queue_name");
+        let dlq_url = match client.get_queue_url().queue_name(dlq_name).send().await {
+            Ok(resolved) => resolved.queue_url().map(|u| u.to_string()),
+            Err(sqs::types::SdkError::ServiceError { err, .. })
+                if err.is_queue_does_not_exist() =>
+            {
+                debug!(queue_name = %dlq_name, "creating missing dead-letter queue");
+                client
+                    .create_queue()
+                    .queue_name(dlq_name)
+                    .send()
+                    .await
+                    .map_err(|e| {
+                        RpcError::ProviderInit(format!(
+                            "unable to create dead-letter queue '{}': {}",
+                            dlq_name, e
+                        ))
+                    })?
+                    .queue_url()
+                    .map(|u| u.to_string())
+            }
+            Err(e) => {
+                return Err(RpcError::ProviderInit(format!(
+                    "unable to resolve dead-letter queue '{}': {}",
+                    dlq_name, e
+                )))
+            }
+        }
+        .ok_or_else(|| {
+            RpcError::ProviderInit(format!("sqs returned no url for queue '{}'", dlq_name))
+        })?;
+
+        // the redrive policy wants the dlq's arn, not its url
+        let attributes = client
+            .get_queue_attributes()
+            .queue_url(&dlq_url)
+            .attribute_names(sqs::model::QueueAttributeName::QueueArn)
+            .send()
+            .await
+            .map_err(|e| {
+                RpcError::ProviderInit(format!(
+                    "unable to read attributes of dead-letter queue '{}': {}",
+                    dlq_name, e
+                ))
+            })?;
+        let dlq_arn = attributes
+            .attributes()
+            .and_then(|attrs| attrs.get(&sqs::model::QueueAttributeName::QueueArn))
+            .ok_or_else(|| {
+                RpcError::ProviderInit(format!(
+                    "dead-letter queue '{}' has no QueueArn attribute",
+                    dlq_name
+                ))
+            })?;
+
+        client
+            .set_queue_attributes()
+            .queue_url(queue_url)
+            .attributes(
+                sqs::model::QueueAttributeName::RedrivePolicy,
+                redrive_policy(dlq_arn, config.max_receive_count),
+            )
+            .send()
+            .await
+            .map_err(|e| {
+                RpcError::ProviderInit(format!("unable to set redrive policy: {}", e))
+            })?;
+        debug!(dead_letter_queue = %dlq_name, "redrive policy attached");
+        Ok(())
+    }
+}
+
+/// Forward a single received message to the linked actor. Returns whether the
+/// actor handled it, so the receive loop can acknowledge handled messages and
+/// leave failed ones on the queue for redelivery. Runs inside the per-message
+/// span from [`message_span`], which carries the ids a trace needs.
+async fn dispatch_message(
+    link_def: &LinkDefinition,
+    config: &SQSConfig,
+    queue_name: &str,
+    message: &sqs::model::Message,
+    s3_client: Option<&s3::Client>,
+) -> bool {
+    let body = match fetch_offloaded_body(s3_client, message).await {
+        Ok(Some(body)) => body,
+        Ok(None) => match decode_body(message) {
+            Ok(body) => body,
+            Err(e) => {
+                error!(error = %e, "discarding message with undecodable body");
+                return false;
+            }
+        },
+        // the pointer object may reappear (eventual consistency, restored
+        // bucket); leave the message for redelivery instead of dropping it
+        Err(e) => {
+            error!(error = %e, "unable to inline offloaded payload; leaving the message on the queue");
+            return false;
+        }
+    };
+    let now_epoch_ms = std::time::SystemTime::now()
+        .duration_since(std::time::SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64;
+    if let Some(latency_ms) = queue_latency_ms(message, now_epoch_ms) {
+        debug!(latency_ms, "message dequeued");
+    }
+    let mut attributes = collect_attributes(message);
+    collect_system_attributes(message, &mut attributes);
+    collect_source_metadata(queue_name, message, &mut attributes);
+    if config.xray_tracing {
+        collect_xray_trace_header(message, &mut attributes);
+    }
+    let body = if config.unwrap_sns_envelope {
+        match unwrap_sns_notification(&body) {
+            Some((inner, sns_attributes)) => {
+                attributes.extend(sns_attributes);
+                inner
+            }
+            None => {
+                warn!("unwrap_sns_envelope is set but the body is not an sns notification; passing it through");
+                body
+            }
+        }
+    } else {
+        body
+    };
+    if config.propagate_trace_context {
+        attach_trace_context(&attributes);
+        // the context now parents the dispatch span; don't echo the raw
+        // headers into the actor's envelope
+        attributes.remove(TRACEPARENT_ATTRIBUTE);
+        attributes.remove(TRACESTATE_ATTRIBUTE);
+    }
+    if config.log_message_bodies {
+        let preview = body_preview(
+            &body,
+            config.log_body_preview_bytes,
+            redact_pattern(config).as_ref(),
+        );
+        debug!(body = %preview, "dispatching message body");
+    }
+    let sub_msg = if config.envelope_format == EnvelopeFormat::Json {
+        match from_json_envelope(&body) {
+            Some(sub_msg) => sub_msg,
+            None => {
+                // a foreign producer on a json-mode queue; dispatch natively
+                // rather than dropping the message
+                warn!("envelope_format is json but the body is not a json envelope; dispatching natively");
+                native_sub_message(queue_name, body, attributes, message)
+            }
+        }
+    } else {
+        native_sub_message(queue_name, body, attributes, message)
+    };
+    let actor = MessageSubscriberSender::for_actor(link_def);
+    let dispatched = match bounded_dispatch(
+        config.dispatch_timeout_ms,
+        actor.handle_message(&dispatch_context(link_def), &sub_msg),
+    )
+    .await
+    {
+        Some(dispatched) => dispatched,
+        None => {
+            // the handler is abandoned, not cancelled; the visibility
+            // timeout (validated longer at link time) keeps the message
+            // hidden until any straggling work has run its course
+            error!(
+                timeout_ms = config.dispatch_timeout_ms,
+                "actor did not answer within dispatch_timeout_ms; leaving the message on the queue"
+            );
+            return false;
+        }
+    };
+    if let Err(e) = dispatched {
+        let error = SqsProviderError::DispatchFailed(e.to_string());
+        error!(%error, "leaving the message on the queue");
+        return false;
+    }
+    true
+}
+
+/// The native-format message an actor receives: the subject names the source
+/// queue and the envelope body carries the payload with its attributes.
+fn native_sub_message(
+    queue_name: &str,
+    body: Vec<u8>,
+    attributes: HashMap<String, String>,
+    message: &sqs::model::Message,
+) -> SubMessage {
+    SubMessage {
+        body: wrap_attributes(body, attributes, collect_typed_attributes(message)),
+        reply_to: None,
+        subject: queue_name.to_string(),
+    }
+}
+
+/// Await a dispatch, bounded by the link's dispatch timeout when one is
+/// configured. None means the handler was still running at the deadline.
+async fn bounded_dispatch<F: std::future::Future>(timeout_ms: u64, dispatch: F) -> Option<F::Output> {
+    if timeout_ms == 0 {
+        return Some(dispatch.await);
+    }
+    tokio::time::timeout(Duration::from_millis(timeout_ms), dispatch)
+        .await
+        .ok()
+}
+
+/// True exactly once per idle stretch: on the poll where the consecutive
+/// empty-poll count first reaches the link's threshold. Activity resets the
+/// count, re-arming the notification.
+fn idle_event_due(empty_polls: u64, threshold: u64) -> bool {
+    threshold > 0 && empty_polls == threshold
+}
+
+/// The synthetic message an actor receives when its queue goes idle
+fn idle_notification(queue_name: &str, empty_polls: u64) -> SubMessage {
+    SubMessage {
+        body: serde_json::json!({ "queue": queue_name, "empty_polls": empty_polls })
+            .to_string()
+            .into_bytes(),
+        reply_to: None,
+        subject: EVENT_QUEUE_IDLE_SUBJECT.to_string(),
+    }
+}
+
+/// The replay limit carried in a [`CONTROL_DLQ_REPLAY_SUBJECT`] request
+/// body: empty means the default, anything else must parse as a positive
+/// integer so a garbled request can't drain an entire dlq by accident.
+fn parse_replay_limit(body: &[u8]) -> RpcResult<u64> {
+    let text = String::from_utf8_lossy(body);
+    let text = text.trim();
+    if text.is_empty() {
+        return Ok(DEFAULT_DLQ_REPLAY_LIMIT);
+    }
+    match text.parse::<u64>() {
+        Ok(limit) if limit > 0 => Ok(limit),
+        _ => Err(RpcError::InvalidParameter(format!(
+            "dlq replay limit must be a positive integer, found \"{}\"",
+            text
+        ))),
+    }
+}
+
+/// The pointer body an offloaded publish sends in place of its payload
+fn s3_pointer_body(bucket: &str, key: &str) -> String {
+    serde_json::json!({ "s3_bucket_name": bucket, "s3_key": key }).to_string()
+}
+
+/// Parse a pointer body back into its bucket and key; None when the body is
+/// not a pointer (a plain message that happens to carry the marker attribute)
+fn parse_s3_pointer(body: &str) -> Option<(String, String)> {
+    let pointer: serde_json::Value = serde_json::from_str(body).ok()?;
+    let bucket = pointer.get("s3_bucket_name")?.as_str()?.to_string();
+    let key = pointer.get("s3_key")?.as_str()?.to_string();
+    Some((bucket, key))
+}
+
+/// An object key for one offloaded payload, unique per process: the queue
+/// name scopes cleanup tooling, the timestamp and counter avoid collisions
+fn large_payload_key(queue_name: &str) -> String {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos();
+    format!(
+        "{}/{:x}-{:x}",
+        queue_name,
+        nanos,
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    )
+}
+
+/// True when a message's body is an s3 pointer rather than its payload
+fn is_offloaded(message: &sqs::model::Message) -> bool {
+    message
+        .message_attributes()
+        .map(|attrs| attrs.contains_key(S3_POINTER_ATTRIBUTE))
+        .unwrap_or(false)
+}
+
+/// Inline an offloaded body: fetch the object the pointer names and hand its
+/// bytes back as the payload. Ok(None) means the message was not offloaded.
+async fn fetch_offloaded_body(
+    s3_client: Option<&s3::Client>,
+    message: &sqs::model::Message,
+) -> RpcResult<Option<Vec<u8>>> {
+    if !is_offloaded(message) {
+        return Ok(None);
+    }
+    let (bucket, key) = parse_s3_pointer(message.body().unwrap_or_default()).ok_or_else(|| {
+        RpcError::InvalidParameter(
+            "message is marked as offloaded but its body is not an s3 pointer".to_string(),
+        )
+    })?;
+    let s3_client = s3_client.ok_or_else(|| {
+        RpcError::InvalidParameter(
+            "received an offloaded message but the link has no large_payload_bucket".to_string(),
+        )
+    })?;
+    let object = s3_client
+        .get_object()
+        .bucket(&bucket)
+        .key(&key)
+        .send()
+        .await
+        .map_err(|e| {
+            SqsProviderError::ReceiveFailed(format!("s3 get_object failed: {}", sdk_error_string(&e)))
+        })?;
+    let bytes = object.body.collect().await.map_err(|e| {
+        SqsProviderError::ReceiveFailed(format!("reading offloaded payload: {}", e))
+    })?;
+    Ok(Some(bytes.into_bytes().to_vec()))
+}
+
+/// How many bytes a publish's typed attributes count against the sqs size
+/// cap: each name, declared type and value (string or binary)
+fn typed_attributes_bytes(typed: &[(String, sqs::model::MessageAttributeValue)]) -> usize {
+    typed
+        .iter()
+        .map(|(name, value)| {
+            name.len()
+                + value.data_type().unwrap_or_default().len()
+                + value.string_value().map(str::len).unwrap_or(0)
+                + value.binary_value().map(|b| b.as_ref().len()).unwrap_or(0)
+        })
+        .sum()
+}
+
+/// Reject a message sqs would bounce for size before it is sent, naming the
+/// actual size instead of the sdk's cryptic service error. The attribute
+/// overhead counts each name, value and the "String" data type, matching how
+/// sqs bills attributes against the cap.
+fn check_message_size(body_len: usize, attributes: &HashMap<String, String>) -> RpcResult<()> {
+    let attribute_bytes: usize = attributes
+        .iter()
+        .map(|(name, value)| name.len() + value.len() + "String".len())
+        .sum();
+    let total = body_len + attribute_bytes;
+    if total > SQS_MAX_MESSAGE_BYTES {
+        return Err(RpcError::InvalidParameter(format!(
+            "message is {} bytes ({} body + {} attribute overhead), over the {} byte sqs limit",
+            total, body_len, attribute_bytes, SQS_MAX_MESSAGE_BYTES
+        )));
+    }
+    Ok(())
+}
+
+/// Remove one acknowledged message's pointer object; a failure only leaks
+/// the object (the message itself is already gone), so it is logged, not
+/// propagated
+async fn delete_offloaded_object(s3_client: &s3::Client, bucket: &str, key: &str) {
+    if let Err(e) = s3_client
+        .delete_object()
+        .bucket(bucket)
+        .key(key)
+        .send()
+        .await
+    {
+        warn!(
+            %bucket,
+            %key,
+            error = %sdk_error_string(&e),
+            "failed to delete offloaded payload object"
+        );
+    }
+}
+
+/// Map a set of receipt handles onto delete batch entries; ids only have to
+/// be unique within the call
+fn delete_batch_entries(receipts: Vec<String>) -> Vec<sqs::model::DeleteMessageBatchRequestEntry> {
+    receipts
+        .into_iter()
+        .enumerate()
+        .map(|(id, receipt_handle)| {
+            sqs::model::DeleteMessageBatchRequestEntry::builder()
+                .id(id.to_string())
+                .receipt_handle(receipt_handle)
+                .build()
+        })
+        .collect()
+}
+
+/// How a visibility heartbeat paces itself: a beat at half the base timeout
+/// keeps the lease comfortably ahead of expiry, and the beat count caps the
+/// total extension so a wedged handler can't hide a message forever.
+fn heartbeat_schedule(base_timeout_seconds: i32, max_extension_seconds: u64) -> (Duration, u32) {
+    let base = base_timeout_seconds.max(1) as u64;
+    let interval = Duration::from_secs((base / 2).max(1));
+    let max_beats = (max_extension_seconds / base).max(1) as u32;
+    (interval, max_beats)
+}
+
+/// Drive a heartbeat: run `beat` every `interval` until the cap is reached.
+/// The caller aborts the task the moment its handler finishes, so reaching
+/// the cap only happens for handlers that outrun max_visibility_extension.
+async fn run_heartbeat<F, Fut>(interval: Duration, max_beats: u32, beat: F)
+where
+    F: Fn() -> Fut,
+    Fut: std::future::Future<Output = ()>,
+{
+    for _ in 0..max_beats {
+        tokio::time::sleep(interval).await;
+        beat().await;
+    }
+}
+
+/// Spawn the visibility heartbeat for one in-flight message, re-extending
+/// its lease by the base timeout on every beat
+fn spawn_heartbeat(
+    client: sqs::Client,
+    queue_url: String,
+    receipt: String,
+    config: &SQSConfig,
+) -> JoinHandle<()> {
+    let base = config.heartbeat_base_seconds();
+    let (interval, max_beats) =
+        heartbeat_schedule(base, config.max_visibility_extension_seconds);
+    tokio::spawn(async move {
+        run_heartbeat(interval, max_beats, || {
+            let client = client.clone();
+            let queue_url = queue_url.clone();
+            let receipt = receipt.clone();
+            async move {
+                if let Err(e) = client
+                    .change_message_visibility()
+                    .queue_url(&queue_url)
+                    .receipt_handle(&receipt)
+                    .visibility_timeout(base)
+                    .send()
+                    .await
+                {
+                    warn!(
+                        %queue_url,
+                        error = %sdk_error_string(&e),
+                        "visibility heartbeat failed; the message may be redelivered"
+                    );
+                }
+            }
+        })
+        .await;
+    })
+}
+
+/// Zero the visibility timeout of messages whose dispatch failed so sqs
+/// redelivers them on the next poll instead of after the full timeout.
+/// Failures here are logged only: the message still comes back eventually.
+async fn fast_retry_batch(client: &sqs::Client, queue_url: &str, receipts: Vec<String>) {
+    let mut change = client.change_message_visibility_batch().queue_url(queue_url);
+    for (n, receipt) in receipts.into_iter().enumerate() {
+        change = change.entries(
+            sqs::model::ChangeMessageVisibilityBatchRequestEntry::builder()
+                .id(format!("msg-{}", n))
+                .receipt_handle(receipt)
+                .visibility_timeout(0)
+                .build(),
+        );
+    }
+    match change.send().await {
+        Ok(changed) => {
+            for failed in changed.failed().unwrap_or_default() {
+                warn!(
+                    %queue_url,
+                    entry_id = ?failed.id(),
+                    code = ?failed.code(),
+                    "failed to fast-retry message; it redelivers after the visibility timeout"
+                );
+            }
+        }
+        Err(e) => {
+            warn!(error = %e, %queue_url, "sqs change_message_visibility_batch failed")
+        }
+    }
+}
+
+/// Acknowledge a batch of handled messages with a single delete_message_batch
+/// call. Entries that fail to delete are logged and left for redelivery; the
+/// receive batch is capped at 10 messages so the delete batch always fits.
+async fn delete_batch(
+    client: &sqs::Client,
+    queue_url: &str,
+    receipts: Vec<String>,
+    metrics: &Metrics,
+) {
+    let total = receipts.len() as u64;
+    let mut delete = client.delete_message_batch().queue_url(queue_url);
+    for entry in delete_batch_entries(receipts) {
+        delete = delete.entries(entry);
+    }
+    let retry = delete.clone();
+    match delete.send().await {
+        Ok(deleted) => {
+            let failed = deleted.failed().unwrap_or_default();
+            Metrics::add(&metrics.delete_err, failed.len() as u64);
+            Metrics::add(&metrics.delete_ok, total - failed.len() as u64);
+            for failed in failed {
+                warn!(
+                    %queue_url,
+                    entry_id = ?failed.id(),
+                    code = ?failed.code(),
+                    "failed to delete handled message; it may be redelivered"
+                );
+            }
+        }
+        Err(e) => {
+            let error_text = sdk_error_string(&e);
+            // a transient failure gets one immediate retry before the
+            // messages are left to redeliver; fatal and config errors don't
+            if classify_sdk_error(&error_text) == SdkErrorClass::Retryable {
+                if let Ok(retried) = retry.send().await {
+                    let failed = retried.failed().unwrap_or_default();
+                    Metrics::add(&metrics.delete_err, failed.len() as u64);
+                    Metrics::add(&metrics.delete_ok, total - failed.len() as u64);
+                    return;
+                }
+            }
+            Metrics::add(&metrics.delete_err, total);
+            warn!(error = %error_text, %queue_url, "sqs delete_message_batch failed; messages may be redelivered")
+        }
+    }
+}
+
+/// Handle provider control commands
+/// put_link (new actor link command), del_link (remove link command), and shutdown
+#[async_trait]
+impl ProviderHandler for SqsMessagingProvider {
+    /// Provider should perform any operations needed for a new link,
+    /// including setting up per-actor resources, and checking authorization.
+    /// If the link is allowed, return true, otherwise return false to deny the link.
+    #[instrument(level = "debug", skip(self, ld), fields(actor_id = %ld.actor_id))]
+    async fn put_link(&self, ld: &LinkDefinition) -> RpcResult<bool> {
+        // a malformed link is denied, not errored: retrying the same values
+        // can never succeed, so let the host surface a failed link instead
+        let config = match SQSConfig::from_link(ld) {
+            Ok(config) => config,
+            Err(e) => {
+                error!(error = %e, "denying link: invalid configuration");
+                return Ok(false);
+            }
+        };
+        debug!(queue_name = %config.queue_name, "linking actor to sqs");
+
+        let (client, client_key) = match self.checkout_client(&config).await {
+            Ok(checked_out) => checked_out,
+            Err(e) => {
+                error!(error = %e, "denying link: unable to build sqs client");
+                return Ok(false);
+            }
+        };
+        match self.start_link(ld, config, client, client_key.clone()).await {
+            Ok(true) => Ok(true),
+            // on a denied or failed link the client reference is handed back,
+            // so an actor stuck in a relink loop can't pin a dead client
+            other => {
+                self.release_client(&client_key).await;
+                other
+            }
+        }
+    }
+
+    /// Handle notification that a link is dropped: stop the receive loop and
+    /// release this link's reference to the shared client.
+    #[instrument(level = "info", skip(self))]
+    async fn delete_link(&self, actor_id: &str) {
+        let mut aw = self.actors.write().await;
+        if let Some(mut bundle) = aw.remove(actor_id) {
+            // let the receive loop finish the poll it is in, then exit
+            bundle.cancel.cancel();
+            drop(aw);
+            if bundle.config.drain_on_delete_link {
+                // wait for messages already received to finish dispatching,
+                // bounded the same way shutdown is
+                let drain = Duration::from_millis(bundle.config.shutdown_drain_timeout_ms);
+                for poll_handle in std::mem::take(&mut bundle.poll_handles) {
+                    if let Ok(mut handle) = Arc::try_unwrap(poll_handle) {
+                        if tokio::time::timeout(drain, &mut handle).await.is_err() {
+                            warn!(
+                                %actor_id,
+                                "receive loop did not drain within the timeout; cancelling it"
+                            );
+                            handle.abort();
+                        }
+                    }
+                }
+            }
+            self.release_client(&bundle.client_key).await;
+        }
+        debug!("finished processing delete link for actor [{}]", actor_id);
+    }
+
+    /// Handle shutdown request by stopping all receive loops and dropping all
+    /// clients. Each loop is given a bounded window to finish its current poll.
+    /// Report connectivity per linked actor, probing each actor's queue and
+    /// caching the verdict briefly so frequent host polls stay cheap
+    async fn health_request(&self, _arg: &HealthCheckRequest) -> RpcResult<HealthCheckResponse> {
+        if let Some((checked_at, response)) = self.health.read().await.as_ref() {
+            if checked_at.elapsed() < HEALTH_CACHE_TTL {
+                return Ok(response.clone());
+            }
+        }
+        let response = self.probe_actor_queues().await;
+        if !response.healthy {
+            warn!(message = ?response.message, "provider health check degraded");
+        }
+        *self.health.write().await = Some((std::time::Instant::now(), response.clone()));
+        Ok(response)
+    }
+
+    async fn shutdown(&self) -> Result<(), Infallible> {
+        let mut aw = self.actors.write().await;
+        for (actor_id, bundle) in aw.drain() {
+            bundle.cancel.cancel();
+            let drain = Duration::from_millis(bundle.config.shutdown_drain_timeout_ms);
+            // bundles handed out to publish/request are short-lived, so the
+            // map's references are normally the last ones standing
+            let mut force_cancelled = 0u32;
+            for poll_handle in bundle.poll_handles {
+                if let Ok(mut handle) = Arc::try_unwrap(poll_handle) {
+                    if tokio::time::timeout(drain, &mut handle).await.is_err() {
+                        handle.abort();
+                        force_cancelled += 1;
+                    }
+                }
+            }
+            if let Some(flush_handle) = bundle.flush_handle {
+                if let Ok(handle) = Arc::try_unwrap(flush_handle) {
+                    if tokio::time::timeout(drain, handle).await.is_err() {
+                        warn!(%actor_id, "publish flusher did not stop within the drain timeout");
+                    }
+                }
+            }
+            if force_cancelled > 0 {
+                warn!(
+                    %actor_id,
+                    force_cancelled,
+                    "receive loops still in flight at the drain timeout were force-cancelled"
+                );
+            }
+        }
+        // every link is gone, so every shared client reference is too
+        self.clients.write().await.clear();
+        Ok(())
+    }
+}
+
+/// Handle Messaging methods that interact with sqs
+#[async_trait]
+impl Messaging for SqsMessagingProvider {
+    #[instrument(
+        level = "debug",
+        skip(self, ctx, msg),
+        fields(
+            actor_id = ?ctx.actor,
+            subject = %msg.subject,
+            queue_url = tracing::field::Empty,
+            message_id = tracing::field::Empty,
+            sequence_number = tracing::field::Empty,
+        )
+    )]
+    async fn publish(&self, ctx: &Context, msg: &PubMessage) -> RpcResult<()> {
+        debug!("publishing message to sqs");
+        let mut bundle = self.bundle_for_actor(ctx).await?;
+        // least privilege enforced at the provider boundary: a denied
+        // subject never reaches aws (nor the control handlers)
+        if !publish_authorized(
+            &msg.subject,
+            &bundle.config.publish_allow,
+            &bundle.config.publish_deny,
+        ) {
+            return Err(SqsProviderError::PublishDenied(format!(
+                "subject \"{}\" is outside this link's publish acl",
+                msg.subject
+            ))
+            .into());
+        }
+        if msg.subject == CONTROL_PURGE_SUBJECT {
+            return bundle.purge_queue().await;
+        }
+        if msg.subject == CONTROL_PAUSE_SUBJECT {
+            bundle.set_paused(true);
+            return Ok(());
+        }
+        if msg.subject == CONTROL_RESUME_SUBJECT {
+            bundle.set_paused(false);
+            return Ok(());
+        }
+        if let Some(rate_limiter) = &bundle.rate_limiter {
+            rate_limiter
+                .take(
+                    bundle.config.rate_limit_behavior,
+                    Duration::from_millis(bundle.config.rate_limit_wait_ms),
+                )
+                .await?;
+        }
+        if let Some(sns_client) = &bundle.sns_client {
+            if is_sns_topic_arn(&msg.subject) {
+                return bundle.publish_sns(sns_client, msg).await;
+            }
+        }
+        let queue_url = bundle.resolve_queue_url(&msg.subject).await?;
+        // the span carries the destination so every later event correlates
+        // with cloudwatch; the body stays out of it deliberately
+        tracing::Span::current().record("queue_url", tracing::field::display(&queue_url));
+
+        let (payload, mut attributes, typed_attributes) =
+            if bundle.config.envelope_format == EnvelopeFormat::Json {
+                // the whole message becomes the body; nothing rides on
+                // message attributes beyond the provider's own markers
+                (to_json_envelope(msg).into_bytes(), HashMap::new(), Vec::new())
+            } else {
+                unwrap_envelope(&msg.body)
+            };
+        // convert now so a bad typed attribute fails this call rather than
+        // the detached flusher
+        let typed_attributes = typed_attributes
+            .iter()
+            .map(|attr| typed_attribute_value(attr).map(|value| (attr.name.clone(), value)))
+            .collect::<RpcResult<Vec<_>>>()?;
+        if bundle.config.propagate_trace_context {
+            inject_trace_context(&mut attributes);
+        }
+        // a link-level ttl stamps every publish; an actor that set its own
+        // expires-at keeps it
+        if bundle.config.message_ttl_seconds > 0 {
+            attributes
+                .entry(EXPIRES_AT_ATTRIBUTE.to_string())
+                .or_insert_with(|| {
+                    (epoch_seconds() + bundle.config.message_ttl_seconds).to_string()
+                });
+        }
+        let fifo_queue = is_fifo(&queue_url);
+        let fifo = if fifo_queue {
+            Some(fifo_ids(
+                &mut attributes,
+                bundle.config.content_based_deduplication,
+                derive_group_id(&bundle.config, &msg.subject, &payload),
+                bundle
+                    .config
+                    .dedup_from_body_hash
+                    .then(|| body_hash_dedup_id(&payload)),
+            )?)
+        } else {
+            None
+        };
+        reject_initial_visibility(&attributes)?;
+        let delay_seconds = delay_from_attributes(&mut attributes, fifo_queue)?;
+        let payload = match &bundle.config.large_payload_bucket {
+            Some(bucket) if payload.len() as u64 > bundle.config.large_payload_threshold => {
+                bundle
+                    .offload_payload(bucket, payload, &mut attributes)
+                    .await?
+            }
+            _ => payload,
+        };
+        if bundle.config.log_message_bodies {
+            let preview = body_preview(
+                &payload,
+                bundle.config.log_body_preview_bytes,
+                redact_pattern(&bundle.config).as_ref(),
+            );
+            debug!(body = %preview, "publishing message body");
+        }
+        let trace_header = bundle
+            .config
+            .xray_tracing
+            .then(|| xray_trace_header(&mut attributes))
+            .flatten();
+        let (body, encoding) = encode_body(&payload, bundle.config.body_encoding)?;
+        check_message_size(
+            body.len() + typed_attributes_bytes(&typed_attributes),
+            &attributes,
+        )?;
+        if let Some(batch_tx) = &bundle.batch_tx {
+            let pending = PendingMessage {
+                body,
+                encoding,
+                attributes,
+                typed_attributes,
+                fifo,
+                delay_seconds,
+                trace_header,
+            };
+            // counted when accepted into the buffer: the flusher's outcome is
+            // no longer attributable to this call
+            return match batch_tx.send((queue_url, pending)).await {
+                Ok(()) => {
+                    Metrics::incr(&bundle.metrics.published);
+                    debug!("message accepted into publish batch buffer");
+                    Ok(())
+                }
+                Err(_) => {
+                    Metrics::incr(&bundle.metrics.publish_err);
+                    Err(RpcError::Other(
+                        "publish buffer is no longer accepting messages".to_string(),
+                    ))
+                }
+            };
+        }
+        let mut queue_url = queue_url;
+        // while the primary's circuit is open, publishes go to the failover
+        // queue; record_* below feed the breaker from primary outcomes only
+        let mut on_failover = false;
+        if let (Some(breaker), Some(failover_url)) = (&bundle.failover, &bundle.failover_queue_url)
+        {
+            if breaker.route_to_failover() {
+                debug!(%failover_url, "primary circuit open; publishing to the failover queue");
+                on_failover = true;
+                queue_url = failover_url.clone();
+            }
+        }
+        let mut retried = false;
+        let mut retried_auth = false;
+        let sent = loop {
+            let send_client = if on_failover {
+                bundle.failover_client.as_ref().unwrap_or(&bundle.client)
+            } else {
+                &bundle.client
+            };
+            let mut send = send_client.send_message()
+                .queue_url(&queue_url)
+                .message_body(&body)
+                .message_attributes(ENCODING_ATTRIBUTE, string_attribute(encoding));
+            if encoding == ENCODING_BASE64 {
+                send = send.message_attributes(
+                    CONTENT_TRANSFER_ENCODING_ATTRIBUTE,
+                    string_attribute(ENCODING_BASE64),
+                );
+            }
+            for (name, value) in &attributes {
+                send = send.message_attributes(name, string_attribute(value.clone()));
+            }
+            for (name, value) in &typed_attributes {
+                send = send.message_attributes(name, value.clone());
+            }
+            if let Some((group_id, dedup_id)) = &fifo {
+                send = send.message_group_id(group_id);
+                if let Some(dedup_id) = dedup_id {
+                    send = send.message_deduplication_id(dedup_id);
+                }
+            }
+            if let Some(delay_seconds) = delay_seconds {
+                send = send.delay_seconds(delay_seconds);
+            }
+            if let Some(header) = &trace_header {
+                send = send.message_system_attributes(
+                    sqs::model::MessageSystemAttributeNameForSends::AwsTraceHeader,
+                    xray_system_attribute(header.clone()),
+                );
+            }
+            match send.send().await {
+                Ok(sent) => break sent,
+                Err(e) if !retried
+                    && classify_sdk_error(&sdk_error_string(&e)) == SdkErrorClass::Fatal
+                    && is_queue_missing(&sdk_error_string(&e)) =>
+                {
+                    // the queue may have been recreated under a fresh url;
+                    // drop the cached one and try once against a re-resolve
+                    retried = true;
+                    warn!(%queue_url, "queue gone; re-resolving its url and retrying once");
+                    bundle.invalidate_queue_url(&msg.subject).await;
+                    queue_url = bundle.resolve_queue_url(&msg.subject).await?;
+                }
+                Err(e) if !retried_auth
+                    && classify_sdk_error(&sdk_error_string(&e)) == SdkErrorClass::Config
+                    && is_credential_expired(&sdk_error_string(&e)) =>
+                {
+                    retried_auth = true;
+                    warn!("credentials expired; rebuilding the aws client and retrying once");
+                    bundle.client = self
+                        .refresh_client(&bundle.config, &bundle.client_key)
+                        .await?;
+                }
+                Err(e) => {
+                    Metrics::incr(&bundle.metrics.publish_err);
+                    if !on_failover {
+                        if let Some(breaker) = &bundle.failover {
+                            if breaker.record_failure() {
+                                warn!(
+                                    failures = bundle.config.failover_threshold,
+                                    "primary queue keeps failing; failing publishes over"
+                                );
+                            }
+                        }
+                    }
+                    return Err(SqsProviderError::SendFailed(format!(
+                        "sqs send_message failed: {}",
+                        sdk_error_string(&e)
+                    ))
+                    .into());
+                }
+            }
+        };
+        if !on_failover {
+            if let Some(breaker) = &bundle.failover {
+                breaker.record_success();
+            }
+        }
+        Metrics::incr(&bundle.metrics.published);
+        if let Some(message_id) = sent.message_id() {
+            tracing::Span::current().record("message_id", tracing::field::display(message_id));
+        }
+        if let Some(sequence_number) = sent.sequence_number() {
+            tracing::Span::current()
+                .record("sequence_number", tracing::field::display(sequence_number));
+        }
+        bundle
+            .record_publish(&queue_url, sent.message_id(), sent.sequence_number())
+            .await;
+        debug!("published message to sqs");
+
+        Ok(())
+    }
+
+    #[instrument(
+        level = "debug",
+        skip(self, ctx, msg),
+        fields(
+            actor_id = ?ctx.actor,
+            subject = %msg.subject,
+            message_id = tracing::field::Empty,
+            receipt_handle = tracing::field::Empty,
+        )
+    )]
+    async fn request(&self, ctx: &Context, msg: &RequestMessage) -> RpcResult<ReplyMessage> {
+        debug!("requesting message from sqs");
+        if msg.subject == CONTROL_DEPTH_SUBJECT {
+            return self.bundle_for_actor(ctx).await?.queue_depth().await;
+        }
+        if msg.subject == CONTROL_LAST_PUBLISH_SUBJECT {
+            return self.bundle_for_actor(ctx).await?.last_publish_reply().await;
+        }
+        if msg.subject == CONTROL_DLQ_REPLAY_SUBJECT {
+            let limit = parse_replay_limit(&msg.body)?;
+            return self.bundle_for_actor(ctx).await?.replay_dlq(limit).await;
+        }
+        let SqsClientBundle {
+            client,
+            config,
+            metrics,
+            subscribe_queues,
+            client_key,
+            s3_client,
+            ..
+        } = self.bundle_for_actor(ctx).await?;
+        // a request consumes from the link's first subscribe-role queue
+        let (_, queue_url) = subscribe_queues.first().ok_or_else(|| {
+            RpcError::InvalidParameter("link has no subscribe-role queue configured".to_string())
+        })?;
+
+        let wait_time_seconds =
+            request_wait_seconds(msg.timeout_ms as u64, config.wait_time_seconds);
+        let mut client = client;
+        let mut retried_auth = false;
+        let received = loop {
+            let receive = client
+                .receive_message()
+                .queue_url(queue_url)
+                .wait_time_seconds(wait_time_seconds)
+                // a request consumes exactly one message; pulling more would
+                // leave the extras invisible until their visibility timeout
+                // expires
+                .max_number_of_messages(1)
+                .set_visibility_timeout(config.visibility_timeout_seconds)
+                .message_attribute_names("All")
+                .attribute_names(sqs::model::QueueAttributeName::All);
+            match receive.send().await {
+                Ok(received) => break received,
+                Err(e) if !retried_auth && is_credential_expired(&sdk_error_string(&e)) => {
+                    retried_auth = true;
+                    warn!("credentials expired; rebuilding the aws client and retrying once");
+                    client = self.refresh_client(&config, &client_key).await?;
+                }
+                Err(e) => return Err(SqsProviderError::ReceiveFailed(sdk_error_string(&e)).into()),
+            }
+        };
+        let messages = received.messages().unwrap_or_default();
+        Metrics::add(&metrics.received, messages.len() as u64);
+        let message = messages.first().ok_or_else(|| {
+            RpcError::Timeout(format!(
+                "no message available on queue '{}' after {}s poll",
+                queue_url, wait_time_seconds
+            ))
+        })?;
+        let span = tracing::Span::current();
+        if let Some(message_id) = message.message_id() {
+            span.record("message_id", tracing::field::display(message_id));
+        }
+        if let Some(receipt_handle) = message.receipt_handle() {
+            span.record("receipt_handle", tracing::field::display(receipt_handle));
+        }
+
+        let offloaded_body = fetch_offloaded_body(s3_client.as_ref(), message).await?;
+        let reply = build_reply(&msg.subject, message, offloaded_body)?;
+
+        // with auto delete, acknowledge the message once the reply is built so
+        // it is not redelivered after the visibility timeout expires
+        if config.message_auto_delete {
+            if let Some(receipt_handle) = message.receipt_handle() {
+                if let Err(e) = client
+                    .delete_message()
+                    .queue_url(queue_url)
+                    .receipt_handle(receipt_handle)
+                    .send()
+                    .await
+                {
+                    warn!(error = %e, "failed to delete received message; it may be redelivered");
+                }
+            }
+        }
+
+        Ok(reply)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use crate::{
+        batch_entry, body_preview, buffer_pending, build_reply, config::BodyEncoding, config::GroupIdStrategy,
+        config::SQSConfig, derive_group_id,
+        collect_attributes,
+        collect_system_attributes, create_queue_attributes, decode_body, delay_from_attributes,
+        delete_batch_entries, dispatch_batch, dispatch_context, exceeded_processing_attempts,
+        depth_from_attributes, new_queue_urls, next_attempt_id, queue_latency_ms,
+        queue_url_from_identifier,
+        receive_count, redrive_policy, unwrap_sns_notification, weighted_batch_size,
+        check_message_size, encode_body, fifo_ids, heartbeat_schedule, is_credential_expired, is_fifo,
+        is_offloaded, is_queue_missing, is_sns_topic_arn, large_payload_key,
+        matches_subscribe_filter, parse_s3_pointer, queue_url_region, s3_pointer_body, CONTROL_LAST_PUBLISH_SUBJECT,
+        request_wait_seconds, run_heartbeat, unwrap_envelope, wrap_attributes,
+        attach_trace_context, batch_span, collect_typed_attributes, collect_xray_trace_header,
+        correlation_id, typed_attribute_value, TypedAttribute,
+        inject_trace_context, message_span, xray_trace_header,
+        body_hash_dedup_id, bounded_dispatch, classify_sdk_error, from_json_envelope,
+        idle_event_due,
+        collect_source_metadata, idle_notification, is_throttling_error, message_expired,
+        native_sub_message, parse_replay_limit,
+        publish_authorized, to_json_envelope,
+        reject_initial_visibility, string_attribute, subject_pattern_matches,
+        validate_link_values, Backoff, FailoverBreaker, PendingMessage,
+        SqsClientBundle, TokenBucket, EVENT_QUEUE_IDLE_SUBJECT, EXPIRES_AT_ATTRIBUTE,
+        INITIAL_VISIBILITY_ATTRIBUTE,
+        AdaptiveConcurrency, SdkErrorClass, SqsMessagingProvider,
+        CONTENT_TRANSFER_ENCODING_ATTRIBUTE,
+        CONTROL_DLQ_REPLAY_SUBJECT, CONTROL_PAUSE_SUBJECT, CONTROL_RESUME_SUBJECT,
+        ENCODING_ATTRIBUTE,
+        ENCODING_BASE64, ENCODING_UTF8,
+    };
+    use wasmbus_rpc::error::RpcError;
+    use std::time::Duration;
+    use aws_sdk_sqs::model::{Message, MessageAttributeValue};
+    use tokio_util::sync::CancellationToken;
+    use wasmbus_rpc::provider::{prelude::Context, ProviderHandler};
+    use wasmcloud_interface_messaging::{Messaging, PubMessage, RequestMessage};
+
+    /// build a bundle without touching the network, for map-handling tests
+    async fn test_bundle(queue_url: &str) -> SqsClientBundle {
+        std::env::set_var("AWS_REGION", "us-east-1");
+        let aws_config = aws_config::from_env().load().await;
+        SqsClientBundle {
+            client: aws_sdk_sqs::Client::new(&aws_config),
+            queue_url: queue_url.to_string(),
+            config: SQSConfig::default(),
+            cancel: CancellationToken::new(),
+            poll_handles: vec![std::sync::Arc::new(tokio::spawn(async {}))],
+            subscribe_queues: vec![(String::from("q"), queue_url.to_string())],
+            resolved_urls: std::sync::Arc::default(),
+            batch_tx: None,
+            flush_handle: None,
+            metrics: std::sync::Arc::default(),
+            client_key: String::new(),
+            sns_client: None,
+            last_publish: std::sync::Arc::default(),
+            s3_client: None,
+            rate_limiter: None,
+            failover: None,
+            failover_client: None,
+            failover_queue_url: None,
+            paused: std::sync::Arc::default(),
+        }
+    }
+
+    /// with drain_on_delete_link, unlinking waits for dispatches already in
+    /// flight; a loop that overruns the timeout is force-cancelled
+    #[tokio::test]
+    async fn test_delete_link_drains_in_flight_dispatches() {
+        let prov = SqsMessagingProvider::default();
+        let dispatched = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let mut bundle = test_bundle("queue-url").await;
+        bundle.config.drain_on_delete_link = true;
+        bundle.config.shutdown_drain_timeout_ms = 5_000;
+        let flag = dispatched.clone();
+        bundle.poll_handles = vec![std::sync::Arc::new(tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            flag.store(true, std::sync::atomic::Ordering::SeqCst);
+        }))];
+        prov.actors
+            .write()
+            .await
+            .insert(String::from("actor-drain"), bundle);
+
+        prov.delete_link("actor-drain").await;
+        assert!(
+            dispatched.load(std::sync::atomic::Ordering::SeqCst),
+            "in-flight dispatch was abandoned instead of drained"
+        );
+
+        // a handler stuck past the timeout is cancelled, not waited forever
+        let stuck = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let mut bundle = test_bundle("queue-url").await;
+        bundle.config.drain_on_delete_link = true;
+        bundle.config.shutdown_drain_timeout_ms = 50;
+        let flag = stuck.clone();
+        bundle.poll_handles = vec![std::sync::Arc::new(tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_secs(3600)).await;
+            flag.store(true, std::sync::atomic::Ordering::SeqCst);
+        }))];
+        prov.actors
+            .write()
+            .await
+            .insert(String::from("actor-stuck"), bundle);
+        prov.delete_link("actor-stuck").await;
+        assert!(!stuck.load(std::sync::atomic::Ordering::SeqCst));
+    }
+
+    /// relinking an already-linked actor swaps the bundle in place: the new
+    /// settings serve immediately, the old loops are cancelled, and the old
+    /// client reference is handed back
+    #[tokio::test]
+    async fn test_relink_replaces_bundle_and_stops_old_loops() {
+        let prov = SqsMessagingProvider::default();
+        let config = SQSConfig {
+            aws_region: Some(String::from("us-east-1")),
+            endpoint_url: Some(String::from("http://127.0.0.1:1")),
+            ..Default::default()
+        };
+        let (_, old_key) = prov.checkout_client(&config).await.unwrap();
+        let mut old_bundle = test_bundle("queue-url-old").await;
+        old_bundle.client_key = old_key.clone();
+        let old_cancel = old_bundle.cancel.clone();
+        prov.replace_bundle("actor-relink", old_bundle).await;
+        // the first link tears nothing down
+        assert!(!old_cancel.is_cancelled());
+        assert_eq!(prov.clients.read().await.len(), 1);
+
+        prov.replace_bundle("actor-relink", test_bundle("queue-url-new").await)
+            .await;
+        assert!(old_cancel.is_cancelled(), "old receive loops keep running");
+        assert!(
+            prov.clients.read().await.is_empty(),
+            "old client reference was not released"
+        );
+        let ctx = Context {
+            actor: Some(String::from("actor-relink")),
+            ..Default::default()
+        };
+        let bundle = prov.bundle_for_actor(&ctx).await.unwrap();
+        assert_eq!(bundle.queue_url, "queue-url-new");
+    }
+
+    /// a publish's service-assigned ids are queryable afterwards on the
+    /// control subject; before any publish the query is a caller error
+    #[tokio::test]
+    async fn test_last_publish_record_queryable() {
+        let prov = SqsMessagingProvider::default();
+        prov.actors
+            .write()
+            .await
+            .insert(String::from("actor-pub"), test_bundle("queue-url").await);
+        let ctx = Context {
+            actor: Some(String::from("actor-pub")),
+            ..Default::default()
+        };
+        let query = RequestMessage {
+            subject: CONTROL_LAST_PUBLISH_SUBJECT.to_string(),
+            body: Vec::new(),
+            timeout_ms: 10,
+        };
+
+        let err = prov.request(&ctx, &query).await.unwrap_err();
+        assert!(matches!(err, RpcError::InvalidParameter(_)));
+
+        prov.actors.read().await["actor-pub"]
+            .record_publish("queue-url", Some("msg-123"), Some("42"))
+            .await;
+        let reply = prov.request(&ctx, &query).await.unwrap();
+        let record: serde_json::Value = serde_json::from_slice(&reply.body).unwrap();
+        assert_eq!(record["destination"], "queue-url");
+        assert_eq!(record["message_id"], "msg-123");
+        assert_eq!(record["sequence_number"], "42");
+    }
+
+    /// three links with identical region/credentials/endpoint share one
+    /// client; each release hands a reference back and the last drops it
+    #[tokio::test]
+    async fn test_client_cache_shared_and_refcounted() {
+        std::env::set_var("AWS_REGION", "us-east-1");
+        let prov = SqsMessagingProvider::default();
+        let config = SQSConfig {
+            aws_region: Some(String::from("us-east-1")),
+            endpoint_url: Some(String::from("http://127.0.0.1:1")),
+            ..Default::default()
+        };
+
+        let mut keys = Vec::new();
+        for _ in 0..3 {
+            let (_client, key) = prov.checkout_client(&config).await.unwrap();
+            keys.push(key);
+        }
+        {
+            let clients = prov.clients.read().await;
+            assert_eq!(clients.len(), 1, "identical configs share one client");
+            assert_eq!(clients.values().next().unwrap().refs, 3);
+        }
+
+        // a different endpoint is a different client
+        let other = SQSConfig {
+            endpoint_url: Some(String::from("http://127.0.0.1:2")),
+            ..config.clone()
+        };
+        let (_client, other_key) = prov.checkout_client(&other).await.unwrap();
+        assert_ne!(other_key, keys[0]);
+        assert_eq!(prov.clients.read().await.len(), 2);
+
+        for key in &keys {
+            prov.release_client(key).await;
+        }
+        prov.release_client(&other_key).await;
+        assert!(prov.clients.read().await.is_empty());
+    }
+
+    /// a task panicking while it holds the actors lock must not poison it:
+    /// the handlers keep working afterwards
+    #[tokio::test]
+    async fn test_actor_map_survives_panicked_holder() {
+        let prov = SqsMessagingProvider::default();
+        let actors = prov.actors.clone();
+        let holder = tokio::spawn(async move {
+            let _guard = actors.write().await;
+            panic!("handler panicked while holding the lock");
+        });
+        assert!(holder.await.is_err());
+
+        // the lock was released by the unwind, not poisoned
+        prov.actors
+            .write()
+            .await
+            .insert(String::from("actor-a"), test_bundle("queue-url-a").await);
+        let ctx = Context {
+            actor: Some(String::from("actor-a")),
+            ..Default::default()
+        };
+        assert_eq!(
+            prov.bundle_for_actor(&ctx).await.unwrap().queue_url,
+            "queue-url-a"
+        );
+    }
+
+    /// under an active otel span, injection writes a traceparent attribute
+    /// that extraction round-trips
+    #[test]
+    fn test_trace_context_round_trip() {
+        use opentelemetry::trace::TracerProvider as _;
+        use tracing_subscriber::layer::SubscriberExt;
+
+        let provider = opentelemetry::sdk::trace::TracerProvider::builder().build();
+        let tracer = provider.tracer("test");
+        let subscriber =
+            tracing_subscriber::registry().with(tracing_opentelemetry::layer().with_tracer(tracer));
+        tracing::subscriber::with_default(subscriber, || {
+            let span = tracing::info_span!("publish-under-test");
+            let _entered = span.enter();
+            let mut attributes = HashMap::new();
+            inject_trace_context(&mut attributes);
+            assert!(attributes.contains_key("traceparent"));
+            // extraction must accept what injection produced
+            attach_trace_context(&attributes);
+        });
+    }
+
+    /// the publish span must carry the destination queue url so events inside
+    /// it correlate with cloudwatch, without ever logging the body
+    #[tracing_test::traced_test]
+    #[tokio::test]
+    async fn test_publish_span_records_queue_url() {
+        let (tx, _rx) = tokio::sync::mpsc::channel(16);
+        let mut bundle = test_bundle("span-queue-url").await;
+        bundle.batch_tx = Some(tx);
+
+        let prov = SqsMessagingProvider::default();
+        prov.actors
+            .write()
+            .await
+            .insert(String::from("actor-span"), bundle);
+        let ctx = Context {
+            actor: Some(String::from("actor-span")),
+            ..Default::default()
+        };
+        let msg = PubMessage {
+            subject: String::new(),
+            reply_to: None,
+            body: b"secret-body".to_vec(),
+        };
+        prov.publish(&ctx, &msg).await.unwrap();
+        assert!(logs_contain("span-queue-url"));
+        assert!(!logs_contain("secret-body"));
+    }
+
+    /// every accepted publish bumps the per-actor published counter
+    #[tokio::test]
+    async fn test_publish_counter() {
+        let (tx, mut rx) = tokio::sync::mpsc::channel(16);
+        let mut bundle = test_bundle("q").await;
+        bundle.batch_tx = Some(tx);
+        let metrics = bundle.metrics.clone();
+
+        let prov = SqsMessagingProvider::default();
+        prov.actors
+            .write()
+            .await
+            .insert(String::from("actor-count"), bundle);
+        let ctx = Context {
+            actor: Some(String::from("actor-count")),
+            ..Default::default()
+        };
+        for i in 0..3 {
+            let msg = PubMessage {
+                subject: String::new(),
+                reply_to: None,
+                body: format!("m{}", i).into_bytes(),
+            };
+            prov.publish(&ctx, &msg).await.unwrap();
+        }
+        assert_eq!(
+            metrics
+                .published
+                .load(std::sync::atomic::Ordering::Relaxed),
+            3
+        );
+        assert_eq!(rx.recv().await.unwrap().1.body, "m0");
+    }
+
+    fn pending(body: &str) -> PendingMessage {
+        PendingMessage {
+            body: body.to_string(),
+            encoding: ENCODING_UTF8,
+            attributes: HashMap::new(),
+            typed_attributes: Vec::new(),
+            fifo: None,
+            delay_seconds: None,
+            trace_header: None,
+        }
+    }
+
+    /// 25 buffered publishes to one queue must become exactly 3 batch calls:
+    /// two full batches of 10 plus the remainder picked up by the timer flush
+    #[test]
+    fn test_batch_coalescing_flush_count() {
+        let mut buffers = HashMap::new();
+        let mut flushes = 0;
+        for i in 0..25 {
+            if let Some(full) = buffer_pending(&mut buffers, "q", pending(&format!("m{}", i))) {
+                assert_eq!(full.len(), 10);
+                flushes += 1;
+            }
+        }
+        // the timer flush drains whatever is left
+        for (_, remainder) in buffers.drain() {
+            if !remainder.is_empty() {
+                assert_eq!(remainder.len(), 5);
+                flushes += 1;
+            }
+        }
+        assert_eq!(flushes, 3);
+    }
+
+    /// a failed send must surface as an Err to the actor, not be logged and
+    /// reported as success
+    #[tokio::test]
+    async fn test_publish_send_failure_returns_err() {
+        std::env::set_var("AWS_REGION", "us-east-1");
+        std::env::set_var("AWS_ACCESS_KEY_ID", "test");
+        std::env::set_var("AWS_SECRET_ACCESS_KEY", "test");
+        let aws_config = aws_config::from_env().load().await;
+        // point the client at a port nothing listens on so the send fails fast
+        let conf = aws_sdk_sqs::config::Builder::from(&aws_config)
+            .endpoint_resolver(aws_sdk_sqs::Endpoint::immutable(
+                "http://127.0.0.1:1".parse().unwrap(),
+            ))
+            .build();
+        let mut bundle = test_bundle("http://127.0.0.1:1/000000000000/missing").await;
+        bundle.client = aws_sdk_sqs::Client::from_conf(conf);
+
+        let prov = SqsMessagingProvider::default();
+        prov.actors
+            .write()
+            .await
+            .insert(String::from("actor-pub"), bundle);
+        let ctx = Context {
+            actor: Some(String::from("actor-pub")),
+            ..Default::default()
+        };
+        let msg = PubMessage {
+            subject: String::new(),
+            reply_to: None,
+            body: b"hello".to_vec(),
+        };
+        assert!(prov.publish(&ctx, &msg).await.is_err());
+    }
+
+    /// request against an unreachable queue must come back as an Err, not a
+    /// panic in the provider task
+    #[tokio::test]
+    async fn test_request_failure_no_panic() {
+        std::env::set_var("AWS_REGION", "us-east-1");
+        std::env::set_var("AWS_ACCESS_KEY_ID", "test");
+        std::env::set_var("AWS_SECRET_ACCESS_KEY", "test");
+        let aws_config = aws_config::from_env().load().await;
+        let conf = aws_sdk_sqs::config::Builder::from(&aws_config)
+            .endpoint_resolver(aws_sdk_sqs::Endpoint::immutable(
+                "http://127.0.0.1:1".parse().unwrap(),
+            ))
+            .build();
+        let mut bundle = test_bundle("http://127.0.0.1:1/000000000000/missing").await;
+        bundle.client = aws_sdk_sqs::Client::from_conf(conf);
+
+        let prov = SqsMessagingProvider::default();
+        prov.actors
+            .write()
+            .await
+            .insert(String::from("actor-req"), bundle);
+        let ctx = Context {
+            actor: Some(String::from("actor-req")),
+            ..Default::default()
+        };
+        let msg = RequestMessage {
+            subject: String::from("anything"),
+            body: Vec::new(),
+            timeout_ms: 10,
+        };
+        assert!(prov.request(&ctx, &msg).await.is_err());
+    }
+
+    /// links with config that can never work are denied rather than errored,
+    /// while transient aws failures still propagate as Err for a host retry
+    #[tokio::test]
+    async fn test_put_link_denies_invalid_config() {
+        std::env::set_var("AWS_REGION", "us-east-1");
+        let prov = SqsMessagingProvider::default();
+
+        // no queue_name: nothing to link against
+        let ld = crate::config::test::link_with_values(&[("aws_region", "us-east-1")]);
+        assert!(!prov.put_link(&ld).await.unwrap());
+
+        // partial credentials are a config error, not a transient one
+        let ld = crate::config::test::link_with_values(&[
+            ("queue_name", "orders"),
+            ("access_key_id", "AKID"),
+        ]);
+        assert!(!prov.put_link(&ld).await.unwrap());
+        assert!(prov.actors.read().await.is_empty());
+    }
+
+    /// the shorter of the caller's timeout and the configured long poll wins
+    #[test]
+    fn test_request_wait_seconds() {
+        assert_eq!(request_wait_seconds(0, 20), 20);
+        assert_eq!(request_wait_seconds(5000, 20), 5);
+        // sub-second timeouts degrade to a short poll
+        assert_eq!(request_wait_seconds(500, 20), 0);
+        assert_eq!(request_wait_seconds(60_000, 2), 2);
+    }
+
+    /// the reply's subject echoes the requested subject and reply_to comes
+    /// from the received message's attribute
+    #[test]
+    fn test_build_reply_subject_and_reply_to() {
+        let message = Message::builder()
+            .body("pong")
+            .message_attributes(
+                "reply_to",
+                MessageAttributeValue::builder()
+                    .data_type("String")
+                    .string_value("replies-queue")
+                    .build(),
+            )
+            .build();
+        let reply = build_reply("ping", &message, None).unwrap();
+        assert_eq!(reply.subject, "ping");
+        assert_eq!(reply.reply_to.as_deref(), Some("replies-queue"));
+        // the reply_to attribute is consumed, not echoed into the body envelope
+        let (body, attributes, _) = unwrap_envelope(&reply.body);
+        assert_eq!(body, b"pong");
+        assert!(attributes.is_empty());
+    }
+
+    /// delays double from the base, stay within the jitter window, respect
+    /// the cap, and reset after a successful poll
+    #[test]
+    fn test_backoff_growth_and_reset() {
+        let mut backoff = Backoff::new(Duration::from_secs(30));
+        for failures in 0..20u32 {
+            let delay = backoff.next_delay();
+            let exp = Duration::from_millis(crate::RECEIVE_BACKOFF_BASE_MS)
+                .saturating_mul(1 << failures.min(16))
+                .min(Duration::from_secs(30));
+            assert!(delay <= exp, "delay {:?} above {:?}", delay, exp);
+            assert!(delay >= exp / 2, "delay {:?} below jitter floor", delay);
+        }
+        backoff.reset();
+        assert!(backoff.next_delay() <= Duration::from_millis(crate::RECEIVE_BACKOFF_BASE_MS));
+    }
+
+    /// five handled messages become one delete batch with five distinct entries
+    #[test]
+    fn test_delete_batch_entries() {
+        let receipts: Vec<String> = (0..5).map(|i| format!("receipt-{}", i)).collect();
+        let entries = delete_batch_entries(receipts);
+        assert_eq!(entries.len(), 5);
+        let mut ids: Vec<_> = entries.iter().map(|e| e.id().unwrap()).collect();
+        ids.dedup();
+        assert_eq!(ids.len(), 5);
+        assert_eq!(entries[4].receipt_handle(), Some("receipt-4"));
+    }
+
+    /// batch entries carry the same attributes and fifo ids a direct send would
+    #[test]
+    fn test_batch_entry_mapping() {
+        let mut message = pending("hello");
+        message
+            .attributes
+            .insert(String::from("content-type"), String::from("text/plain"));
+        message.fifo = Some((String::from("group-1"), Some(String::from("d-1"))));
+        let entry = batch_entry(3, message);
+        assert_eq!(entry.id(), Some("3"));
+        assert_eq!(entry.message_body(), Some("hello"));
+        assert_eq!(entry.message_group_id(), Some("group-1"));
+        assert_eq!(entry.message_deduplication_id(), Some("d-1"));
+        let attrs = entry.message_attributes().unwrap();
+        assert_eq!(attrs.len(), 2);
+    }
+
+    /// Subject routing should use the cache when it can and fall back to the
+    /// link's queue when routing is off or the subject is empty
+    #[tokio::test]
+    async fn test_resolve_queue_url_routing() {
+        // routing off: the subject is ignored entirely
+        let bundle = test_bundle("default-url").await;
+        assert_eq!(bundle.resolve_queue_url("orders").await.unwrap(), "default-url");
+
+        let mut bundle = test_bundle("default-url").await;
+        bundle.config.subject_routing = true;
+        bundle
+            .resolved_urls
+            .write()
+            .await
+            .insert(String::from("orders"), String::from("orders-url"));
+
+        // empty subject falls back to the configured queue
+        assert_eq!(bundle.resolve_queue_url("").await.unwrap(), "default-url");
+        // cached subjects resolve without another get_queue_url call
+        assert_eq!(bundle.resolve_queue_url("orders").await.unwrap(), "orders-url");
+    }
+
+    /// with url caching off a prepopulated cache entry is ignored, and
+    /// invalidation drops an entry so the next resolve goes back to sqs
+    #[tokio::test]
+    async fn test_queue_url_cache_bypass_and_invalidation() {
+        std::env::set_var("AWS_REGION", "us-east-1");
+        std::env::set_var("AWS_ACCESS_KEY_ID", "test");
+        std::env::set_var("AWS_SECRET_ACCESS_KEY", "test");
+        let config = SQSConfig {
+            endpoint_url: Some(String::from("http://127.0.0.1:1")),
+            max_attempts: Some(1),
+            ..Default::default()
+        };
+        let client = SqsMessagingProvider::build_client(&config).await.unwrap();
+
+        let mut bundle = test_bundle("default-url").await;
+        bundle.client = client;
+        bundle.config.subject_routing = true;
+        bundle.config.cache_queue_url = false;
+        bundle
+            .resolved_urls
+            .write()
+            .await
+            .insert(String::from("orders"), String::from("stale-url"));
+
+        // caching off: the stale entry is not served, so the resolve goes to
+        // the (unreachable) endpoint and fails instead
+        assert!(bundle.resolve_queue_url("orders").await.is_err());
+
+        // invalidation empties the cache entry for the subject
+        bundle.invalidate_queue_url("orders").await;
+        assert!(bundle.resolved_urls.read().await.get("orders").is_none());
+    }
+
+    /// only expiry/invalid-token failures trigger a client rebuild
+    #[test]
+    fn test_is_credential_expired() {
+        assert!(is_credential_expired("ExpiredToken: The security token is expired"));
+        assert!(is_credential_expired("InvalidClientTokenId"));
+        assert!(is_credential_expired("UnrecognizedClientException"));
+        assert!(!is_credential_expired("AccessDenied"));
+        assert!(!is_credential_expired("Throttling"));
+    }
+
+    /// refreshing a client swaps the shared cache entry and every bundle on
+    /// the same key without disturbing the reference count
+    #[tokio::test]
+    async fn test_refresh_client_replaces_cached_client() {
+        std::env::set_var("AWS_REGION", "us-east-1");
+        let prov = SqsMessagingProvider::default();
+        let config = SQSConfig {
+            aws_region: Some(String::from("us-east-1")),
+            endpoint_url: Some(String::from("http://127.0.0.1:1")),
+            ..Default::default()
+        };
+        let (client, key) = prov.checkout_client(&config).await.unwrap();
+        let mut bundle = test_bundle("q").await;
+        bundle.client = client;
+        bundle.client_key = key.clone();
+        prov.actors
+            .write()
+            .await
+            .insert(String::from("actor-refresh"), bundle);
+
+        prov.refresh_client(&config, &key).await.unwrap();
+        let clients = prov.clients.read().await;
+        assert_eq!(clients.len(), 1);
+        assert_eq!(clients[&key].refs, 1, "refresh must not touch the refcount");
+    }
+
+    /// only a missing-queue error is worth a cache drop and a retry
+    #[test]
+    fn test_is_queue_missing() {
+        assert!(is_queue_missing(
+            "AWS.SimpleQueueService.NonExistentQueue: The specified queue does not exist"
+        ));
+        assert!(is_queue_missing("QueueDoesNotExist"));
+        assert!(!is_queue_missing("AccessDenied"));
+    }
+
+    /// Two linked actors must not clobber each other's client or queue
+    #[tokio::test]
+    async fn test_per_actor_client_isolation() {
+        let prov = SqsMessagingProvider::default();
+        {
+            let mut map = prov.actors.write().await;
+            map.insert(String::from("actor-a"), test_bundle("queue-url-a").await);
+            map.insert(String::from("actor-b"), test_bundle("queue-url-b").await);
+        }
+
+        let ctx_a = Context {
+            actor: Some(String::from("actor-a")),
+            ..Default::default()
+        };
+        let ctx_b = Context {
+            actor: Some(String::from("actor-b")),
+            ..Default::default()
+        };
+        assert_eq!(prov.bundle_for_actor(&ctx_a).await.unwrap().queue_url, "queue-url-a");
+        assert_eq!(prov.bundle_for_actor(&ctx_b).await.unwrap().queue_url, "queue-url-b");
+
+        prov.delete_link("actor-a").await;
+        assert!(prov.bundle_for_actor(&ctx_a).await.is_err());
+        assert_eq!(prov.bundle_for_actor(&ctx_b).await.unwrap().queue_url, "queue-url-b");
+    }
+
+    /// Concurrent invocations from different actors each resolve their own
+    /// bundle, and an invocation without an actor identity is rejected
+    #[tokio::test]
+    async fn test_concurrent_requests_use_own_clients() {
+        let prov = SqsMessagingProvider::default();
+        {
+            let mut map = prov.actors.write().await;
+            map.insert(String::from("actor-a"), test_bundle("queue-url-a").await);
+            map.insert(String::from("actor-b"), test_bundle("queue-url-b").await);
+        }
+
+        let ctx_a = Context {
+            actor: Some(String::from("actor-a")),
+            ..Default::default()
+        };
+        let ctx_b = Context {
+            actor: Some(String::from("actor-b")),
+            ..Default::default()
+        };
+        let (bundle_a, bundle_b) =
+            tokio::join!(prov.bundle_for_actor(&ctx_a), prov.bundle_for_actor(&ctx_b));
+        assert_eq!(bundle_a.unwrap().queue_url, "queue-url-a");
+        assert_eq!(bundle_b.unwrap().queue_url, "queue-url-b");
+
+        // no hardcoded fallback when the invocation carries no actor
+        let err = prov.bundle_for_actor(&Context::default()).await.unwrap_err();
+        assert!(matches!(err, RpcError::InvalidParameter(_)));
+    }
+
+    /// a binding only gets its own client when it actually points somewhere
+    /// other than the link's endpoint
+    #[tokio::test]
+    async fn test_binding_client_per_endpoint() {
+        use crate::config::{QueueBinding, QueueRole};
+        std::env::set_var("AWS_REGION", "us-east-1");
+        let config = SQSConfig {
+            endpoint_url: Some(String::from("http://localhost:4566")),
+            ..Default::default()
+        };
+        let binding = |endpoint: Option<&str>| QueueBinding {
+            name: String::from("orders"),
+            role: QueueRole::Both,
+            weight: 1,
+            endpoint_url: endpoint.map(str::to_string),
+        };
+
+        let shared = SqsMessagingProvider::binding_client(&config, &binding(None))
+            .await
+            .unwrap();
+        assert!(shared.is_none(), "no override should reuse the link client");
+        let same = SqsMessagingProvider::binding_client(
+            &config,
+            &binding(Some("http://localhost:4566")),
+        )
+        .await
+        .unwrap();
+        assert!(same.is_none(), "a matching override should reuse the link client");
+        let dedicated = SqsMessagingProvider::binding_client(
+            &config,
+            &binding(Some("http://localhost:4567")),
+        )
+        .await
+        .unwrap();
+        assert!(dedicated.is_some(), "a distinct endpoint needs its own client");
+    }
+
+    /// The link's aws_region wins over the ambient environment
+    #[tokio::test]
+    async fn test_build_client_region() {
+        std::env::set_var("AWS_REGION", "us-east-1");
+
+        let east = SQSConfig {
+            queue_name: String::from("q"),
+            aws_region: Some(String::from("us-east-1")),
+            ..Default::default()
+        };
+        let west = SQSConfig {
+            aws_region: Some(String::from("eu-west-1")),
+            ..east.clone()
+        };
+        let ambient = SQSConfig {
+            aws_region: None,
+            ..east.clone()
+        };
+
+        let client = SqsMessagingProvider::build_client(&east).await.unwrap();
+        assert_eq!(
+            client.conf().region().map(|r| r.to_string()),
+            Some(String::from("us-east-1"))
+        );
+        let client = SqsMessagingProvider::build_client(&west).await.unwrap();
+        assert_eq!(
+            client.conf().region().map(|r| r.to_string()),
+            Some(String::from("eu-west-1"))
+        );
+        let client = SqsMessagingProvider::build_client(&ambient).await.unwrap();
+        assert_eq!(
+            client.conf().region().map(|r| r.to_string()),
+            Some(String::from("us-east-1"))
+        );
+    }
+
+    /// Unlinking must signal the receive loop so it exits instead of leaking
+    #[tokio::test]
+    async fn test_unlink_stops_receive_loop() {
+        let prov = SqsMessagingProvider::default();
+        let mut bundle = test_bundle("q").await;
+        let cancel = bundle.cancel.clone();
+        let loop_token = cancel.clone();
+        bundle.poll_handles = vec![std::sync::Arc::new(tokio::spawn(async move {
+            loop_token.cancelled().await;
+        }))];
+        let handle = bundle.poll_handles[0].clone();
+        prov.actors
+            .write()
+            .await
+            .insert(String::from("actor-a"), bundle);
+
+        prov.delete_link("actor-a").await;
+        assert!(cancel.is_cancelled());
+        for _ in 0..100 {
+            if handle.is_finished() {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        assert!(handle.is_finished(), "receive loop should exit after unlink");
+    }
+
+    /// fifo queues are created with the fifo flag and the link's
+    /// content-based deduplication setting; standard queues get neither
+    #[test]
+    fn test_create_queue_attributes_fifo() {
+        use aws_sdk_sqs::model::QueueAttributeName;
+
+        let config = SQSConfig {
+            content_based_deduplication: true,
+            ..Default::default()
+        };
+        let attributes = create_queue_attributes(&config, "orders.fifo");
+        assert!(attributes.contains(&(QueueAttributeName::FifoQueue, String::from("true"))));
+        assert!(attributes.contains(&(
+            QueueAttributeName::ContentBasedDeduplication,
+            String::from("true")
+        )));
+
+        let config = SQSConfig::default();
+        let attributes = create_queue_attributes(&config, "orders.fifo");
+        assert!(attributes.contains(&(
+            QueueAttributeName::ContentBasedDeduplication,
+            String::from("false")
+        )));
+
+        let attributes = create_queue_attributes(&config, "orders");
+        assert!(!attributes
+            .iter()
+            .any(|(name, _)| matches!(name, QueueAttributeName::FifoQueue)));
+
+        // high-throughput fifo settings ride along when configured
+        let config = SQSConfig {
+            deduplication_scope: Some(String::from("messageGroup")),
+            fifo_throughput_limit: Some(String::from("perMessageGroupId")),
+            ..Default::default()
+        };
+        let attributes = create_queue_attributes(&config, "orders.fifo");
+        assert!(attributes.contains(&(
+            QueueAttributeName::DeduplicationScope,
+            String::from("messageGroup")
+        )));
+        assert!(attributes.contains(&(
+            QueueAttributeName::FifoThroughputLimit,
+            String::from("perMessageGroupId")
+        )));
+    }
+
+    /// with a limit of 2 and a slow handler, no more than two dispatches run
+    /// at once, and every successful dispatch reports its receipt handle
+    #[tokio::test]
+    async fn test_dispatch_batch_bounds_concurrency() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let messages: Vec<aws_sdk_sqs::model::Message> = (0..6)
+            .map(|i| {
+                aws_sdk_sqs::model::Message::builder()
+                    .receipt_handle(format!("receipt-{}", i))
+                    .build()
+            })
+            .collect();
+
+        let current = std::sync::Arc::new(AtomicUsize::new(0));
+        let peak = std::sync::Arc::new(AtomicUsize::new(0));
+        let (handled, failed) = dispatch_batch(messages, 2, {
+            let current = current.clone();
+            let peak = peak.clone();
+            move |_message| {
+                let current = current.clone();
+                let peak = peak.clone();
+                async move {
+                    let running = current.fetch_add(1, Ordering::SeqCst) + 1;
+                    peak.fetch_max(running, Ordering::SeqCst);
+                    tokio::time::sleep(Duration::from_millis(20)).await;
+                    current.fetch_sub(1, Ordering::SeqCst);
+                    true
+                }
+            }
+        })
+        .await;
+
+        assert_eq!(handled.len(), 6);
+        assert!(failed.is_empty());
+        assert!(handled.iter().all(|receipt| receipt.is_some()));
+        assert!(
+            peak.load(Ordering::SeqCst) <= 2,
+            "saw {} concurrent handlers",
+            peak.load(Ordering::SeqCst)
+        );
+    }
+
+    /// failed dispatches come back separately from handled ones so the
+    /// dispatch error policy can act on exactly the failed receipts
+    #[tokio::test]
+    async fn test_dispatch_batch_separates_failures() {
+        let messages: Vec<aws_sdk_sqs::model::Message> = (0..4)
+            .map(|i| {
+                aws_sdk_sqs::model::Message::builder()
+                    .receipt_handle(format!("receipt-{}", i))
+                    .build()
+            })
+            .collect();
+        let (handled, failed) = dispatch_batch(messages, 4, |message| {
+            let ok = message.receipt_handle().unwrap_or_default().ends_with(['0', '2']);
+            async move { ok }
+        })
+        .await;
+        assert_eq!(handled.len(), 2);
+        assert_eq!(failed, vec!["receipt-1", "receipt-3"]);
+    }
+
+    /// a queue created between refreshes is picked up exactly once, and
+    /// queues that are already subscribed never get a second loop
+    #[test]
+    fn test_prefix_discovery_finds_new_queues() {
+        let url = |name: &str| format!("https://sqs.us-east-1.amazonaws.com/123/{}", name);
+        let mut known: std::collections::HashSet<String> =
+            std::collections::HashSet::from([url("tenant-a")]);
+
+        // first refresh: nothing new
+        assert!(new_queue_urls(&known, &[url("tenant-a")]).is_empty());
+
+        // a queue appears between refreshes
+        let listed = vec![url("tenant-a"), url("tenant-b")];
+        let found = new_queue_urls(&known, &listed);
+        assert_eq!(found, vec![url("tenant-b")]);
+        known.extend(found);
+
+        // the next refresh doesn't re-subscribe it
+        assert!(new_queue_urls(&known, &listed).is_empty());
+    }
+
+    /// the heartbeat fires at half the visibility timeout and stops once the
+    /// configured extension budget is spent
+    #[test]
+    fn test_heartbeat_schedule() {
+        let (interval, max_beats) = heartbeat_schedule(30, 3_600);
+        assert_eq!(interval, Duration::from_secs(15));
+        assert_eq!(max_beats, 120);
+
+        // degenerate settings still beat at least once, at least every second
+        let (interval, max_beats) = heartbeat_schedule(1, 1);
+        assert_eq!(interval, Duration::from_secs(1));
+        assert_eq!(max_beats, 1);
+        let (_, max_beats) = heartbeat_schedule(30, 10);
+        assert_eq!(max_beats, 1);
+    }
+
+    /// a handler running well past the base visibility timeout keeps its
+    /// lease alive as long as the heartbeat is running
+    #[tokio::test]
+    async fn test_heartbeat_outlives_slow_handler() {
+        use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+
+        let start = std::time::Instant::now();
+        let base_ms = 40u64;
+        // the lease deadline, as milliseconds since `start`; each beat
+        // re-extends it the way change_message_visibility would
+        let deadline = std::sync::Arc::new(AtomicU64::new(base_ms));
+        let beats = std::sync::Arc::new(AtomicUsize::new(0));
+
+        let heartbeat = tokio::spawn({
+            let deadline = deadline.clone();
+            let beats = beats.clone();
+            async move {
+                run_heartbeat(Duration::from_millis(base_ms / 2), 100, || {
+                    let deadline = deadline.clone();
+                    let beats = beats.clone();
+                    async move {
+                        let now_ms = start.elapsed().as_millis() as u64;
+                        deadline.store(now_ms + base_ms, Ordering::SeqCst);
+                        beats.fetch_add(1, Ordering::SeqCst);
+                    }
+                })
+                .await;
+            }
+        });
+
+        // the "handler" runs for twice the base timeout
+        tokio::time::sleep(Duration::from_millis(base_ms * 2)).await;
+        heartbeat.abort();
+
+        let now_ms = start.elapsed().as_millis() as u64;
+        assert!(
+            deadline.load(Ordering::SeqCst) > now_ms,
+            "lease expired while the handler was still running"
+        );
+        assert!(beats.load(Ordering::SeqCst) >= 2);
+    }
+
+    /// an unattended heartbeat stops on its own once the extension budget
+    /// is spent instead of hiding the message forever
+    #[tokio::test]
+    async fn test_heartbeat_bounded_by_max_extension() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        let beats = std::sync::Arc::new(AtomicUsize::new(0));
+        run_heartbeat(Duration::from_millis(1), 3, || {
+            let beats = beats.clone();
+            async move {
+                beats.fetch_add(1, Ordering::SeqCst);
+            }
+        })
+        .await;
+        assert_eq!(beats.load(Ordering::SeqCst), 3);
+    }
+
+    /// every message in a batch gets its own dispatch span, and each one is
+    /// an explicit child of the batch span
+    #[tokio::test]
+    async fn test_batch_spans_nest_per_message() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use tracing::Instrument;
+        use tracing_subscriber::{layer::SubscriberExt, registry::LookupSpan, Layer};
+
+        #[derive(Default, Clone)]
+        struct SpanCount {
+            batches: std::sync::Arc<AtomicUsize>,
+            children: std::sync::Arc<AtomicUsize>,
+        }
+        impl<S> Layer<S> for SpanCount
+        where
+            S: tracing::Subscriber + for<'a> LookupSpan<'a>,
+        {
+            fn on_new_span(
+                &self,
+                attrs: &tracing::span::Attributes<'_>,
+                _id: &tracing::span::Id,
+                ctx: tracing_subscriber::layer::Context<'_, S>,
+            ) {
+                match attrs.metadata().name() {
+                    "sqs_batch" => {
+                        self.batches.fetch_add(1, Ordering::SeqCst);
+                    }
+                    "dispatch_message" => {
+                        let under_batch = attrs
+                            .parent()
+                            .and_then(|id| ctx.span(id))
+                            .map(|span| span.name() == "sqs_batch")
+                            .unwrap_or(false);
+                        assert!(under_batch, "dispatch span not nested under the batch");
+                        self.children.fetch_add(1, Ordering::SeqCst);
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        let count = SpanCount::default();
+        let subscriber = tracing_subscriber::registry().with(count.clone());
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let messages: Vec<aws_sdk_sqs::model::Message> = (0..3)
+            .map(|i| {
+                aws_sdk_sqs::model::Message::builder()
+                    .message_id(format!("id-{}", i))
+                    .receipt_handle(format!("receipt-{}", i))
+                    .build()
+            })
+            .collect();
+        let batch = batch_span("https://sqs/q", messages.len(), "actor-spans");
+        dispatch_batch(messages, 3, move |message| {
+            let span = message_span(&batch, &message);
+            async move { true }.instrument(span)
+        })
+        .await;
+
+        assert_eq!(count.batches.load(Ordering::SeqCst), 1);
+        assert_eq!(count.children.load(Ordering::SeqCst), 3);
+    }
+
+    /// with xray on, the outgoing entry carries the AWSTraceHeader system
+    /// attribute, whether the header came from the caller or the environment
+    #[test]
+    fn test_xray_trace_header_on_outgoing_message() {
+        // explicit envelope attribute wins and is consumed
+        let mut attributes = HashMap::from([(
+            String::from("aws_trace_header"),
+            String::from("Root=1-5759e988-bd862e3fe1be46a994272793"),
+        )]);
+        let header = xray_trace_header(&mut attributes).unwrap();
+        assert!(attributes.is_empty());
+
+        let mut message = pending("body");
+        message.trace_header = Some(header.clone());
+        let entry = batch_entry(0, message);
+        let system = entry.message_system_attributes().unwrap();
+        let value = system
+            .get(&aws_sdk_sqs::model::MessageSystemAttributeNameForSends::AwsTraceHeader)
+            .unwrap();
+        assert_eq!(value.string_value(), Some(header.as_str()));
+
+        // the environment is the fallback
+        std::env::set_var("_X_AMZN_TRACE_ID", "Root=1-aaaaaaaa-bbbbbbbbbbbbbbbbbbbbbbbb");
+        let fallback = xray_trace_header(&mut HashMap::new());
+        std::env::remove_var("_X_AMZN_TRACE_ID");
+        assert_eq!(
+            fallback.as_deref(),
+            Some("Root=1-aaaaaaaa-bbbbbbbbbbbbbbbbbbbbbbbb")
+        );
+    }
+
+    #[test]
+    fn test_body_preview_truncates_and_redacts() {
+        // short body, no pattern: passes through untouched
+        assert_eq!(body_preview(b"hello", 256, None), "hello");
+        // matches of the redaction pattern never reach the log
+        let redact = regex::Regex::new(r"\d{4}-\d{4}").unwrap();
+        let preview = body_preview(b"card 1234-5678 charged", 256, Some(&redact));
+        assert_eq!(preview, "card [REDACTED] charged");
+        assert!(!preview.contains("1234"));
+        // truncation lands on a char boundary even for multibyte text
+        let preview = body_preview("héllo wörld".as_bytes(), 5, None);
+        assert!(preview.ends_with('…'));
+        assert!(preview.len() <= 5 + '…'.len_utf8());
+    }
+
+    /// a received trace header is surfaced under the same envelope attribute
+    #[test]
+    fn test_xray_trace_header_on_receive() {
+        let message = aws_sdk_sqs::model::Message::builder()
+            .attributes(
+                aws_sdk_sqs::model::MessageSystemAttributeName::AwsTraceHeader,
+                "Root=1-5759e988-bd862e3fe1be46a994272793",
+            )
+            .build();
+        let mut attributes = HashMap::new();
+        collect_xray_trace_header(&message, &mut attributes);
+        assert_eq!(
+            attributes["aws_trace_header"],
+            "Root=1-5759e988-bd862e3fe1be46a994272793"
+        );
+    }
+
+    /// the correlation id attribute is surfaced when present and absent
+    /// otherwise
+    #[test]
+    fn test_correlation_id_attribute() {
+        let message = aws_sdk_sqs::model::Message::builder()
+            .message_attributes("correlation-id", string_attribute("corr-42"))
+            .build();
+        assert_eq!(correlation_id(&message).as_deref(), Some("corr-42"));
+        let bare = aws_sdk_sqs::model::Message::builder().build();
+        assert_eq!(correlation_id(&bare), None);
+    }
+
+    /// dispatches are attributed to the linked actor, not an anonymous call
+    #[test]
+    fn test_dispatch_context_carries_actor_id() {
+        let ld = crate::config::test::link_with_values(&[("queue_name", "q")]);
+        let ctx = dispatch_context(&ld);
+        assert_eq!(ctx.actor.as_deref(), Some(ld.actor_id.as_str()));
+    }
+
+    /// only messages carrying every filtered attribute reach the actor
+    #[test]
+    fn test_matches_subscribe_filter() {
+        let tagged = |pairs: &[(&str, &str)]| {
+            let mut builder = Message::builder();
+            for (name, value) in pairs {
+                builder = builder.message_attributes(
+                    name.to_string(),
+                    MessageAttributeValue::builder()
+                        .data_type("String")
+                        .string_value(value.to_string())
+                        .build(),
+                );
+            }
+            builder.build()
+        };
+        let filter = std::collections::HashMap::from([(
+            String::from("type"),
+            String::from("order"),
+        )]);
+
+        assert!(matches_subscribe_filter(&tagged(&[("type", "order")]), &filter));
+        assert!(!matches_subscribe_filter(&tagged(&[("type", "refund")]), &filter));
+        // a message without the attribute at all never matches
+        assert!(!matches_subscribe_filter(&tagged(&[]), &filter));
+        // the empty filter dispatches everything
+        assert!(matches_subscribe_filter(&tagged(&[]), &Default::default()));
+
+        // every pair has to hold, not just one
+        let both = std::collections::HashMap::from([
+            (String::from("type"), String::from("order")),
+            (String::from("region"), String::from("eu")),
+        ]);
+        assert!(matches_subscribe_filter(
+            &tagged(&[("type", "order"), ("region", "eu")]),
+            &both
+        ));
+        assert!(!matches_subscribe_filter(&tagged(&[("type", "order")]), &both));
+    }
+
+    /// pointer bodies survive the round trip, and anything else parses to
+    /// None instead of being mistaken for a pointer
+    #[test]
+    fn test_s3_pointer_round_trip() {
+        let body = s3_pointer_body("overflow-bucket", "orders/abc-1");
+        assert_eq!(
+            parse_s3_pointer(&body),
+            Some((String::from("overflow-bucket"), String::from("orders/abc-1")))
+        );
+        assert_eq!(parse_s3_pointer("a plain message body"), None);
+        assert_eq!(parse_s3_pointer("{\"s3_bucket_name\": \"b\"}"), None);
+
+        // only the marker attribute makes a message offloaded
+        let marked = Message::builder()
+            .body(&body)
+            .message_attributes(
+                crate::S3_POINTER_ATTRIBUTE,
+                MessageAttributeValue::builder()
+                    .data_type("String")
+                    .string_value("1048576")
+                    .build(),
+            )
+            .build();
+        assert!(is_offloaded(&marked));
+        assert!(!is_offloaded(&Message::builder().body(&body).build()));
+    }
+
+    /// concurrent offloads never reuse an object key
+    #[test]
+    fn test_large_payload_keys_unique() {
+        let keys: std::collections::HashSet<String> =
+            (0..100).map(|_| large_payload_key("orders")).collect();
+        assert_eq!(keys.len(), 100);
+        assert!(keys.iter().all(|k| k.starts_with("orders/")));
+    }
+
+    /// an oversized publish is rejected with the size spelled out, counting
+    /// attribute overhead the same way sqs does
+    #[test]
+    fn test_check_message_size() {
+        let no_attributes = std::collections::HashMap::new();
+        assert!(check_message_size(262_144, &no_attributes).is_ok());
+        let err = check_message_size(300 * 1024, &no_attributes).unwrap_err();
+        assert!(matches!(err, RpcError::InvalidParameter(_)));
+        let text = err.to_string();
+        assert!(text.contains("307200"), "error names the actual size: {}", text);
+        assert!(text.contains("262144"), "error names the limit: {}", text);
+
+        // attributes count against the cap, so a body at the limit with any
+        // attribute is over it
+        let attributes = std::collections::HashMap::from([(
+            String::from("type"),
+            String::from("order"),
+        )]);
+        assert!(check_message_size(262_144, &attributes).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_token_bucket_paces_bursts() {
+        use crate::config::RateLimitBehavior;
+        let bucket = TokenBucket::new(50);
+        // the burst allowance covers the first second's worth of publishes
+        for _ in 0..50 {
+            assert!(bucket.try_take().await.is_ok());
+        }
+        let wait = bucket
+            .try_take()
+            .await
+            .expect_err("bucket should be empty");
+        assert!(wait > Duration::ZERO && wait <= Duration::from_millis(25));
+        // blocking behavior waits for the refill instead of failing
+        let start = std::time::Instant::now();
+        bucket
+            .take(RateLimitBehavior::Block, Duration::from_secs(5))
+            .await
+            .expect("blocking take should succeed");
+        assert!(start.elapsed() >= Duration::from_millis(10));
+        // error behavior fails as soon as the bucket is dry
+        while bucket.try_take().await.is_ok() {}
+        let denied = bucket
+            .take(RateLimitBehavior::Error, Duration::from_secs(5))
+            .await
+            .expect_err("error behavior should not wait");
+        assert!(denied.to_string().contains("throttled"));
+    }
+
+    #[test]
+    fn test_idle_event_fires_once_per_idle_stretch() {
+        // disabled links never notify
+        assert!(!idle_event_due(5, 0));
+        // ten empty polls against a threshold of three: exactly one event
+        let fired: Vec<u64> = (1..=10).filter(|n| idle_event_due(*n, 3)).collect();
+        assert_eq!(fired, vec![3]);
+        // activity resets the count, so the next idle stretch fires again
+        assert!(idle_event_due(3, 3));
+        let event = idle_notification("wasmcloud-queue", 3);
+        assert_eq!(event.subject, EVENT_QUEUE_IDLE_SUBJECT);
+        let body: serde_json::Value = serde_json::from_slice(&event.body).unwrap();
+        assert_eq!(body["queue"], "wasmcloud-queue");
+        assert_eq!(body["empty_polls"], 3);
+    }
+
+    #[test]
+    fn test_failover_breaker_routes_after_threshold() {
+        let breaker = FailoverBreaker::new(3, Duration::from_secs(60));
+        assert!(!breaker.route_to_failover());
+        assert!(!breaker.record_failure());
+        assert!(!breaker.record_failure());
+        assert!(!breaker.route_to_failover(), "below the threshold");
+        // the third consecutive failure opens the circuit
+        assert!(breaker.record_failure());
+        assert!(breaker.route_to_failover());
+        assert!(breaker.route_to_failover(), "stays open between probes");
+        // a primary success (from a probe) closes it again
+        breaker.record_success();
+        assert!(!breaker.route_to_failover());
+    }
+
+    #[test]
+    fn test_failover_breaker_probes_primary() {
+        // a zero probe interval releases a probe on every check
+        let breaker = FailoverBreaker::new(1, Duration::ZERO);
+        assert!(breaker.record_failure());
+        assert!(!breaker.route_to_failover(), "probe goes to the primary");
+        // a failed probe keeps the circuit open
+        assert!(!breaker.record_failure());
+        let held = FailoverBreaker::new(1, Duration::from_secs(60));
+        assert!(held.record_failure());
+        assert!(held.route_to_failover(), "no probe before the interval");
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_names_failed_field() {
+        // a queue url in the wrong region fails the queue check and the
+        // detail names aws_region, without any network traffic
+        let values: HashMap<String, String> = [
+            (
+                "queue_name",
+                "https://sqs.us-east-1.amazonaws.com/123456789012/orders",
+            ),
+            ("aws_region", "us-west-2"),
+            ("access_key_id", "AKIDEXAMPLE"),
+            ("secret_access_key", "secret"),
+            ("endpoint_url", "http://127.0.0.1:1"),
+        ]
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+        let report = validate_link_values(&values).await;
+        assert!(!report.passed);
+        let queue = report
+            .checks
+            .iter()
+            .find(|c| c.name.starts_with("queue"))
+            .expect("queue check present");
+        assert!(!queue.passed);
+        assert!(queue.detail.contains("aws_region"), "{}", queue.detail);
+        // config and client stages still passed
+        assert!(report.checks.iter().any(|c| c.name == "config" && c.passed));
+        assert!(report.checks.iter().any(|c| c.name == "client" && c.passed));
+
+        // an unparsable link stops at the config check
+        let values: HashMap<String, String> = [("queue_name", "q"), ("delivery_mode", "bogus")]
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        let report = validate_link_values(&values).await;
+        assert!(!report.passed);
+        assert_eq!(report.checks.len(), 1);
+        assert!(report.checks[0].detail.contains("delivery_mode"));
+    }
+
+    #[test]
+    fn test_content_transfer_encoding_round_trip() {
+        let payload = vec![0u8, 159, 146, 150, 255];
+        let (body, encoding) = encode_body(&payload, BodyEncoding::Auto).unwrap();
+        assert_eq!(encoding, ENCODING_BASE64);
+        // a foreign consumer sees the standard marker; a foreign producer
+        // sets only it - decode still recovers the exact bytes
+        let message = Message::builder()
+            .body(&body)
+            .message_attributes(
+                CONTENT_TRANSFER_ENCODING_ATTRIBUTE,
+                MessageAttributeValue::builder()
+                    .data_type("String")
+                    .string_value(ENCODING_BASE64)
+                    .build(),
+            )
+            .build();
+        assert_eq!(decode_body(&message).unwrap(), payload);
+        // our own marker wins when both are present
+        let message = Message::builder()
+            .body("plain text")
+            .message_attributes(
+                ENCODING_ATTRIBUTE,
+                MessageAttributeValue::builder()
+                    .data_type("String")
+                    .string_value(ENCODING_UTF8)
+                    .build(),
+            )
+            .message_attributes(
+                CONTENT_TRANSFER_ENCODING_ATTRIBUTE,
+                MessageAttributeValue::builder()
+                    .data_type("String")
+                    .string_value(ENCODING_BASE64)
+                    .build(),
+            )
+            .build();
+        assert_eq!(decode_body(&message).unwrap(), b"plain text".to_vec());
+        // the transport marker never leaks into the actor's attributes
+        assert!(collect_attributes(&message).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_hanging_dispatch_abandoned_after_timeout() {
+        // a handler that never answers is abandoned once the timeout lapses
+        let start = std::time::Instant::now();
+        let outcome = bounded_dispatch(20, std::future::pending::<Result<(), RpcError>>()).await;
+        assert!(outcome.is_none());
+        assert!(start.elapsed() >= Duration::from_millis(20));
+        // a prompt handler is unaffected
+        let outcome = bounded_dispatch(20, std::future::ready(Ok::<(), RpcError>(()))).await;
+        assert!(matches!(outcome, Some(Ok(()))));
+        // zero disables the bound entirely
+        let outcome = bounded_dispatch(0, std::future::ready(Ok::<(), RpcError>(()))).await;
+        assert!(matches!(outcome, Some(Ok(()))));
+    }
+
+    #[test]
+    fn test_subject_pattern_matching() {
+        assert!(subject_pattern_matches("orders", "orders"));
+        assert!(!subject_pattern_matches("orders", "orders-eu"));
+        assert!(subject_pattern_matches("orders-*", "orders-eu"));
+        assert!(subject_pattern_matches("orders-*", "orders-"));
+        assert!(subject_pattern_matches("*-eu", "orders-eu"));
+        assert!(!subject_pattern_matches("*-eu", "eu"));
+        assert!(subject_pattern_matches("orders-*-audit", "orders-eu-audit"));
+        assert!(!subject_pattern_matches("orders-*-audit", "orders-audit"));
+        assert!(subject_pattern_matches("*", ""));
+        assert!(!subject_pattern_matches("orders", ""));
+    }
+
+    #[test]
+    fn test_publish_acl() {
+        let allow = vec!["orders".to_string(), "invoices-*".to_string()];
+        let deny = vec!["invoices-internal".to_string()];
+        assert!(publish_authorized("orders", &allow, &deny));
+        assert!(publish_authorized("invoices-acme", &allow, &deny));
+        // the denylist wins over a matching allow pattern
+        assert!(!publish_authorized("invoices-internal", &allow, &deny));
+        assert!(!publish_authorized("payments", &allow, &deny));
+        // an empty allowlist permits everything not denied
+        assert!(publish_authorized("payments", &[], &deny));
+        assert!(!publish_authorized("invoices-internal", &[], &deny));
+    }
+
+    #[test]
+    fn test_json_envelope_round_trip() {
+        let msg = PubMessage {
+            subject: "orders".to_string(),
+            reply_to: Some("orders-replies".to_string()),
+            body: vec![0u8, 159, 146, 150, 255],
+        };
+        let wire = to_json_envelope(&msg);
+        // the wire form is self-describing json with a base64 body
+        let parsed: serde_json::Value = serde_json::from_str(&wire).unwrap();
+        assert_eq!(parsed["subject"], "orders");
+        let sub = from_json_envelope(wire.as_bytes()).expect("valid envelope");
+        assert_eq!(sub.subject, msg.subject);
+        assert_eq!(sub.reply_to, msg.reply_to);
+        assert_eq!(sub.body, msg.body);
+        // an absent reply_to stays absent
+        let msg = PubMessage {
+            subject: String::new(),
+            reply_to: None,
+            body: b"plain".to_vec(),
+        };
+        let sub = from_json_envelope(to_json_envelope(&msg).as_bytes()).unwrap();
+        assert_eq!(sub.reply_to, None);
+        assert_eq!(sub.body, b"plain");
+        // a non-envelope body is not mistaken for one
+        assert!(from_json_envelope(b"just text").is_none());
+    }
+
+    /// consumer_count fans one queue out to parallel poll loops; one cancel
+    /// stops every loop, so shutdown cannot leak consumers
+    #[tokio::test]
+    async fn test_consumer_count_spawns_and_stops_loops() {
+        let config = SQSConfig {
+            aws_region: Some("us-east-1".to_string()),
+            access_key_id: Some("AKIDEXAMPLE".to_string()),
+            secret_access_key: Some("secret".to_string()),
+            endpoint_url: Some("http://127.0.0.1:1".to_string()),
+            consumer_count: 4,
+            ..Default::default()
+        };
+        let client = SqsMessagingProvider::build_client(&config).await.unwrap();
+        let cancel = CancellationToken::new();
+        let ld = crate::config::test::link_with_values(&[("queue_name", "q")]);
+        let handles = SqsMessagingProvider::spawn_consumers(
+            client,
+            None,
+            crate::config::QueueBinding {
+                name: "q".to_string(),
+                role: crate::config::QueueRole::Subscribe,
+                weight: 1,
+                endpoint_url: None,
+            },
+            "http://127.0.0.1:1/q".to_string(),
+            config,
+            cancel.clone(),
+            std::sync::Arc::default(),
+            std::sync::Arc::default(),
+            &ld,
+        );
+        assert_eq!(handles.len(), 4);
+        cancel.cancel();
+        for handle in handles {
+            tokio::time::timeout(Duration::from_secs(5), async {
+                std::sync::Arc::try_unwrap(handle).unwrap().await.unwrap()
+            })
+            .await
+            .expect("loop should stop promptly on cancel");
+        }
+    }
+
+    #[test]
+    fn test_sdk_error_classification() {
+        let cases = [
+            // a queue that is gone will not come back by retrying
+            ("AWS.SimpleQueueService.NonExistentQueue: does not exist", SdkErrorClass::Fatal),
+            ("QueueDoesNotExist", SdkErrorClass::Fatal),
+            ("MessageTooLong: 300000 bytes", SdkErrorClass::Fatal),
+            ("InvalidParameterValue: delay out of range", SdkErrorClass::Fatal),
+            // auth problems need a rebuilt client or a fixed link
+            ("ExpiredToken: the security token is expired", SdkErrorClass::Config),
+            ("AccessDenied: not authorized to SendMessage", SdkErrorClass::Config),
+            ("SignatureDoesNotMatch", SdkErrorClass::Config),
+            // throttling and 5xx clear on their own
+            ("ThrottlingException: Rate exceeded", SdkErrorClass::Retryable),
+            ("RequestThrottled", SdkErrorClass::Retryable),
+            ("ServiceUnavailable", SdkErrorClass::Retryable),
+            ("connection refused", SdkErrorClass::Retryable),
+        ];
+        for (text, expected) in cases {
+            assert_eq!(classify_sdk_error(text), expected, "{}", text);
+        }
+    }
+
+    #[test]
+    fn test_adaptive_concurrency_aimd() {
+        let adaptive = AdaptiveConcurrency::new(8, 10);
+        assert_eq!(adaptive.handlers(), 8);
+        assert_eq!(adaptive.batch(), 10);
+        // throttling halves both limits
+        adaptive.on_throttle();
+        assert_eq!(adaptive.handlers(), 4);
+        assert_eq!(adaptive.batch(), 5);
+        // repeated throttling floors at one, never zero
+        for _ in 0..5 {
+            adaptive.on_throttle();
+        }
+        assert_eq!(adaptive.handlers(), 1);
+        assert_eq!(adaptive.batch(), 1);
+        // successes climb back one step at a time, capped at the ceilings
+        for _ in 0..20 {
+            adaptive.on_success();
+        }
+        assert_eq!(adaptive.handlers(), 8);
+        assert_eq!(adaptive.batch(), 10);
+        assert!(is_throttling_error("RequestThrottled"));
+        assert!(is_throttling_error("ThrottlingException: Rate exceeded"));
+        assert!(is_throttling_error("OverLimit"));
+        assert!(!is_throttling_error("ServiceUnavailable"));
+    }
+
+    #[test]
+    fn test_body_hash_dedup_id() {
+        // two identical publishes derive the same id, so sqs delivers one
+        // message within the dedup window; different bodies never collide
+        let first = body_hash_dedup_id(b"same payload");
+        let second = body_hash_dedup_id(b"same payload");
+        assert_eq!(first, second);
+        assert_eq!(first.len(), 64);
+        assert_ne!(first, body_hash_dedup_id(b"different payload"));
+        // the derived id satisfies the fifo dedup requirement
+        let mut attributes = HashMap::new();
+        attributes.insert(crate::GROUP_ID_ATTRIBUTE.to_string(), "g".to_string());
+        let (_, dedup_id) =
+            fifo_ids(&mut attributes, false, None, Some(first.clone())).unwrap();
+        assert_eq!(dedup_id.as_deref(), Some(first.as_str()));
+        // but an explicit per-message id still wins
+        let mut attributes = HashMap::new();
+        attributes.insert(crate::GROUP_ID_ATTRIBUTE.to_string(), "g".to_string());
+        attributes.insert(crate::DEDUP_ID_ATTRIBUTE.to_string(), "explicit".to_string());
+        let (_, dedup_id) = fifo_ids(&mut attributes, false, None, Some(first)).unwrap();
+        assert_eq!(dedup_id.as_deref(), Some("explicit"));
+    }
+
+    /// a dispatched message names its source queue in the subject and
+    /// carries queue, receive count, sent timestamp and id in its envelope
+    #[test]
+    fn test_dispatch_source_metadata() {
+        let message = Message::builder()
+            .message_id("id-123")
+            .attributes(
+                aws_sdk_sqs::model::MessageSystemAttributeName::ApproximateReceiveCount,
+                "2",
+            )
+            .attributes(
+                aws_sdk_sqs::model::MessageSystemAttributeName::SentTimestamp,
+                "1700000000000",
+            )
+            .build();
+        let mut attributes = HashMap::new();
+        collect_system_attributes(&message, &mut attributes);
+        collect_source_metadata("orders", &message, &mut attributes);
+        let sub_msg = native_sub_message("orders", b"payload".to_vec(), attributes, &message);
+        assert_eq!(sub_msg.subject, "orders");
+        let (payload, attributes, _) = unwrap_envelope(&sub_msg.body);
+        assert_eq!(payload, b"payload");
+        assert_eq!(
+            attributes.get(crate::SOURCE_QUEUE_ATTRIBUTE).map(String::as_str),
+            Some("orders")
+        );
+        assert_eq!(
+            attributes.get(crate::MESSAGE_ID_ATTRIBUTE).map(String::as_str),
+            Some("id-123")
+        );
+        assert_eq!(
+            attributes.get(crate::RECEIVE_COUNT_ATTRIBUTE).map(String::as_str),
+            Some("2")
+        );
+        assert_eq!(
+            attributes.get(crate::SENT_TIMESTAMP_ATTRIBUTE).map(String::as_str),
+            Some("1700000000000")
+        );
+    }
+
+    /// an expired stamp drops the message, a fresh or absent one dispatches
+    #[test]
+    fn test_message_ttl_expiry() {
+        let stamped = |value: &str| {
+            Message::builder()
+                .message_attributes(
+                    EXPIRES_AT_ATTRIBUTE,
+                    MessageAttributeValue::builder()
+                        .data_type("String")
+                        .string_value(value)
+                        .build(),
+                )
+                .build()
+        };
+        let now = 1_000;
+        assert!(message_expired(&stamped("999"), now));
+        assert!(message_expired(&stamped("1000"), now));
+        assert!(!message_expired(&stamped("1001"), now));
+        // no stamp or a garbled one never expires a message
+        assert!(!message_expired(&Message::builder().build(), now));
+        assert!(!message_expired(&stamped("soon"), now));
+    }
+
+    #[test]
+    fn test_parse_replay_limit() {
+        // an empty body replays up to the default cap
+        assert_eq!(parse_replay_limit(b"").unwrap(), 100);
+        assert_eq!(parse_replay_limit(b"25").unwrap(), 25);
+        assert_eq!(parse_replay_limit(b" 7 ").unwrap(), 7);
+        // zero and junk are rejected rather than draining the whole dlq
+        assert!(parse_replay_limit(b"0").is_err());
+        assert!(parse_replay_limit(b"many").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_dlq_replay_guards() {
+        let prov = SqsMessagingProvider::default();
+        let mut bundle = test_bundle("queue-url").await;
+        bundle.config.dead_letter_queue_name = Some("orders-dlq".to_string());
+        prov.actors
+            .write()
+            .await
+            .insert("actor-replay".to_string(), bundle);
+        let ctx = Context {
+            actor: Some("actor-replay".to_string()),
+            ..Default::default()
+        };
+        let request = RequestMessage {
+            subject: CONTROL_DLQ_REPLAY_SUBJECT.to_string(),
+            body: Vec::new(),
+            timeout_ms: 1_000,
+        };
+        // without the opt-in flag the replay is refused before any aws call
+        let err = prov.request(&ctx, &request).await.unwrap_err();
+        assert!(err.to_string().contains("allow_dlq_replay"), "{}", err);
+        // with the flag but no dlq configured there is nothing to replay from
+        let mut bundle = test_bundle("queue-url").await;
+        bundle.config.allow_dlq_replay = true;
+        prov.replace_bundle("actor-replay", bundle).await;
+        let err = prov.request(&ctx, &request).await.unwrap_err();
+        assert!(err.to_string().contains("dead_letter_queue_name"), "{}", err);
+        prov.delete_link("actor-replay").await;
+    }
+
+    /// the pause control parks consumption without touching the publish
+    /// path, and resume releases it
+    #[tokio::test]
+    async fn test_pause_resume_controls() {
+        let prov = SqsMessagingProvider::default();
+        let mut bundle = test_bundle("queue-url").await;
+        bundle.config.publish_allow = vec!["orders".to_string(), "__control/*".to_string()];
+        prov.actors
+            .write()
+            .await
+            .insert("actor-pause".to_string(), bundle);
+        let ctx = Context {
+            actor: Some("actor-pause".to_string()),
+            ..Default::default()
+        };
+        let control = |subject: &str| PubMessage {
+            subject: subject.to_string(),
+            reply_to: None,
+            body: Vec::new(),
+        };
+        prov.publish(&ctx, &control(CONTROL_PAUSE_SUBJECT)).await.unwrap();
+        {
+            let actors = prov.actors.read().await;
+            let bundle = actors.get("actor-pause").unwrap();
+            assert!(bundle.paused.load(std::sync::atomic::Ordering::Relaxed));
+        }
+        // publishing stays live while paused: a denied subject still gets
+        // the acl's verdict instead of a paused refusal
+        let err = prov
+            .publish(&ctx, &control("other-tenant"))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("not authorized"), "{}", err);
+        prov.publish(&ctx, &control(CONTROL_RESUME_SUBJECT)).await.unwrap();
+        {
+            let actors = prov.actors.read().await;
+            let bundle = actors.get("actor-pause").unwrap();
+            assert!(!bundle.paused.load(std::sync::atomic::Ordering::Relaxed));
+        }
+        prov.delete_link("actor-pause").await;
+    }
+
+    #[tokio::test]
+    async fn test_publish_denied_before_aws() {
+        let prov = SqsMessagingProvider::default();
+        let mut bundle = test_bundle("queue-url").await;
+        bundle.config.publish_allow = vec!["orders".to_string()];
+        prov.actors
+            .write()
+            .await
+            .insert("acl-actor".to_string(), bundle);
+        let ctx = Context {
+            actor: Some("acl-actor".to_string()),
+            ..Default::default()
+        };
+        // the denied publish fails before any aws call could be attempted
+        let err = prov
+            .publish(
+                &ctx,
+                &PubMessage {
+                    subject: "other-tenant".to_string(),
+                    reply_to: None,
+                    body: b"payload".to_vec(),
+                },
+            )
+            .await
+            .expect_err("acl must deny");
+        assert!(matches!(err, RpcError::InvalidParameter(_)), "{}", err);
+        assert!(err.to_string().contains("other-tenant"));
+    }
+
+    /// the quarantine threshold only trips once sqs reports more deliveries
+    /// than the link allows, and never when the limit is unset
+    #[test]
+    fn test_exceeded_processing_attempts() {
+        let delivered = |count: &str| {
+            aws_sdk_sqs::model::Message::builder()
+                .attributes(
+                    aws_sdk_sqs::model::MessageSystemAttributeName::ApproximateReceiveCount,
+                    count,
+                )
+                .build()
+        };
+        assert!(!exceeded_processing_attempts(&delivered("3"), Some(3)));
+        assert!(exceeded_processing_attempts(&delivered("4"), Some(3)));
+        assert!(!exceeded_processing_attempts(&delivered("100"), None));
+        // no delivery count means sqs didn't return system attributes; don't
+        // quarantine on missing information
+        let bare = aws_sdk_sqs::model::Message::builder().build();
+        assert!(!exceeded_processing_attempts(&bare, Some(1)));
+    }
+
+    #[test]
+    fn test_is_sns_topic_arn() {
+        assert!(is_sns_topic_arn("arn:aws:sns:us-east-1:123456789012:orders"));
+        assert!(is_sns_topic_arn(
+            "arn:aws-us-gov:sns:us-gov-west-1:123456789012:orders"
+        ));
+        assert!(!is_sns_topic_arn("arn:aws:sqs:us-east-1:123456789012:orders"));
+        assert!(!is_sns_topic_arn("my-queue"));
+        assert!(!is_sns_topic_arn("https://sqs.us-east-1.amazonaws.com/1/q"));
+    }
+
+    /// an sns notification unwraps to its inner message and attributes;
+    /// anything else falls back to the raw body
+    #[test]
+    fn test_unwrap_sns_notification() {
+        let notification = br#"{
+            "Type": "Notification",
+            "MessageId": "11111111-2222-3333-4444-555555555555",
+            "TopicArn": "arn:aws:sns:us-east-1:123456789012:orders",
+            "Message": "order 42 shipped",
+            "MessageAttributes": {
+                "origin": {"Type": "String", "Value": "warehouse-7"}
+            }
+        }"#;
+        let (body, attributes) = unwrap_sns_notification(notification).unwrap();
+        assert_eq!(body, b"order 42 shipped");
+        assert_eq!(attributes.get("origin").map(String::as_str), Some("warehouse-7"));
+
+        // not json, or json that isn't a notification, passes through
+        assert!(unwrap_sns_notification(b"plain text").is_none());
+        assert!(unwrap_sns_notification(br#"{"Type": "SubscriptionConfirmation", "Message": "x"}"#).is_none());
+    }
+
+    /// the batch size scales with the binding's weight but never leaves the
+    /// 1-10 window sqs accepts
+    #[test]
+    fn test_weighted_batch_size() {
+        assert_eq!(weighted_batch_size(1, 1), 1);
+        assert_eq!(weighted_batch_size(2, 3), 6);
+        assert_eq!(weighted_batch_size(10, 5), 10);
+        assert_eq!(weighted_batch_size(1, u32::MAX), 10);
+    }
+
+    /// queue latency is the gap between SentTimestamp and now, floored at
+    /// zero when clocks disagree, and absent without the attribute
+    #[test]
+    fn test_queue_latency_ms() {
+        let message = aws_sdk_sqs::model::Message::builder()
+            .attributes(
+                aws_sdk_sqs::model::MessageSystemAttributeName::SentTimestamp,
+                "1693300000000",
+            )
+            .build();
+        assert_eq!(queue_latency_ms(&message, 1_693_300_000_250), Some(250));
+        assert_eq!(queue_latency_ms(&message, 1_693_299_999_000), Some(0));
+
+        let bare = aws_sdk_sqs::model::Message::builder().build();
+        assert_eq!(queue_latency_ms(&bare, 1_693_300_000_250), None);
+    }
+
+    /// the delivery count and send timestamp come back as system attributes
+    /// and are surfaced to actors under stable envelope keys
+    #[test]
+    fn test_collect_system_attributes() {
+        let message = aws_sdk_sqs::model::Message::builder()
+            .attributes(
+                aws_sdk_sqs::model::MessageSystemAttributeName::ApproximateReceiveCount,
+                "3",
+            )
+            .attributes(
+                aws_sdk_sqs::model::MessageSystemAttributeName::SentTimestamp,
+                "1693300000000",
+            )
+            .build();
+        assert_eq!(receive_count(&message), Some(3));
+
+        let mut attributes = HashMap::new();
+        collect_system_attributes(&message, &mut attributes);
+        assert_eq!(
+            attributes.get("approximate_receive_count").map(String::as_str),
+            Some("3")
+        );
+        assert_eq!(
+            attributes.get("sent_timestamp").map(String::as_str),
+            Some("1693300000000")
+        );
+
+        // a message without system attributes surfaces nothing extra
+        let bare = aws_sdk_sqs::model::Message::builder().build();
+        assert_eq!(receive_count(&bare), None);
+        let mut attributes = HashMap::new();
+        collect_system_attributes(&bare, &mut attributes);
+        assert!(attributes.is_empty());
+    }
+
+    /// the region is read out of standard and cn-partition urls; everything
+    /// else (legacy, localstack) carries no region to check against
+    #[test]
+    fn test_queue_url_region() {
+        assert_eq!(
+            queue_url_region("https://sqs.eu-west-1.amazonaws.com/123456789012/orders").as_deref(),
+            Some("eu-west-1")
+        );
+        assert_eq!(
+            queue_url_region("https://sqs.cn-north-1.amazonaws.com.cn/123456789012/orders")
+                .as_deref(),
+            Some("cn-north-1")
+        );
+        assert_eq!(
+            queue_url_region("https://queue.amazonaws.com/123456789012/orders"),
+            None
+        );
+        assert_eq!(queue_url_region("http://localhost:4566/000000000000/orders"), None);
+        assert_eq!(queue_url_region("orders"), None);
+    }
+
+    /// binding a queue url from another region is denied with the mismatch
+    /// named, instead of failing later with a signature error
+    #[tokio::test]
+    async fn test_queue_url_region_mismatch() {
+        std::env::set_var("AWS_REGION", "us-east-1");
+        let config = SQSConfig {
+            aws_region: Some(String::from("us-east-1")),
+            ..Default::default()
+        };
+        let client = SqsMessagingProvider::build_client(&config).await.unwrap();
+
+        let err = SqsMessagingProvider::resolve_queue(
+            &client,
+            &config,
+            "https://sqs.eu-west-1.amazonaws.com/123456789012/orders",
+        )
+        .await
+        .unwrap_err();
+        assert!(matches!(err, RpcError::InvalidParameter(_)));
+        assert!(err.to_string().contains("eu-west-1"));
+        assert!(err.to_string().contains("us-east-1"));
+
+        // matching and region-less urls pass straight through
+        let url = "https://sqs.us-east-1.amazonaws.com/123456789012/orders";
+        let resolved = SqsMessagingProvider::resolve_queue(&client, &config, url)
+            .await
+            .unwrap();
+        assert_eq!(resolved.as_deref(), Some(url));
+        let local = "http://localhost:4566/000000000000/orders";
+        let resolved = SqsMessagingProvider::resolve_queue(&client, &config, local)
+            .await
+            .unwrap();
+        assert_eq!(resolved.as_deref(), Some(local));
+    }
+
+    /// an explicit queue_url links successfully against an unreachable
+    /// endpoint: every sqs call would fail, so success proves neither
+    /// get_queue_url nor list_queues was invoked
+    #[tokio::test]
+    async fn test_queue_url_override_skips_resolution() {
+        let prov = SqsMessagingProvider::default();
+        let url = "https://sqs.us-east-1.amazonaws.com/123456789012/orders";
+        let ld = crate::config::test::link_with_values(&[
+            ("queue_url", url),
+            ("aws_region", "us-east-1"),
+            ("access_key_id", "AKIDEXAMPLE"),
+            ("secret_access_key", "secret"),
+            ("endpoint_url", "http://127.0.0.1:1"),
+        ]);
+        assert!(prov.put_link(&ld).await.unwrap());
+        let ctx = Context {
+            actor: Some(String::from("test-actor")),
+            ..Default::default()
+        };
+        let bundle = prov.bundle_for_actor(&ctx).await.unwrap();
+        assert_eq!(bundle.queue_url, url);
+        prov.delete_link("test-actor").await;
+    }
+
+    /// queue identifiers come in three shapes: bare names resolve later, full
+    /// urls pass through, and arns are rewritten to the matching url
+    #[test]
+    fn test_queue_url_from_identifier() {
+        assert_eq!(queue_url_from_identifier("my-queue").unwrap(), None);
+        assert_eq!(
+            queue_url_from_identifier("https://sqs.us-east-1.amazonaws.com/123456789012/my-queue")
+                .unwrap()
+                .as_deref(),
+            Some("https://sqs.us-east-1.amazonaws.com/123456789012/my-queue")
+        );
+        assert_eq!(
+            queue_url_from_identifier("arn:aws:sqs:us-east-1:123456789012:my-queue")
+                .unwrap()
+                .as_deref(),
+            Some("https://sqs.us-east-1.amazonaws.com/123456789012/my-queue")
+        );
+        // a cn-partition arn resolves to the china domain
+        assert_eq!(
+            queue_url_from_identifier("arn:aws-cn:sqs:cn-north-1:123456789012:my-queue")
+                .unwrap()
+                .as_deref(),
+            Some("https://sqs.cn-north-1.amazonaws.com.cn/123456789012/my-queue")
+        );
+        // an arn for another service, or one missing pieces, is rejected
+        assert!(queue_url_from_identifier("arn:aws:sns:us-east-1:123456789012:topic").is_err());
+        assert!(queue_url_from_identifier("arn:aws:sqs:::").is_err());
+    }
+
+    /// per-message delays are consumed from the attributes, clamped to the
+    /// sqs range, and rejected outright on fifo queues
+    #[test]
+    fn test_delay_from_attributes() {
+        let mut attributes =
+            HashMap::from([(String::from("delay_seconds"), String::from("30"))]);
+        assert_eq!(
+            delay_from_attributes(&mut attributes, false).unwrap(),
+            Some(30)
+        );
+        assert!(attributes.is_empty());
+
+        let mut attributes =
+            HashMap::from([(String::from("delay_seconds"), String::from("1200"))]);
+        assert_eq!(
+            delay_from_attributes(&mut attributes, false).unwrap(),
+            Some(900)
+        );
+
+        let mut attributes =
+            HashMap::from([(String::from("delay_seconds"), String::from("30"))]);
+        assert!(delay_from_attributes(&mut attributes, true).is_err());
+
+        let mut attributes =
+            HashMap::from([(String::from("delay_seconds"), String::from("soon"))]);
+        assert!(delay_from_attributes(&mut attributes, false).is_err());
+
+        assert_eq!(delay_from_attributes(&mut HashMap::new(), false).unwrap(), None);
+    }
+
+    #[test]
+    fn test_initial_visibility_redirects_to_delay() {
+        let mut attributes = HashMap::new();
+        attributes.insert(INITIAL_VISIBILITY_ATTRIBUTE.to_string(), "30".to_string());
+        let error = reject_initial_visibility(&attributes).expect_err("must be rejected");
+        let text = error.to_string();
+        assert!(text.contains("receipt handle"), "{}", text);
+        assert!(text.contains("delay_seconds"), "{}", text);
+        // publishes without the attribute are untouched
+        let mut attributes = HashMap::new();
+        attributes.insert("tenant".to_string(), "acme".to_string());
+        assert!(reject_initial_visibility(&attributes).is_ok());
+    }
+
+    /// the redrive policy json names the dlq arn and stringly receive count
+    #[test]
+    fn test_redrive_policy_json() {
+        let policy = redrive_policy("arn:aws:sqs:us-east-1:123:orders-dlq", 3);
+        let parsed: serde_json::Value = serde_json::from_str(&policy).unwrap();
+        assert_eq!(
+            parsed["deadLetterTargetArn"],
+            "arn:aws:sqs:us-east-1:123:orders-dlq"
+        );
+        assert_eq!(parsed["maxReceiveCount"], "3");
+    }
+
+    /// a link bound to several subscribe queues runs one receive loop per
+    /// queue, and unlinking stops all of them
+    #[tokio::test]
+    async fn test_unlink_stops_all_receive_loops() {
+        let prov = SqsMessagingProvider::default();
+        let mut bundle = test_bundle("q").await;
+        bundle.subscribe_queues = vec![
+            (String::from("orders"), String::from("orders-url")),
+            (String::from("audit"), String::from("audit-url")),
+        ];
+        let cancel = bundle.cancel.clone();
+        bundle.poll_handles = (0..2)
+            .map(|_| {
+                let token = cancel.clone();
+                std::sync::Arc::new(tokio::spawn(async move {
+                    token.cancelled().await;
+                }))
+            })
+            .collect();
+        let handles: Vec<_> = bundle.poll_handles.clone();
+        prov.actors
+            .write()
+            .await
+            .insert(String::from("actor-multi"), bundle);
+
+        prov.delete_link("actor-multi").await;
+        for handle in handles {
+            for _ in 0..100 {
+                if handle.is_finished() {
+                    break;
+                }
+                tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+            }
+            assert!(handle.is_finished(), "every receive loop should exit");
+        }
+    }
+
+    /// Shutdown cancels and joins every receive loop
+    #[tokio::test]
+    async fn test_shutdown_joins_receive_loops() {
+        let prov = SqsMessagingProvider::default();
+        for actor in ["actor-a", "actor-b"] {
+            let mut bundle = test_bundle("q").await;
+            let token = bundle.cancel.clone();
+            bundle.poll_handles = vec![std::sync::Arc::new(tokio::spawn(async move {
+                token.cancelled().await;
+            }))];
+            prov.actors
+                .write()
+                .await
+                .insert(String::from(actor), bundle);
+        }
+
+        prov.shutdown().await.unwrap();
+        assert!(prov.actors.read().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_client_for_actor_requires_link() {
+        let prov = SqsMessagingProvider::default();
+
+        // context without an actor
+        let err = prov
+            .bundle_for_actor(&Context::default())
+            .await
+            .expect_err("no actor in context should fail");
+        assert!(err.to_string().contains("no actor"));
+
+        // actor that was never linked
+        let ctx = Context {
+            actor: Some(String::from("unlinked")),
+            ..Default::default()
+        };
+        let err = prov
+            .bundle_for_actor(&ctx)
+            .await
+            .expect_err("unlinked actor should fail");
+        assert!(err.to_string().contains("not linked"));
+    }
+
+    fn message_with_encoding(body: &str, encoding: Option<&str>) -> Message {
+        let mut builder = Message::builder().body(body);
+        if let Some(encoding) = encoding {
+            builder = builder.message_attributes(
+                ENCODING_ATTRIBUTE,
+                MessageAttributeValue::builder()
+                    .data_type("String")
+                    .string_value(encoding)
+                    .build(),
+            );
+        }
+        builder.build()
+    }
+
+    #[test]
+    fn test_body_round_trip_utf8() {
+        let payload = "hello sqs".as_bytes();
+        let (body, encoding) = encode_body(payload, BodyEncoding::Auto).unwrap();
+        assert_eq!(body, "hello sqs");
+        assert_eq!(encoding, ENCODING_UTF8);
+        let message = message_with_encoding(&body, Some(encoding));
+        assert_eq!(decode_body(&message).unwrap(), payload);
+    }
+
+    #[test]
+    fn test_body_round_trip_binary() {
+        let payload: &[u8] = &[0xde, 0xad, 0xbe, 0xef, 0xff];
+        let (body, encoding) = encode_body(payload, BodyEncoding::Auto).unwrap();
+        assert_eq!(encoding, ENCODING_BASE64);
+        let message = message_with_encoding(&body, Some(encoding));
+        assert_eq!(decode_body(&message).unwrap(), payload);
+    }
+
+    /// a short operation timeout turns an unreachable endpoint into a quick
+    /// error instead of a hang
+    #[tokio::test]
+    async fn test_short_operation_timeout_fails_fast() {
+        std::env::set_var("AWS_REGION", "us-east-1");
+        std::env::set_var("AWS_ACCESS_KEY_ID", "test");
+        std::env::set_var("AWS_SECRET_ACCESS_KEY", "test");
+        let config = SQSConfig {
+            // a blackhole address: packets go nowhere, so only the timeout
+            // gets the call back
+            endpoint_url: Some(String::from("http://10.255.255.1:9")),
+            connect_timeout_ms: Some(100),
+            operation_timeout_ms: Some(300),
+            max_attempts: Some(1),
+            ..Default::default()
+        };
+        let client = SqsMessagingProvider::build_client(&config).await.unwrap();
+
+        let started = std::time::Instant::now();
+        let result = client.get_queue_url().queue_name("q").send().await;
+        assert!(result.is_err());
+        assert!(
+            started.elapsed() < Duration::from_secs(5),
+            "timed out too slowly: {:?}",
+            started.elapsed()
+        );
+    }
+
+    /// an actor whose queue endpoint is unreachable turns the provider's
+    /// health degraded, and the verdict is served from cache until the ttl
+    #[tokio::test]
+    async fn test_health_degraded_on_unreachable_endpoint() {
+        std::env::set_var("AWS_REGION", "us-east-1");
+        std::env::set_var("AWS_ACCESS_KEY_ID", "test");
+        std::env::set_var("AWS_SECRET_ACCESS_KEY", "test");
+        let config = SQSConfig {
+            // nothing listens here, so the probe fails immediately
+            endpoint_url: Some(String::from("http://127.0.0.1:1")),
+            max_attempts: Some(1),
+            ..Default::default()
+        };
+        let client = SqsMessagingProvider::build_client(&config).await.unwrap();
+        let mut bundle = test_bundle("http://127.0.0.1:1/123/q").await;
+        bundle.client = client;
+
+        let prov = SqsMessagingProvider::default();
+        prov.actors
+            .write()
+            .await
+            .insert(String::from("actor-health"), bundle);
+
+        let response = prov
+            .health_request(&wasmbus_rpc::core::HealthCheckRequest {})
+            .await
+            .unwrap();
+        assert!(!response.healthy);
+        assert!(response.message.unwrap().contains("actor-health"));
+
+        // the degraded verdict is cached: removing the actor doesn't flip
+        // health back until the ttl expires
+        prov.actors.write().await.clear();
+        let cached = prov
+            .health_request(&wasmbus_rpc::core::HealthCheckRequest {})
+            .await
+            .unwrap();
+        assert!(!cached.healthy);
+    }
+
+    /// without actor context the provider only proceeds when the operator
+    /// explicitly configured a fallback actor, and it warns every time
+    #[tracing_test::traced_test]
+    #[tokio::test]
+    async fn test_default_actor_fallback_warns() {
+        let prov = SqsMessagingProvider::default();
+        prov.actors
+            .write()
+            .await
+            .insert(String::from("fallback-actor-66"), test_bundle("q").await);
+
+        std::env::set_var("SQS_DEFAULT_ACTOR", "fallback-actor-66");
+        let bundle = prov.bundle_for_actor(&Context::default()).await;
+        std::env::remove_var("SQS_DEFAULT_ACTOR");
+
+        assert_eq!(bundle.unwrap().queue_url, "q");
+        assert!(logs_contain("falling back to SQS_DEFAULT_ACTOR"));
+    }
+
+    /// with no linked actors there is nothing to probe and the provider
+    /// reports healthy
+    #[tokio::test]
+    async fn test_health_ok_with_no_links() {
+        let prov = SqsMessagingProvider::default();
+        let response = prov
+            .health_request(&wasmbus_rpc::core::HealthCheckRequest {})
+            .await
+            .unwrap();
+        assert!(response.healthy);
+        assert_eq!(response.message, None);
+    }
+
+    /// a handler finishing inside the drain window is allowed to complete;
+    /// shutdown only force-cancels what outlives the timeout
+    #[tokio::test]
+    async fn test_shutdown_drains_in_flight_work() {
+        let finished = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let prov = SqsMessagingProvider::default();
+        let mut bundle = test_bundle("http://127.0.0.1:1/q").await;
+        bundle.poll_handles = vec![std::sync::Arc::new(tokio::spawn({
+            let finished = finished.clone();
+            async move {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+                finished.store(true, std::sync::atomic::Ordering::SeqCst);
+            }
+        }))];
+        prov.actors
+            .write()
+            .await
+            .insert(String::from("actor"), bundle);
+
+        prov.shutdown().await.unwrap();
+        assert!(
+            finished.load(std::sync::atomic::Ordering::SeqCst),
+            "in-flight work was cancelled before the drain timeout"
+        );
+        assert!(prov.actors.read().await.is_empty());
+    }
+
+    /// a retried fifo receive reuses the cached attempt id; only a successful
+    /// receive (which clears the cache) leads to a fresh one
+    #[test]
+    fn test_next_attempt_id_stable_across_retries() {
+        let mut cache = None;
+        let first = next_attempt_id(&mut cache);
+        let retried = next_attempt_id(&mut cache);
+        assert_eq!(first, retried);
+
+        cache = None;
+        let fresh = next_attempt_id(&mut cache);
+        assert_ne!(first, fresh);
+    }
+
+    /// the depth counters come straight off the queue attributes, with
+    /// anything missing reported as zero instead of an error
+    #[test]
+    fn test_depth_from_attributes() {
+        use aws_sdk_sqs::model::QueueAttributeName;
+
+        let attributes = HashMap::from([
+            (QueueAttributeName::ApproximateNumberOfMessages, String::from("41")),
+            (
+                QueueAttributeName::ApproximateNumberOfMessagesNotVisible,
+                String::from("2"),
+            ),
+        ]);
+        let depth = depth_from_attributes(Some(&attributes));
+        assert_eq!(depth.messages, 41);
+        assert_eq!(depth.not_visible, 2);
+        assert_eq!(depth.delayed, 0);
+
+        let depth = depth_from_attributes(None);
+        assert_eq!((depth.messages, depth.not_visible, depth.delayed), (0, 0, 0));
+    }
+
+    /// the purge control subject is rejected outright unless the link opted
+    /// in, and even then fails loudly (rather than silently) when sqs does
+    #[tokio::test]
+    async fn test_purge_gated_by_allow_purge() {
+        let bundle = test_bundle("http://127.0.0.1:1/q").await;
+        let denied = bundle.purge_queue().await;
+        assert!(matches!(denied, Err(RpcError::InvalidParameter(_))));
+
+        let mut bundle = test_bundle("http://127.0.0.1:1/q").await;
+        bundle.config.allow_purge = true;
+        // the flag check passes; the unreachable endpoint turns the actual
+        // purge into a send failure
+        let attempted = bundle.purge_queue().await;
+        assert!(matches!(attempted, Err(RpcError::Other(_))));
+    }
+
+    #[test]
+    fn test_body_encoding_modes() {
+        let binary: &[u8] = &[0xde, 0xad, 0xbe, 0xef, 0xff];
+        let text = "hello sqs".as_bytes();
+
+        // utf8 passes text through and rejects binary outright
+        let (body, encoding) = encode_body(text, BodyEncoding::Utf8).unwrap();
+        assert_eq!((body.as_str(), encoding), ("hello sqs", ENCODING_UTF8));
+        assert!(encode_body(binary, BodyEncoding::Utf8).is_err());
+
+        // base64 always encodes, even valid utf-8, and round-trips
+        let (body, encoding) = encode_body(text, BodyEncoding::Base64).unwrap();
+        assert_eq!(encoding, ENCODING_BASE64);
+        let message = message_with_encoding(&body, Some(encoding));
+        assert_eq!(decode_body(&message).unwrap(), text);
+
+        let (body, encoding) = encode_body(binary, BodyEncoding::Base64).unwrap();
+        let message = message_with_encoding(&body, Some(encoding));
+        assert_eq!(decode_body(&message).unwrap(), binary);
+    }
+
+    #[test]
+    fn test_decode_body_unmarked_is_plain_text() {
+        // messages published by something other than this provider carry no
+        // encoding attribute and pass through unchanged
+        let message = message_with_encoding("plain", None);
+        assert_eq!(decode_body(&message).unwrap(), b"plain");
+    }
+
+    /// Attributes set through the envelope survive publish and receive
+    #[test]
+    fn test_attribute_round_trip() {
+        let published = br#"{"attributes":{"content-type":"application/json","trace-id":"abc123"},"body":"payload"}"#;
+        let (payload, attributes, _) = unwrap_envelope(published);
+        assert_eq!(payload, b"payload");
+        assert_eq!(attributes.len(), 2);
+        assert_eq!(attributes.get("trace-id").map(String::as_str), Some("abc123"));
+
+        // simulate the message coming back with the same attributes
+        let mut builder = Message::builder().body("payload").message_attributes(
+            ENCODING_ATTRIBUTE,
+            MessageAttributeValue::builder()
+                .data_type("String")
+                .string_value(ENCODING_UTF8)
+                .build(),
+        );
+        for (name, value) in &attributes {
+            builder = builder.message_attributes(
+                name,
+                MessageAttributeValue::builder()
+                    .data_type("String")
+                    .string_value(value)
+                    .build(),
+            );
+        }
+        let message = builder.build();
+
+        let collected = collect_attributes(&message);
+        assert_eq!(collected, attributes, "encoding marker must be filtered out");
+
+        let delivered = wrap_attributes(decode_body(&message).unwrap(), collected, Vec::new());
+        let (payload, attributes_back, _) = unwrap_envelope(&delivered);
+        assert_eq!(payload, b"payload");
+        assert_eq!(attributes_back, attributes);
+    }
+
+    #[test]
+    fn test_unwrap_envelope_passthrough() {
+        // non-envelope bodies are sent untouched with no attributes
+        let (payload, attributes, typed) = unwrap_envelope(b"just a plain body");
+        assert_eq!(payload, b"just a plain body");
+        assert!(attributes.is_empty());
+        assert!(typed.is_empty());
+    }
+
+    /// Number and Binary attributes keep their declared type across the
+    /// receive envelope and back out through a publish
+    #[test]
+    fn test_typed_attributes_round_trip() {
+        let message = Message::builder()
+            .body("hello")
+            .message_attributes(
+                "note",
+                MessageAttributeValue::builder()
+                    .data_type("String")
+                    .string_value("plain")
+                    .build(),
+            )
+            .message_attributes(
+                "retries",
+                MessageAttributeValue::builder()
+                    .data_type("Number")
+                    .string_value("3")
+                    .build(),
+            )
+            .message_attributes(
+                "checksum",
+                MessageAttributeValue::builder()
+                    .data_type("Binary")
+                    .binary_value(aws_sdk_sqs::types::Blob::new(vec![0xde, 0xad]))
+                    .build(),
+            )
+            .build();
+
+        // strings stay in the plain map; the others keep their types
+        let attributes = crate::collect_attributes(&message);
+        assert_eq!(attributes.len(), 1);
+        assert_eq!(attributes["note"], "plain");
+        let mut typed = collect_typed_attributes(&message);
+        typed.sort_by(|a, b| a.name.cmp(&b.name));
+        assert_eq!(typed.len(), 2);
+        assert_eq!(typed[0].name, "checksum");
+        assert_eq!(typed[0].data_type, "Binary");
+        assert_eq!(typed[0].binary_value.as_deref(), Some("3q0="));
+        assert_eq!(typed[1].name, "retries");
+        assert_eq!(typed[1].data_type, "Number");
+        assert_eq!(typed[1].value.as_deref(), Some("3"));
+
+        // through the envelope and back out as sqs attribute values
+        let wrapped = wrap_attributes(b"hello".to_vec(), attributes, typed.clone());
+        let (payload, _, typed_back) = unwrap_envelope(&wrapped);
+        assert_eq!(payload, b"hello");
+        assert_eq!(typed_back, typed);
+        let number = typed_attribute_value(&typed_back[1]).unwrap();
+        assert_eq!(number.data_type(), Some("Number"));
+        assert_eq!(number.string_value(), Some("3"));
+        let binary = typed_attribute_value(&typed_back[0]).unwrap();
+        assert_eq!(binary.data_type(), Some("Binary"));
+        assert_eq!(binary.binary_value().unwrap().as_ref(), &[0xde, 0xad]);
+    }
+
+    /// half-specified typed attributes are caller errors, not silent sends
+    #[test]
+    fn test_typed_attribute_validation() {
+        let missing = TypedAttribute {
+            name: String::from("checksum"),
+            data_type: String::from("Binary"),
+            value: None,
+            binary_value: None,
+        };
+        assert!(typed_attribute_value(&missing).is_err());
+        let bad_base64 = TypedAttribute {
+            binary_value: Some(String::from("not base64 !!")),
+            ..missing.clone()
+        };
+        assert!(typed_attribute_value(&bad_base64).is_err());
+        let no_value = TypedAttribute {
+            name: String::from("retries"),
+            data_type: String::from("Number"),
+            value: None,
+            binary_value: None,
+        };
+        assert!(typed_attribute_value(&no_value).is_err());
+    }
+
+    #[test]
+    fn test_wrap_attributes_binary_drops_attributes() {
+        let body: Vec<u8> = vec![0xde, 0xad, 0xbe, 0xef];
+        let attributes =
+            HashMap::from([(String::from("content-type"), String::from("bytes"))]);
+        assert_eq!(wrap_attributes(body.clone(), attributes, Vec::new()), body);
+    }
+
+    #[test]
+    fn test_fifo_detection_and_ids() {
+        assert!(is_fifo("https://sqs.us-east-1.amazonaws.com/123/orders.fifo"));
+        assert!(!is_fifo("https://sqs.us-east-1.amazonaws.com/123/orders"));
+
+        // group id and dedup id are consumed rather than forwarded as attributes
+        let mut attributes = HashMap::from([
+            (String::from("message_group_id"), String::from("group-1")),
+            (String::from("message_deduplication_id"), String::from("d-1")),
+            (String::from("content-type"), String::from("text/plain")),
+        ]);
+        let (group_id, dedup_id) = fifo_ids(&mut attributes, false, None, None).unwrap();
+        assert_eq!(group_id, "group-1");
+        assert_eq!(dedup_id.as_deref(), Some("d-1"));
+        assert_eq!(attributes.len(), 1);
+
+        // missing group id is always an error
+        let mut attributes = HashMap::new();
+        assert!(fifo_ids(&mut attributes, true, None, None).is_err());
+
+        // missing dedup id is only allowed with content-based deduplication
+        let mut attributes =
+            HashMap::from([(String::from("message_group_id"), String::from("group-1"))]);
+        assert!(fifo_ids(&mut attributes.clone(), false, None, None).is_err());
+        let (_, dedup_id) = fifo_ids(&mut attributes, true, None, None).unwrap();
+        assert!(dedup_id.is_none());
+    }
+
+    #[test]
+    fn test_group_id_strategies() {
+        let mut config = SQSConfig::default();
+
+        // attribute strategy only offers the configured default
+        assert_eq!(derive_group_id(&config, "orders", b"body"), None);
+        config.default_message_group_id = Some(String::from("default-group"));
+        assert_eq!(
+            derive_group_id(&config, "orders", b"body").as_deref(),
+            Some("default-group")
+        );
+
+        config.group_id_from = GroupIdStrategy::Subject;
+        assert_eq!(
+            derive_group_id(&config, "orders", b"body").as_deref(),
+            Some("orders")
+        );
+        assert_eq!(derive_group_id(&config, "", b"body"), None);
+
+        config.group_id_from = GroupIdStrategy::Static;
+        assert_eq!(
+            derive_group_id(&config, "orders", b"body").as_deref(),
+            Some("default-group")
+        );
+
+        config.group_id_from = GroupIdStrategy::HashOfBody;
+        let hashed = derive_group_id(&config, "orders", b"body").unwrap();
+        assert_eq!(hashed.len(), 16);
+        assert_eq!(derive_group_id(&config, "other", b"body").unwrap(), hashed);
+        assert_ne!(derive_group_id(&config, "orders", b"else").unwrap(), hashed);
+
+        // an explicit attribute always wins over the derived id, and a derived
+        // id must satisfy the sqs group id limits
+        let mut attributes =
+            HashMap::from([(String::from("message_group_id"), String::from("explicit"))]);
+        let (group_id, _) = fifo_ids(&mut attributes, true, Some(String::from("derived")), None).unwrap();
+        assert_eq!(group_id, "explicit");
+        let (group_id, _) =
+            fifo_ids(&mut HashMap::new(), true, Some(String::from("derived")), None).unwrap();
+        assert_eq!(group_id, "derived");
+        assert!(fifo_ids(&mut HashMap::new(), true, Some(String::from("bad id")), None).is_err());
+        assert!(fifo_ids(&mut HashMap::new(), true, Some("x".repeat(129)), None).is_err());
+    }
+
+    #[test]
+    fn test_decode_body_corrupt_base64() {
+        let message = message_with_encoding("!!not-base64!!", Some(ENCODING_BASE64));
+        assert!(decode_body(&message).is_err());
+    }
+
+}